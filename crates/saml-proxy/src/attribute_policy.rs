@@ -0,0 +1,392 @@
+use crate::attributes::{ProxiedAttribute, URI_NAME_FORMAT, resolve_name};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// NameFormat for attributes released under a plain name such as `email`.
+pub const BASIC_NAME_FORMAT: &str = "urn:oasis:names:tc:SAML:2.0:attrname-format:basic";
+
+/// Decides which of the university's attributes each Service Provider
+/// receives, and under what names. Loaded from a JSON document:
+///
+/// ```json
+/// {
+///   "default": [{ "source": "eduPersonPrincipalName" }],
+///   "service_providers": {
+///     "https://sso.example.org/realms/students": [
+///       { "source": "mail", "name": "email", "transform": ["lowercase"] },
+///       { "source": "eduPersonPrincipalName", "name": "school_domain", "transform": ["scope"] }
+///     ]
+///   }
+/// }
+/// ```
+///
+/// Each rule releases one attribute: the SP receives exactly the attributes
+/// its rules produce. Renaming, value transformation and derived attributes
+/// are all rules; the same source may feed several of them. SPs without an
+/// entry use `default`, and without that receive every attribute unchanged.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttributePolicy {
+    #[serde(default)]
+    default: Option<Vec<ReleaseRule>>,
+    #[serde(default)]
+    service_providers: HashMap<String, Vec<ReleaseRule>>,
+}
+
+/// Releases the values of one incoming attribute.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseRule {
+    /// Incoming attribute, by name or, for well-known attributes, friendly
+    /// name (e.g. `mail`).
+    pub source: String,
+    /// Name to release the attribute under, instead of the source's OID.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub friendly_name: Option<String>,
+    /// Applied to each value in order. Values a transform drops, and
+    /// duplicates left after transforming, are not released.
+    #[serde(default)]
+    pub transform: Vec<Transform>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    Lowercase,
+    /// `student@example.edu` becomes `student`; unscoped values are kept.
+    StripScope,
+    /// `alice@cs.example.edu` becomes `cs.example.edu`; unscoped values are
+    /// dropped.
+    Scope,
+}
+
+impl Transform {
+    fn apply(self, value: String) -> Option<String> {
+        match self {
+            Self::Lowercase => Some(value.to_lowercase()),
+            Self::StripScope => Some(match value.rsplit_once('@') {
+                Some((unscoped, _)) => unscoped.to_string(),
+                None => value,
+            }),
+            Self::Scope => value
+                .rsplit_once('@')
+                .map(|(_, scope)| scope.to_string())
+                .filter(|scope| !scope.is_empty()),
+        }
+    }
+}
+
+impl AttributePolicy {
+    pub fn load(path: &str) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read attribute policy {path}"))?;
+        Self::from_json(&json).with_context(|| format!("invalid attribute policy {path}"))
+    }
+
+    /// Parses a policy, resolving every rule's source to the name it will be
+    /// looked up by. A plain source that is not a well-known friendly name is
+    /// rejected, as it is more likely a typo than a basic-named attribute.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut policy: Self = serde_json::from_str(json)?;
+        let default = policy.default.iter_mut().map(|rules| ("default", rules));
+        let per_sp = policy
+            .service_providers
+            .iter_mut()
+            .map(|(sp, rules)| (sp.as_str(), rules));
+        for (owner, rules) in default.chain(per_sp) {
+            for rule in rules {
+                rule.source = resolve_name(&rule.source)
+                    .with_context(|| format!("{owner}: unknown attribute {:?}", rule.source))?
+                    .to_string();
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Entity IDs of the SPs with rules of their own.
+    pub fn service_providers(&self) -> impl Iterator<Item = &str> {
+        self.service_providers.keys().map(String::as_str)
+    }
+
+    /// Produces the attributes to release to `sp_entity_id` from those the
+    /// university asserted, keyed by name.
+    pub fn apply(
+        &self,
+        sp_entity_id: &str,
+        attrs: BTreeMap<String, ProxiedAttribute>,
+    ) -> BTreeMap<String, ProxiedAttribute> {
+        let Some(rules) = self
+            .service_providers
+            .get(sp_entity_id)
+            .or(self.default.as_ref())
+        else {
+            return attrs;
+        };
+
+        let mut released: BTreeMap<String, ProxiedAttribute> = BTreeMap::new();
+        for rule in rules {
+            let Some(source) = attrs.get(&rule.source) else {
+                continue;
+            };
+            let (name, name_format, friendly_name) = match &rule.name {
+                Some(name) => (
+                    name.clone(),
+                    Some(name_format(name).to_string()),
+                    rule.friendly_name.clone(),
+                ),
+                None => (
+                    rule.source.clone(),
+                    source.name_format.clone(),
+                    rule.friendly_name
+                        .clone()
+                        .or_else(|| source.friendly_name.clone()),
+                ),
+            };
+            let entry = released.entry(name).or_insert_with(|| ProxiedAttribute {
+                name_format,
+                friendly_name,
+                values: Vec::new(),
+            });
+            for value in &source.values {
+                let value = rule
+                    .transform
+                    .iter()
+                    .try_fold(value.clone(), |value, transform| transform.apply(value));
+                if let Some(value) = value
+                    && !entry.values.contains(&value)
+                {
+                    entry.values.push(value);
+                }
+            }
+        }
+        released.retain(|_, attr| !attr.values.is_empty());
+        released
+    }
+}
+
+/// URI-like names (OIDs, URNs, URLs) keep the URI NameFormat; anything else
+/// is a basic name.
+fn name_format(name: &str) -> &'static str {
+    if name.contains(':') {
+        URI_NAME_FORMAT
+    } else {
+        BASIC_NAME_FORMAT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attributes::{
+        DISPLAY_NAME, EDU_PERSON_AFFILIATION, EDU_PERSON_ENTITLEMENT, EDU_PERSON_PRINCIPAL_NAME,
+        EDU_PERSON_SCOPED_AFFILIATION, MAIL,
+    };
+
+    const SP: &str = "https://sp.example.org";
+
+    fn attr(values: &[&str]) -> ProxiedAttribute {
+        ProxiedAttribute {
+            name_format: Some(URI_NAME_FORMAT.into()),
+            friendly_name: None,
+            values: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    fn asserted() -> BTreeMap<String, ProxiedAttribute> {
+        BTreeMap::from([
+            (
+                EDU_PERSON_PRINCIPAL_NAME.to_string(),
+                attr(&["Alice@CS.Example.edu"]),
+            ),
+            (
+                EDU_PERSON_SCOPED_AFFILIATION.to_string(),
+                attr(&["member@example.edu", "student@cs.example.edu"]),
+            ),
+            (MAIL.to_string(), attr(&["Alice.Smith@Example.edu"])),
+            (DISPLAY_NAME.to_string(), attr(&["Alice Smith"])),
+        ])
+    }
+
+    fn policy(rules: &str) -> AttributePolicy {
+        AttributePolicy::from_json(&format!(r#"{{"service_providers": {{"{SP}": {rules}}}}}"#))
+            .unwrap()
+    }
+
+    fn values<'a>(attrs: &'a BTreeMap<String, ProxiedAttribute>, name: &str) -> &'a [String] {
+        &attrs[name].values
+    }
+
+    #[test]
+    fn empty_policy_releases_everything() {
+        let policy = AttributePolicy::from_json("{}").unwrap();
+        assert_eq!(policy.apply(SP, asserted()), asserted());
+    }
+
+    #[test]
+    fn releases_only_listed_attributes() {
+        let policy =
+            policy(r#"[{"source": "mail"}, {"source": "urn:oid:2.16.840.1.113730.3.1.241"}]"#);
+        let released = policy.apply(SP, asserted());
+        assert_eq!(released.keys().collect::<Vec<_>>(), [MAIL, DISPLAY_NAME]);
+        assert_eq!(released[MAIL], asserted()[MAIL]);
+    }
+
+    #[test]
+    fn unlisted_sp_uses_default() {
+        let policy = AttributePolicy::from_json(
+            r#"{
+                "default": [{"source": "eduPersonPrincipalName"}],
+                "service_providers": {"https://other.example.org": [{"source": "mail"}]}
+            }"#,
+        )
+        .unwrap();
+        let released = policy.apply(SP, asserted());
+        assert_eq!(released.len(), 1);
+        assert!(released.contains_key(EDU_PERSON_PRINCIPAL_NAME));
+
+        let released = policy.apply("https://other.example.org", asserted());
+        assert_eq!(released.len(), 1);
+        assert!(released.contains_key(MAIL));
+    }
+
+    #[test]
+    fn renames_attributes() {
+        let policy = policy(
+            r#"[
+                {"source": "mail", "name": "email"},
+                {"source": "displayName", "name": "urn:example:name", "friendly_name": "name"}
+            ]"#,
+        );
+        let released = policy.apply(SP, asserted());
+        assert_eq!(values(&released, "email"), ["Alice.Smith@Example.edu"]);
+        assert_eq!(
+            released["email"].name_format.as_deref(),
+            Some(BASIC_NAME_FORMAT)
+        );
+        assert_eq!(released["email"].friendly_name, None);
+
+        let name = &released["urn:example:name"];
+        assert_eq!(name.name_format.as_deref(), Some(URI_NAME_FORMAT));
+        assert_eq!(name.friendly_name.as_deref(), Some("name"));
+        assert!(!released.contains_key(MAIL));
+    }
+
+    #[test]
+    fn keeps_source_friendly_name_unless_renamed() {
+        let mut attrs = asserted();
+        attrs.get_mut(MAIL).unwrap().friendly_name = Some("mail".into());
+
+        let released = policy(r#"[{"source": "mail"}]"#).apply(SP, attrs.clone());
+        assert_eq!(released[MAIL].friendly_name.as_deref(), Some("mail"));
+
+        let released = policy(r#"[{"source": "mail", "name": "email"}]"#).apply(SP, attrs);
+        assert_eq!(released["email"].friendly_name, None);
+    }
+
+    #[test]
+    fn transforms_values() {
+        let policy = policy(
+            r#"[
+                {"source": "mail", "transform": ["lowercase"]},
+                {"source": "eduPersonScopedAffiliation", "name": "affiliation", "transform": ["strip_scope"]}
+            ]"#,
+        );
+        let released = policy.apply(SP, asserted());
+        assert_eq!(values(&released, MAIL), ["alice.smith@example.edu"]);
+        assert_eq!(values(&released, "affiliation"), ["member", "student"]);
+    }
+
+    #[test]
+    fn derives_attributes_from_a_source() {
+        let policy = policy(
+            r#"[
+                {"source": "eduPersonPrincipalName"},
+                {"source": "eduPersonPrincipalName", "name": "school_domain", "transform": ["scope", "lowercase"]}
+            ]"#,
+        );
+        let released = policy.apply(SP, asserted());
+        assert_eq!(
+            values(&released, EDU_PERSON_PRINCIPAL_NAME),
+            ["Alice@CS.Example.edu"]
+        );
+        assert_eq!(values(&released, "school_domain"), ["cs.example.edu"]);
+    }
+
+    #[test]
+    fn drops_values_left_empty_or_duplicated() {
+        let mut attrs = asserted();
+        attrs.insert(
+            EDU_PERSON_SCOPED_AFFILIATION.to_string(),
+            attr(&["member@example.edu", "member@cs.example.edu", "staff"]),
+        );
+        attrs.insert(EDU_PERSON_AFFILIATION.to_string(), attr(&["member"]));
+
+        let policy = policy(
+            r#"[
+                {"source": "eduPersonScopedAffiliation", "name": "affiliation", "transform": ["strip_scope"]},
+                {"source": "eduPersonAffiliation", "name": "affiliation"},
+                {"source": "eduPersonAffiliation", "name": "affiliation_scope", "transform": ["scope"]}
+            ]"#,
+        );
+        let released = policy.apply(SP, attrs);
+        // Rules releasing under the same name share its values.
+        assert_eq!(values(&released, "affiliation"), ["member", "staff"]);
+        // Unscoped values have no scope to derive.
+        assert!(!released.contains_key("affiliation_scope"));
+    }
+
+    #[test]
+    fn missing_source_is_skipped() {
+        let mut attrs = asserted();
+        attrs.remove(MAIL);
+        let released =
+            policy(r#"[{"source": "mail"}, {"source": "displayName"}]"#).apply(SP, attrs);
+        assert_eq!(released.len(), 1);
+        assert!(released.contains_key(DISPLAY_NAME));
+    }
+
+    #[test]
+    fn releases_attributes_outside_the_well_known_set() {
+        let mut attrs = asserted();
+        attrs.insert(
+            EDU_PERSON_ENTITLEMENT.to_string(),
+            attr(&["urn:mace:dir:entitlement:common-lib-terms"]),
+        );
+        attrs.insert("urn:oid:9.9.9.9.9".to_string(), attr(&["custom"]));
+
+        let released =
+            policy(r#"[{"source": "eduPersonEntitlement"}, {"source": "urn:oid:9.9.9.9.9"}]"#)
+                .apply(SP, attrs.clone());
+        assert_eq!(
+            released.keys().collect::<Vec<_>>(),
+            [EDU_PERSON_ENTITLEMENT, "urn:oid:9.9.9.9.9"]
+        );
+        assert_eq!(released["urn:oid:9.9.9.9.9"], attrs["urn:oid:9.9.9.9.9"]);
+
+        // Attributes no rule names stay with the university.
+        let released = policy(r#"[{"source": "mail"}]"#).apply(SP, attrs);
+        assert!(!released.contains_key(EDU_PERSON_ENTITLEMENT));
+    }
+
+    #[test]
+    fn rejects_unknown_attributes_and_fields() {
+        assert!(
+            AttributePolicy::from_json(r#"{"service_providers": {"x": [{"source": "Mail"}]}}"#)
+                .is_err()
+        );
+        assert!(
+            AttributePolicy::from_json(r#"{"default": [{"source": "mail", "rename": "email"}]}"#)
+                .is_err()
+        );
+        assert!(
+            AttributePolicy::from_json(
+                r#"{"default": [{"source": "mail", "transform": ["uppercase"]}]}"#
+            )
+            .is_err()
+        );
+        assert!(AttributePolicy::from_json(r#"{"sps": {}}"#).is_err());
+    }
+}
@@ -1,5 +1,6 @@
-use samael::schema::Assertion;
-use std::collections::HashMap;
+use samael::attribute::{Attribute, AttributeValue};
+use samael::schema::{Assertion, AttributeStatement};
+use std::collections::BTreeMap;
 
 // OIDs from the eduPerson (202208) specification:
 // https://wiki.refeds.org/display/STAN/eduPerson+(202208)+v4.4.0
@@ -10,43 +11,123 @@ pub const MAIL: &str = "urn:oid:0.9.2342.19200300.100.1.3";
 pub const DISPLAY_NAME: &str = "urn:oid:2.16.840.1.113730.3.1.241";
 pub const GIVEN_NAME: &str = "urn:oid:2.5.4.42";
 pub const SURNAME: &str = "urn:oid:2.5.4.4";
+pub const EDU_PERSON_ENTITLEMENT: &str = "urn:oid:1.3.6.1.4.1.5923.1.1.1.7";
+pub const EDU_PERSON_ASSURANCE: &str = "urn:oid:1.3.6.1.4.1.5923.1.1.1.11";
 
-const KNOWN_ATTRIBUTES: &[&str] = &[
-    EDU_PERSON_PRINCIPAL_NAME,
-    EDU_PERSON_SCOPED_AFFILIATION,
-    EDU_PERSON_AFFILIATION,
-    MAIL,
-    DISPLAY_NAME,
-    GIVEN_NAME,
-    SURNAME,
+// From the SAML V2.0 Subject Identifier Attributes Profile.
+pub const SUBJECT_ID: &str = "urn:oasis:names:tc:SAML:attribute:subject-id";
+pub const PAIRWISE_ID: &str = "urn:oasis:names:tc:SAML:attribute:pairwise-id";
+
+/// Well-known attributes with their standard friendly names, which release
+/// rules may use in place of the name.
+const KNOWN_ATTRIBUTES: &[(&str, &str)] = &[
+    (EDU_PERSON_PRINCIPAL_NAME, "eduPersonPrincipalName"),
+    (EDU_PERSON_SCOPED_AFFILIATION, "eduPersonScopedAffiliation"),
+    (EDU_PERSON_AFFILIATION, "eduPersonAffiliation"),
+    (MAIL, "mail"),
+    (DISPLAY_NAME, "displayName"),
+    (GIVEN_NAME, "givenName"),
+    (SURNAME, "sn"),
+    (EDU_PERSON_ENTITLEMENT, "eduPersonEntitlement"),
+    (EDU_PERSON_ASSURANCE, "eduPersonAssurance"),
+    (SUBJECT_ID, "subject-id"),
+    (PAIRWISE_ID, "pairwise-id"),
 ];
 
-// Extracts known eduPerson attributes from a SAML Assertion's attribute
-/// statements, returning a map of OID -> value for recognized attributes.
-pub fn extract_attributes(assertion: &Assertion) -> HashMap<String, String> {
-    let mut attrs = HashMap::new();
+/// Resolves an attribute to the name it is asserted under. Friendly names of
+/// well-known attributes resolve to their URI; any other URI, such as an OID
+/// outside the table, is taken as is.
+pub fn resolve_name(name: &str) -> Option<&str> {
+    KNOWN_ATTRIBUTES
+        .iter()
+        .find(|(oid, friendly)| *oid == name || *friendly == name)
+        .map(|(oid, _)| *oid)
+        .or_else(|| name.contains(':').then_some(name))
+}
+
+/// NameFormat for attributes named by URI, which every well-known attribute
+/// is.
+pub const URI_NAME_FORMAT: &str = "urn:oasis:names:tc:SAML:2.0:attrname-format:uri";
+
+/// An attribute asserted by the upstream IdP, with every value it carried.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProxiedAttribute {
+    pub name_format: Option<String>,
+    pub friendly_name: Option<String>,
+    pub values: Vec<String>,
+}
+
+/// Extracts every attribute from a SAML Assertion's attribute statements,
+/// keyed by name. All values are kept, in order; an attribute split across
+/// several statements is merged, dropping repeated values. Which of them an
+/// SP receives is up to the attribute policy.
+pub fn extract_attributes(assertion: &Assertion) -> BTreeMap<String, ProxiedAttribute> {
+    let mut attrs: BTreeMap<String, ProxiedAttribute> = BTreeMap::new();
     let Some(stmts) = &assertion.attribute_statements else {
         return attrs;
     };
     for stmt in stmts {
         for attr in &stmt.attributes {
             let Some(name) = &attr.name else { continue };
-            if !KNOWN_ATTRIBUTES.contains(&name.as_str()) {
+            let values = attr.values.iter().filter_map(|v| v.value.as_ref());
+            let entry = attrs.entry(name.clone()).or_default();
+            for value in values {
+                if !entry.values.contains(value) {
+                    entry.values.push(value.clone());
+                }
+            }
+            if entry.values.is_empty() {
+                attrs.remove(name);
                 continue;
             }
-            if let Some(val) = attr.values.first().and_then(|v| v.value.as_ref()) {
-                attrs.insert(name.clone(), val.clone());
+            if entry.name_format.is_none() {
+                entry.name_format.clone_from(&attr.name_format);
+            }
+            if entry.friendly_name.is_none() {
+                entry.friendly_name.clone_from(&attr.friendly_name);
             }
         }
     }
     attrs
 }
 
+/// Builds the attribute statement for the proxied assertion, with one
+/// `Attribute` per name carrying all of its values. Attributes that arrived
+/// without a NameFormat are marked as URI-named.
+pub fn attribute_statement(
+    attrs: &BTreeMap<String, ProxiedAttribute>,
+) -> Option<AttributeStatement> {
+    if attrs.is_empty() {
+        return None;
+    }
+
+    let attributes = attrs
+        .iter()
+        .map(|(name, attr)| Attribute {
+            friendly_name: attr.friendly_name.clone(),
+            name: Some(name.clone()),
+            name_format: Some(
+                attr.name_format
+                    .clone()
+                    .unwrap_or_else(|| URI_NAME_FORMAT.into()),
+            ),
+            values: attr
+                .values
+                .iter()
+                .map(|value| AttributeValue {
+                    attribute_type: None,
+                    value: Some(value.clone()),
+                })
+                .collect(),
+        })
+        .collect();
+    Some(AttributeStatement { attributes })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use samael::attribute::{Attribute, AttributeValue};
-    use samael::schema::{AttributeStatement, Issuer};
+    use samael::schema::Issuer;
 
     fn make_assertion(stmts: Option<Vec<AttributeStatement>>) -> Assertion {
         Assertion {
@@ -86,24 +167,43 @@ mod tests {
 
         let attrs = extract_attributes(&assertion);
         assert_eq!(attrs.len(), 3);
-        assert_eq!(attrs[MAIL], "user@example.edu");
-        assert_eq!(attrs[DISPLAY_NAME], "Test User");
-        assert_eq!(attrs[EDU_PERSON_PRINCIPAL_NAME], "user@example.edu");
+        assert_eq!(attrs[MAIL].values, ["user@example.edu"]);
+        assert_eq!(attrs[DISPLAY_NAME].values, ["Test User"]);
+        assert_eq!(
+            attrs[EDU_PERSON_PRINCIPAL_NAME].values,
+            ["user@example.edu"]
+        );
     }
 
     #[test]
-    fn skips_unknown_attributes() {
+    fn passes_other_attributes_through() {
         let assertion = make_assertion(Some(vec![AttributeStatement {
             attributes: vec![
                 make_attr(MAIL, "user@example.edu"),
-                make_attr("urn:oid:9.9.9.9.9", "should be skipped"),
+                make_multi_attr(
+                    EDU_PERSON_ENTITLEMENT,
+                    &["urn:mace:dir:entitlement:common-lib-terms"],
+                ),
+                Attribute {
+                    friendly_name: Some("uid".into()),
+                    name_format: Some("urn:oasis:names:tc:SAML:2.0:attrname-format:basic".into()),
+                    ..make_attr("uid", "alice")
+                },
             ],
         }]));
 
         let attrs = extract_attributes(&assertion);
-        assert_eq!(attrs.len(), 1);
-        assert!(attrs.contains_key(MAIL));
-        assert!(!attrs.contains_key("urn:oid:9.9.9.9.9"));
+        assert_eq!(attrs.len(), 3);
+        assert_eq!(
+            attrs[EDU_PERSON_ENTITLEMENT].values,
+            ["urn:mace:dir:entitlement:common-lib-terms"]
+        );
+        assert_eq!(attrs["uid"].values, ["alice"]);
+        assert_eq!(attrs["uid"].friendly_name.as_deref(), Some("uid"));
+        assert_eq!(
+            attrs["uid"].name_format.as_deref(),
+            Some("urn:oasis:names:tc:SAML:2.0:attrname-format:basic")
+        );
     }
 
     #[test]
@@ -128,27 +228,136 @@ mod tests {
         assert!(attrs.is_empty());
     }
 
+    fn make_multi_attr(name: &str, values: &[&str]) -> Attribute {
+        Attribute {
+            values: values
+                .iter()
+                .map(|v| AttributeValue {
+                    attribute_type: None,
+                    value: Some((*v).into()),
+                })
+                .collect(),
+            ..make_attr(name, "")
+        }
+    }
+
+    #[test]
+    fn keeps_every_value() {
+        let assertion = make_assertion(Some(vec![AttributeStatement {
+            attributes: vec![make_multi_attr(
+                MAIL,
+                &["first@example.edu", "second@example.edu"],
+            )],
+        }]));
+
+        let attrs = extract_attributes(&assertion);
+        assert_eq!(
+            attrs[MAIL].values,
+            ["first@example.edu", "second@example.edu"]
+        );
+    }
+
+    #[test]
+    fn keeps_multi_valued_scoped_affiliation() {
+        let assertion = make_assertion(Some(vec![AttributeStatement {
+            attributes: vec![make_multi_attr(
+                EDU_PERSON_SCOPED_AFFILIATION,
+                &["student@example.edu", "member@example.edu"],
+            )],
+        }]));
+
+        let attrs = extract_attributes(&assertion);
+        assert_eq!(
+            attrs[EDU_PERSON_SCOPED_AFFILIATION].values,
+            ["student@example.edu", "member@example.edu"]
+        );
+    }
+
     #[test]
-    fn takes_first_value_only() {
+    fn merges_values_across_statements() {
+        let assertion = make_assertion(Some(vec![
+            AttributeStatement {
+                attributes: vec![make_multi_attr(
+                    EDU_PERSON_AFFILIATION,
+                    &["student", "member"],
+                )],
+            },
+            AttributeStatement {
+                attributes: vec![make_multi_attr(
+                    EDU_PERSON_AFFILIATION,
+                    &["member", "staff"],
+                )],
+            },
+        ]));
+
+        let attrs = extract_attributes(&assertion);
+        assert_eq!(
+            attrs[EDU_PERSON_AFFILIATION].values,
+            ["student", "member", "staff"]
+        );
+    }
+
+    #[test]
+    fn preserves_name_format_and_friendly_name() {
         let assertion = make_assertion(Some(vec![AttributeStatement {
             attributes: vec![Attribute {
-                friendly_name: None,
-                name: Some(MAIL.into()),
-                name_format: None,
-                values: vec![
-                    AttributeValue {
-                        attribute_type: None,
-                        value: Some("first@example.edu".into()),
-                    },
-                    AttributeValue {
-                        attribute_type: None,
-                        value: Some("second@example.edu".into()),
-                    },
-                ],
+                friendly_name: Some("mail".into()),
+                name_format: Some(URI_NAME_FORMAT.into()),
+                ..make_attr(MAIL, "user@example.edu")
             }],
         }]));
 
         let attrs = extract_attributes(&assertion);
-        assert_eq!(attrs[MAIL], "first@example.edu");
+        assert_eq!(attrs[MAIL].friendly_name.as_deref(), Some("mail"));
+        assert_eq!(attrs[MAIL].name_format.as_deref(), Some(URI_NAME_FORMAT));
+    }
+
+    #[test]
+    fn attribute_statement_emits_every_value() {
+        let mut attrs = BTreeMap::new();
+        attrs.insert(
+            EDU_PERSON_SCOPED_AFFILIATION.to_string(),
+            ProxiedAttribute {
+                name_format: None,
+                friendly_name: Some("eduPersonScopedAffiliation".into()),
+                values: vec!["student@example.edu".into(), "member@example.edu".into()],
+            },
+        );
+
+        let stmt = attribute_statement(&attrs).unwrap();
+        assert_eq!(stmt.attributes.len(), 1);
+        let attr = &stmt.attributes[0];
+        assert_eq!(attr.name.as_deref(), Some(EDU_PERSON_SCOPED_AFFILIATION));
+        assert_eq!(
+            attr.friendly_name.as_deref(),
+            Some("eduPersonScopedAffiliation")
+        );
+        // Missing NameFormat defaults to URI.
+        assert_eq!(attr.name_format.as_deref(), Some(URI_NAME_FORMAT));
+        let values: Vec<_> = attr
+            .values
+            .iter()
+            .filter_map(|v| v.value.as_deref())
+            .collect();
+        assert_eq!(values, ["student@example.edu", "member@example.edu"]);
+    }
+
+    #[test]
+    fn resolves_oids_and_friendly_names() {
+        assert_eq!(resolve_name("mail"), Some(MAIL));
+        assert_eq!(resolve_name(MAIL), Some(MAIL));
+        assert_eq!(
+            resolve_name("eduPersonPrincipalName"),
+            Some(EDU_PERSON_PRINCIPAL_NAME)
+        );
+        assert_eq!(resolve_name("sn"), Some(SURNAME));
+        assert_eq!(resolve_name("subject-id"), Some(SUBJECT_ID));
+        assert_eq!(resolve_name("urn:oid:9.9.9.9.9"), Some("urn:oid:9.9.9.9.9"));
+        assert_eq!(resolve_name("Mail"), None);
+    }
+
+    #[test]
+    fn attribute_statement_omitted_when_empty() {
+        assert!(attribute_statement(&BTreeMap::new()).is_none());
     }
 }
@@ -1,10 +1,142 @@
 use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::str::FromStr;
+
+/// Where in-flight authentication sessions are kept.
+pub enum SessionStoreConfig {
+    /// Process-local; only safe with a single replica.
+    Memory,
+    /// Shared by all replicas through Valkey (or Redis).
+    Valkey { url: String },
+    /// No server-side state: sessions are sealed into encrypted tokens with
+    /// the first of `keys`; the rest are still accepted for rotation.
+    Stateless {
+        keys: Vec<Vec<u8>>,
+        max_token_len: usize,
+        /// A token survives its use, so only this cache stops a response
+        /// from being accepted twice.
+        replay_cache: ReplayCacheConfig,
+    },
+}
+
+/// Where stateless mode remembers accepted assertions.
+pub enum ReplayCacheConfig {
+    /// Process-local; a replay sent to another replica is not caught, so
+    /// only safe with a single replica.
+    Memory,
+    /// Shared by all replicas through Valkey (or Redis).
+    Valkey { url: String },
+}
+
+/// Where university IdP metadata comes from. Sources are listed in priority
+/// order; the first one that knows an entity wins.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MetadataSourceConfig {
+    /// An MDQ server whose responses are verified against
+    /// `signing_cert_path` (PEM), or the bundled InCommon MDQ certificate
+    /// when unset.
+    Mdq {
+        base_url: String,
+        signing_cert_path: Option<String>,
+    },
+    /// An EntitiesDescriptor aggregate file or https URL, read again on
+    /// every discovery index refresh. Its signature is checked against
+    /// `signing_cert_path` (PEM), which a URL requires.
+    Aggregate {
+        source: String,
+        signing_cert_path: Option<String>,
+    },
+    /// A single IdP EntityDescriptor file.
+    Entity { path: String },
+}
+
+impl MetadataSourceConfig {
+    /// The InCommon MDQ service, used when no sources are configured.
+    pub fn incommon() -> Self {
+        Self::Mdq {
+            base_url: "https://mdq.incommon.org".into(),
+            signing_cert_path: None,
+        }
+    }
+}
+
+impl FromStr for MetadataSourceConfig {
+    type Err = anyhow::Error;
+
+    /// Parses `mdq <url> [cert]`, `aggregate <file-or-url> [cert]` or
+    /// `entity <file>`.
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        Ok(match parts.as_slice() {
+            ["mdq", base_url] => Self::Mdq {
+                base_url: base_url.to_string(),
+                signing_cert_path: None,
+            },
+            ["mdq", base_url, cert] => Self::Mdq {
+                base_url: base_url.to_string(),
+                signing_cert_path: Some(cert.to_string()),
+            },
+            ["aggregate", source] => Self::Aggregate {
+                source: source.to_string(),
+                signing_cert_path: None,
+            },
+            ["aggregate", source, cert] => Self::Aggregate {
+                source: source.to_string(),
+                signing_cert_path: Some(cert.to_string()),
+            },
+            ["entity", path] => Self::Entity {
+                path: path.to_string(),
+            },
+            _ => anyhow::bail!("invalid metadata source: {s:?}"),
+        })
+    }
+}
+
+/// A source of downstream SP metadata: a local file, or an https URL whose
+/// signature is checked against `signing_cert_path` (PEM).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpMetadataSource {
+    pub source: String,
+    pub signing_cert_path: Option<String>,
+}
+
+impl FromStr for SpMetadataSource {
+    type Err = anyhow::Error;
+
+    /// Parses `<file-or-url> [cert]`.
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        Ok(match parts.as_slice() {
+            [source] => Self {
+                source: source.to_string(),
+                signing_cert_path: None,
+            },
+            [source, cert] => Self {
+                source: source.to_string(),
+                signing_cert_path: Some(cert.to_string()),
+            },
+            _ => anyhow::bail!("invalid SP metadata source: {s:?}"),
+        })
+    }
+}
 
 pub struct Config {
     pub base_url: String,
     pub entity_id: String,
     pub idp_cert_path: String,
     pub idp_key_path: String,
+    /// Metadata of the Service Providers allowed to use the proxy.
+    pub sp_metadata: Vec<SpMetadataSource>,
+    /// Require every SP to sign its requests, not just those whose metadata
+    /// sets `AuthnRequestsSigned`. Advertised as `WantAuthnRequestsSigned`.
+    pub require_signed_requests: bool,
+    /// JSON file of per-SP attribute release rules; without one, every SP
+    /// receives every attribute unchanged.
+    pub attribute_policy: Option<String>,
+    /// University IdP metadata sources, highest priority first.
+    pub metadata_sources: Vec<MetadataSourceConfig>,
+    pub session_store: SessionStoreConfig,
     pub host: String,
     pub port: u16,
 }
@@ -19,6 +151,74 @@ impl Config {
             .context("SAML_PROXY_IDP_CERT_PATH must be set")?;
         let idp_key_path = std::env::var("SAML_PROXY_IDP_KEY_PATH")
             .context("SAML_PROXY_IDP_KEY_PATH must be set")?;
+        let sp_metadata = std::env::var("SAML_PROXY_SP_METADATA")
+            .context("SAML_PROXY_SP_METADATA must be set")?
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::parse)
+            .collect::<Result<_>>()?;
+        let require_signed_requests = std::env::var("SAML_PROXY_REQUIRE_SIGNED_REQUESTS")
+            .unwrap_or_else(|_| "false".into())
+            .parse::<bool>()
+            .context("SAML_PROXY_REQUIRE_SIGNED_REQUESTS must be true or false")?;
+        let attribute_policy = std::env::var("SAML_PROXY_ATTRIBUTE_POLICY").ok();
+        let metadata_sources = match std::env::var("SAML_PROXY_METADATA_SOURCES") {
+            Ok(v) => v
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::parse)
+                .collect::<Result<_>>()?,
+            Err(_) => vec![MetadataSourceConfig::incommon()],
+        };
+
+        let session_store = match std::env::var("SAML_PROXY_SESSION_STORE")
+            .unwrap_or_else(|_| "memory".into())
+            .as_str()
+        {
+            "memory" => SessionStoreConfig::Memory,
+            "valkey" => SessionStoreConfig::Valkey {
+                url: std::env::var("SAML_PROXY_VALKEY_URL").context(
+                    "SAML_PROXY_VALKEY_URL must be set when SAML_PROXY_SESSION_STORE=valkey",
+                )?,
+            },
+            "stateless" => SessionStoreConfig::Stateless {
+                keys: std::env::var("SAML_PROXY_SESSION_KEYS")
+                    .context(
+                        "SAML_PROXY_SESSION_KEYS must be set when SAML_PROXY_SESSION_STORE=stateless",
+                    )?
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|k| STANDARD.decode(k))
+                    .collect::<Result<_, _>>()
+                    .context("SAML_PROXY_SESSION_KEYS must be comma-separated base64 keys")?,
+                max_token_len: match std::env::var("SAML_PROXY_SESSION_TOKEN_MAX_LEN") {
+                    Ok(v) => v
+                        .parse()
+                        .context("SAML_PROXY_SESSION_TOKEN_MAX_LEN must be a number of bytes")?,
+                    Err(_) => crate::session::DEFAULT_MAX_TOKEN_LEN,
+                },
+                replay_cache: match std::env::var("SAML_PROXY_REPLAY_CACHE")
+                    .context(
+                        "SAML_PROXY_REPLAY_CACHE must be set when SAML_PROXY_SESSION_STORE=\
+                         stateless: valkey to share it between replicas, or memory for a single \
+                         replica",
+                    )?
+                    .as_str()
+                {
+                    "memory" => ReplayCacheConfig::Memory,
+                    "valkey" => ReplayCacheConfig::Valkey {
+                        url: std::env::var("SAML_PROXY_VALKEY_URL").context(
+                            "SAML_PROXY_VALKEY_URL must be set when SAML_PROXY_REPLAY_CACHE=valkey",
+                        )?,
+                    },
+                    other => anyhow::bail!("unknown SAML_PROXY_REPLAY_CACHE: {other}"),
+                },
+            },
+            other => anyhow::bail!("unknown SAML_PROXY_SESSION_STORE: {other}"),
+        };
 
         let host = std::env::var("SAML_PROXY_HOST").unwrap_or_else(|_| "0.0.0.0".into());
         let port = std::env::var("SAML_PROXY_PORT")
@@ -31,8 +231,96 @@ impl Config {
             entity_id,
             idp_cert_path,
             idp_key_path,
+            sp_metadata,
+            require_signed_requests,
+            attribute_policy,
+            metadata_sources,
+            session_store,
             host,
             port,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sp_metadata_sources() {
+        assert_eq!(
+            "metadata/sp.xml".parse::<SpMetadataSource>().unwrap(),
+            SpMetadataSource {
+                source: "metadata/sp.xml".into(),
+                signing_cert_path: None,
+            }
+        );
+        assert_eq!(
+            " https://sp.example.org/metadata  certs/sp.pem "
+                .parse::<SpMetadataSource>()
+                .unwrap(),
+            SpMetadataSource {
+                source: "https://sp.example.org/metadata".into(),
+                signing_cert_path: Some("certs/sp.pem".into()),
+            }
+        );
+        assert!("a b c".parse::<SpMetadataSource>().is_err());
+        assert!("".parse::<SpMetadataSource>().is_err());
+    }
+
+    #[test]
+    fn parses_metadata_sources() {
+        assert_eq!(
+            "mdq https://mdq.example.org"
+                .parse::<MetadataSourceConfig>()
+                .unwrap(),
+            MetadataSourceConfig::Mdq {
+                base_url: "https://mdq.example.org".into(),
+                signing_cert_path: None,
+            }
+        );
+        assert_eq!(
+            "mdq  https://mdq.example.org  certs/mdq.pem"
+                .parse::<MetadataSourceConfig>()
+                .unwrap(),
+            MetadataSourceConfig::Mdq {
+                base_url: "https://mdq.example.org".into(),
+                signing_cert_path: Some("certs/mdq.pem".into()),
+            }
+        );
+        assert_eq!(
+            "aggregate metadata/federation.xml"
+                .parse::<MetadataSourceConfig>()
+                .unwrap(),
+            MetadataSourceConfig::Aggregate {
+                source: "metadata/federation.xml".into(),
+                signing_cert_path: None,
+            }
+        );
+        assert_eq!(
+            "aggregate https://mds.edugain.org/edugain-v2.xml certs/edugain.pem"
+                .parse::<MetadataSourceConfig>()
+                .unwrap(),
+            MetadataSourceConfig::Aggregate {
+                source: "https://mds.edugain.org/edugain-v2.xml".into(),
+                signing_cert_path: Some("certs/edugain.pem".into()),
+            }
+        );
+        assert_eq!(
+            "entity idp.xml".parse::<MetadataSourceConfig>().unwrap(),
+            MetadataSourceConfig::Entity {
+                path: "idp.xml".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_metadata_sources() {
+        for source in ["", "mdq", "entity", "entity a b", "file idp.xml"] {
+            assert!(
+                source.parse::<MetadataSourceConfig>().is_err(),
+                "{source:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,128 @@
+/// A federation known by its registration authority, for labelling and
+/// filtering discovery results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Federation {
+    pub name: &'static str,
+    /// ISO 3166-1 alpha-2 code of the federation's home country.
+    pub country: &'static str,
+}
+
+/// Registration authorities of research and education federations, as they
+/// appear in `mdrpi:RegistrationInfo`. Compared after [`normalize`], so the
+/// scheme and a trailing slash do not matter.
+const KNOWN: &[(&str, Federation)] = &[
+    ("aaf.edu.au", fed("AAF", "AU")),
+    ("aai.pionier.net.pl", fed("PIONIER.Id", "PL")),
+    ("eduid.at", fed("ACOnet", "AT")),
+    ("federation.belnet.be", fed("Belnet", "BE")),
+    ("federation.renater.fr", fed("RENATER", "FR")),
+    ("feide.no", fed("Feide", "NO")),
+    ("incommon.org", fed("InCommon", "US")),
+    ("rr.aai.switch.ch", fed("SWITCHaai", "CH")),
+    ("tuakiri.ac.nz", fed("Tuakiri", "NZ")),
+    ("ukfederation.org.uk", fed("UK federation", "GB")),
+    ("www.aai.dfn.de", fed("DFN-AAI", "DE")),
+    ("www.canarie.ca", fed("CAF", "CA")),
+    ("www.csc.fi/haka", fed("Haka", "FI")),
+    ("www.gakunin.jp", fed("GakuNin", "JP")),
+    ("www.heanet.ie", fed("Edugate", "IE")),
+    ("www.idem.garr.it", fed("IDEM", "IT")),
+    ("www.rediris.es", fed("SIR", "ES")),
+    ("www.surfconext.nl", fed("SURFconext", "NL")),
+    ("www.swamid.se", fed("SWAMID", "SE")),
+    ("www.wayf.dk", fed("WAYF", "DK")),
+];
+
+const fn fed(name: &'static str, country: &'static str) -> Federation {
+    Federation { name, country }
+}
+
+/// Looks up the federation behind a registration authority.
+pub fn lookup(registration_authority: &str) -> Option<Federation> {
+    let authority = normalize(registration_authority);
+    KNOWN
+        .iter()
+        .find(|(known, _)| *known == authority)
+        .map(|(_, federation)| *federation)
+}
+
+fn normalize(authority: &str) -> String {
+    let authority = authority.trim();
+    authority
+        .strip_prefix("https://")
+        .or_else(|| authority.strip_prefix("http://"))
+        .unwrap_or(authority)
+        .trim_end_matches('/')
+        .to_ascii_lowercase()
+}
+
+/// Reads the `registrationAuthority` attribute of the
+/// `mdrpi:RegistrationInfo` element in an EntityDescriptor fragment.
+pub(crate) fn registration_authority(fragment: &str) -> Option<String> {
+    let start = fragment.find("RegistrationInfo")?;
+    let tag = &fragment[start..];
+    let tag = &tag[..tag.find('>')?];
+
+    const ATTRIBUTE: &str = "registrationAuthority=";
+    let value = &tag[tag.find(ATTRIBUTE)? + ATTRIBUTE.len()..];
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    Some(value[..value.find(quote)?].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_ignores_scheme_and_trailing_slash() {
+        let incommon = lookup("https://incommon.org").unwrap();
+        assert_eq!(incommon.name, "InCommon");
+        assert_eq!(incommon.country, "US");
+
+        assert_eq!(lookup("http://www.swamid.se/").unwrap().name, "SWAMID");
+        assert_eq!(lookup("https://www.swamid.se").unwrap().name, "SWAMID");
+    }
+
+    #[test]
+    fn lookup_unknown_authority() {
+        assert!(lookup("https://federation.example.org").is_none());
+        assert!(lookup("").is_none());
+    }
+
+    #[test]
+    fn reads_registration_authority() {
+        let fragment = r#"<EntityDescriptor entityID="https://idp.example.ac.uk">
+    <Extensions>
+        <mdrpi:RegistrationInfo xmlns:mdrpi="urn:oasis:names:tc:SAML:metadata:rpi" registrationAuthority="http://ukfederation.org.uk" registrationInstant="2014-11-07T16:40:51Z">
+            <mdrpi:RegistrationPolicy xml:lang="en">http://ukfederation.org.uk/doc/mdrps-20130902</mdrpi:RegistrationPolicy>
+        </mdrpi:RegistrationInfo>
+    </Extensions>
+</EntityDescriptor>"#;
+        assert_eq!(
+            registration_authority(fragment).as_deref(),
+            Some("http://ukfederation.org.uk")
+        );
+    }
+
+    #[test]
+    fn reads_single_quoted_registration_authority() {
+        let fragment = "<md:EntityDescriptor><md:Extensions><RegistrationInfo registrationAuthority='https://incommon.org'/></md:Extensions></md:EntityDescriptor>";
+        assert_eq!(
+            registration_authority(fragment).as_deref(),
+            Some("https://incommon.org")
+        );
+    }
+
+    #[test]
+    fn missing_registration_info() {
+        assert!(registration_authority(r#"<EntityDescriptor entityID="x"/>"#).is_none());
+        // The attribute must belong to RegistrationInfo itself.
+        assert!(
+            registration_authority(
+                r#"<RegistrationInfo/><Other registrationAuthority="https://incommon.org"/>"#
+            )
+            .is_none()
+        );
+    }
+}
@@ -1,20 +1,65 @@
+use super::federation::{self, registration_authority};
+use crate::metadata_sources::MetadataSources;
 use samael::metadata::EntityDescriptor;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 
-const AGGREGATE_URL: &str = "https://mdq.incommon.org/entities";
 const REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
 
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
 pub struct EntityEntry {
     pub entity_id: String,
     pub display_name: String,
+    /// The federation that registered the entity, from its
+    /// `mdrpi:RegistrationInfo`.
+    pub registration_authority: Option<String>,
+    /// Name of the registering federation, when it is a known one.
+    pub federation: Option<String>,
+    /// ISO 3166-1 alpha-2 country of the registering federation.
+    pub country: Option<String>,
 }
 
-/// In-memory index of InCommon federation IdP entities, refreshed periodically
-/// from the MDQ aggregate endpoint. Parses individual EntityDescriptor fragments
-/// rather than deserializing the full aggregate into a DOM.
+/// Narrows a search to one federation and/or country, both compared
+/// case-insensitively. `federation` matches either the federation's name or
+/// its registration authority.
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchFilter {
+    pub federation: Option<String>,
+    pub country: Option<String>,
+}
+
+impl SearchFilter {
+    fn matches(&self, entry: &EntityEntry) -> bool {
+        let eq =
+            |a: &Option<String>, b: &str| a.as_deref().is_some_and(|a| a.eq_ignore_ascii_case(b));
+        let federation = self
+            .federation
+            .as_deref()
+            .is_none_or(|f| eq(&entry.federation, f) || eq(&entry.registration_authority, f));
+        let country = self
+            .country
+            .as_deref()
+            .is_none_or(|c| eq(&entry.country, c));
+        federation && country
+    }
+}
+
+/// A federation represented in the index, for the discovery UI's filter.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct FederationSummary {
+    /// The federation's name, or its registration authority when unknown.
+    pub name: String,
+    pub country: Option<String>,
+    pub count: usize,
+}
+
+/// In-memory index of federation IdP entities, refreshed periodically from
+/// the configured [`MetadataSources`]. Parses individual EntityDescriptor
+/// fragments rather than deserializing the full aggregate into a DOM.
 #[derive(Clone)]
 pub struct FederationIndex {
     entries: Arc<RwLock<Vec<EntityEntry>>>,
@@ -37,97 +82,235 @@ impl FederationIndex {
         &self.entries
     }
 
-    pub async fn search(&self, query: &str, limit: usize) -> Vec<EntityEntry> {
+    pub async fn search(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+        limit: usize,
+    ) -> Vec<EntityEntry> {
         let query_lower = query.to_lowercase();
         let entries = self.entries.read().await;
         entries
             .iter()
             .filter(|e| e.display_name.to_lowercase().contains(&query_lower))
+            .filter(|e| filter.matches(e))
             .take(limit)
             .cloned()
             .collect()
     }
 
-    pub async fn refresh(&self) -> anyhow::Result<()> {
-        let xml = reqwest::get(AGGREGATE_URL).await?.text().await?;
-        let entries = parse_idp_entries(&xml);
+    /// The federations that registered the indexed entities, by name.
+    /// Entities without registration information are not counted.
+    pub async fn federations(&self) -> Vec<FederationSummary> {
+        let mut summaries: BTreeMap<&str, FederationSummary> = BTreeMap::new();
+        let entries = self.entries.read().await;
+        for entry in entries.iter() {
+            let Some(name) = entry
+                .federation
+                .as_deref()
+                .or(entry.registration_authority.as_deref())
+            else {
+                continue;
+            };
+            summaries
+                .entry(name)
+                .or_insert_with(|| FederationSummary {
+                    name: name.to_string(),
+                    country: entry.country.clone(),
+                    count: 0,
+                })
+                .count += 1;
+        }
+        summaries.into_values().collect()
+    }
+
+    /// Rebuilds the index from every configured metadata source. A source
+    /// that fails keeps the entries it last supplied; see
+    /// [`MetadataSources::idp_entries`].
+    pub async fn refresh(&self, sources: &MetadataSources) {
+        let entries = sources.idp_entries().await;
 
         let count = entries.len();
         *self.entries.write().await = entries;
         tracing::info!(count, "refreshed federation index");
-
-        Ok(())
     }
 }
 
+/// Merges the entries of several aggregates, given highest priority first.
+/// An entity that appears in more than one, as entities exported to eduGAIN
+/// do, is kept once, from the first aggregate that lists it.
+pub(crate) fn merge(aggregates: impl IntoIterator<Item = Vec<EntityEntry>>) -> Vec<EntityEntry> {
+    let mut seen = HashSet::new();
+    let mut merged: Vec<EntityEntry> = aggregates
+        .into_iter()
+        .flatten()
+        .filter(|e| seen.insert(e.entity_id.clone()))
+        .collect();
+
+    merged.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+    merged
+}
+
 /// Splits the aggregate XML into individual EntityDescriptor fragments, skips
 /// any that lack an IDPSSODescriptor, then parses the remaining ones with
 /// samael to extract entity ID and display name.
 pub(crate) fn parse_idp_entries(xml: &str) -> Vec<EntityEntry> {
-    let mut entries = Vec::new();
-
-    for fragment in entity_descriptor_fragments(xml) {
-        if !fragment.contains("IDPSSODescriptor") {
-            continue;
-        }
+    let mut entries: Vec<EntityEntry> = entity_descriptor_fragments(xml)
+        .iter()
+        .filter_map(|fragment| parse_idp_fragment(fragment))
+        .map(|(_, entry)| entry)
+        .collect();
 
-        let entity: EntityDescriptor = match fragment.parse() {
-            Ok(e) => e,
-            Err(e) => {
-                tracing::debug!(error = %e, "skipping unparseable EntityDescriptor");
-                continue;
-            }
-        };
-
-        let entity_id = match entity.entity_id {
-            Some(id) => id,
-            None => continue,
-        };
+    entries.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+    entries
+}
 
-        let display_name = entity
-            .organization
-            .as_ref()
-            .and_then(|org| org.organization_display_names.as_ref())
-            .and_then(|names| {
-                names
-                    .iter()
-                    .find(|n| n.lang.as_deref() == Some("en"))
-                    .or(names.first())
-            })
-            .map(|n| n.value.clone())
-            .unwrap_or_else(|| entity_id.clone());
-
-        entries.push(EntityEntry {
-            entity_id,
-            display_name,
-        });
+/// Parses a single EntityDescriptor fragment if it describes an IdP,
+/// returning it along with its discovery entry.
+pub(crate) fn parse_idp_fragment(fragment: &str) -> Option<(EntityDescriptor, EntityEntry)> {
+    if !fragment.contains("IDPSSODescriptor") {
+        return None;
     }
 
-    entries.sort_by(|a, b| a.display_name.cmp(&b.display_name));
-    entries
+    let entity: EntityDescriptor = match fragment.parse() {
+        Ok(e) => e,
+        Err(e) => {
+            tracing::debug!(error = %e, "skipping unparseable EntityDescriptor");
+            return None;
+        }
+    };
+    let entry = idp_entry(fragment, &entity)?;
+    Some((entity, entry))
+}
+
+/// The discovery entry for an IdP: its entity ID, English display name
+/// (falling back to the first display name and then the entity ID), and the
+/// federation that registered it.
+fn idp_entry(fragment: &str, entity: &EntityDescriptor) -> Option<EntityEntry> {
+    let entity_id = entity.entity_id.clone()?;
+
+    let display_name = entity
+        .organization
+        .as_ref()
+        .and_then(|org| org.organization_display_names.as_ref())
+        .and_then(|names| {
+            names
+                .iter()
+                .find(|n| n.lang.as_deref() == Some("en"))
+                .or(names.first())
+        })
+        .map(|n| n.value.clone())
+        .unwrap_or_else(|| entity_id.clone());
+
+    let registration_authority = registration_authority(fragment);
+    let known = registration_authority
+        .as_deref()
+        .and_then(federation::lookup);
+
+    Some(EntityEntry {
+        entity_id,
+        display_name,
+        registration_authority,
+        federation: known.map(|f| f.name.to_string()),
+        country: known.map(|f| f.country.to_string()),
+    })
 }
 
-/// Yields the substring of each top-level <EntityDescriptor ...>...</EntityDescriptor>
-/// element in the aggregate XML.
-pub(crate) fn entity_descriptor_fragments(xml: &str) -> Vec<&str> {
+/// Yields each top-level `<EntityDescriptor>` element in the aggregate XML,
+/// with or without a namespace prefix such as eduGAIN's `md:`. Namespace
+/// declarations made on the aggregate's root element are copied onto each
+/// fragment, so it still parses on its own.
+pub(crate) fn entity_descriptor_fragments(xml: &str) -> Vec<Cow<'_, str>> {
+    const NAME: &str = "EntityDescriptor";
+    let root_namespaces = root_namespace_declarations(xml);
     let mut fragments = Vec::new();
     let mut search_from = 0;
 
-    while let Some(start) = xml[search_from..].find("<EntityDescriptor") {
-        let abs_start = search_from + start;
-        let close_tag = "</EntityDescriptor>";
-        if let Some(end) = xml[abs_start..].find(close_tag) {
-            let abs_end = abs_start + end + close_tag.len();
-            fragments.push(&xml[abs_start..abs_end]);
-            search_from = abs_end;
-        } else {
-            break;
+    while let Some(found) = xml[search_from..].find(NAME) {
+        let name_start = search_from + found;
+        let name_end = name_start + NAME.len();
+        search_from = name_end;
+
+        // Walk back over an optional `prefix:` to the opening '<'.
+        let Some(open) = xml[..name_start].rfind('<') else {
+            continue;
+        };
+        let prefix = &xml[open + 1..name_start];
+        let is_prefix = prefix.is_empty()
+            || prefix.strip_suffix(':').is_some_and(|p| {
+                !p.is_empty()
+                    && p.chars()
+                        .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            });
+        let ends_name = xml[name_end..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_whitespace() || c == '>');
+        if !is_prefix || !ends_name {
+            continue;
         }
+
+        let close_tag = format!("</{prefix}{NAME}>");
+        let Some(end) = xml[open..].find(&close_tag) else {
+            break;
+        };
+        let abs_end = open + end + close_tag.len();
+        fragments.push(with_namespaces(
+            &xml[open..abs_end],
+            name_end - open,
+            &root_namespaces,
+        ));
+        search_from = abs_end;
     }
 
     fragments
 }
 
+/// The `xmlns` attributes of the document's root element.
+fn root_namespace_declarations(xml: &str) -> Vec<&str> {
+    let mut rest = xml;
+    let root = loop {
+        let Some(start) = rest.find('<') else {
+            return Vec::new();
+        };
+        rest = &rest[start + 1..];
+        if !rest.starts_with('?') && !rest.starts_with('!') {
+            break &rest[..rest.find('>').unwrap_or(rest.len())];
+        }
+    };
+
+    root.split_whitespace()
+        .filter(|attr| attr.starts_with("xmlns"))
+        .map(|attr| attr.trim_end_matches('/'))
+        .collect()
+}
+
+/// Adds the declarations in `namespaces` that the fragment's start tag does
+/// not make itself, right after the element name ending at `name_end`.
+fn with_namespaces<'a>(fragment: &'a str, name_end: usize, namespaces: &[&str]) -> Cow<'a, str> {
+    let start_tag = &fragment[..fragment.find('>').unwrap_or(fragment.len())];
+    let missing: Vec<&str> = namespaces
+        .iter()
+        .copied()
+        .filter(|decl| {
+            let name = decl.split('=').next().unwrap_or(decl);
+            !start_tag.contains(&format!("{name}="))
+        })
+        .collect();
+    if missing.is_empty() {
+        return Cow::Borrowed(fragment);
+    }
+
+    let mut owned = String::with_capacity(fragment.len() + 64);
+    owned.push_str(&fragment[..name_end]);
+    for decl in missing {
+        owned.push(' ');
+        owned.push_str(decl);
+    }
+    owned.push_str(&fragment[name_end..]);
+    Cow::Owned(owned)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -162,6 +345,189 @@ mod tests {
         assert!(fragments[2].contains("idp2.example.org"));
     }
 
+    const EDUGAIN_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<md:EntitiesDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" xmlns:mdrpi="urn:oasis:names:tc:SAML:metadata:rpi" xmlns:mdui="urn:oasis:names:tc:SAML:metadata:ui" Name="http://edugain.org/">
+<md:EntityDescriptor entityID="https://idp.example.ac.uk/shibboleth">
+    <md:Extensions>
+        <mdrpi:RegistrationInfo registrationAuthority="http://ukfederation.org.uk"/>
+    </md:Extensions>
+    <md:IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+        <md:SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" Location="https://idp.example.ac.uk/sso"/>
+    </md:IDPSSODescriptor>
+    <md:Organization>
+        <md:OrganizationDisplayName xml:lang="en">Example UK University</md:OrganizationDisplayName>
+    </md:Organization>
+</md:EntityDescriptor>
+<md:EntityDescriptor entityID="https://idp.example.org">
+    <md:Extensions>
+        <mdrpi:RegistrationInfo registrationAuthority="https://federation.example.org/"/>
+    </md:Extensions>
+    <md:IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+        <md:SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" Location="https://idp.example.org/sso"/>
+    </md:IDPSSODescriptor>
+</md:EntityDescriptor>
+</md:EntitiesDescriptor>"#;
+
+    fn entry(entity_id: &str, name: &str, federation: Option<(&str, &str)>) -> EntityEntry {
+        EntityEntry {
+            entity_id: entity_id.into(),
+            display_name: name.into(),
+            registration_authority: federation.map(|(f, _)| format!("https://{f}.example")),
+            federation: federation.map(|(f, _)| f.into()),
+            country: federation.map(|(_, c)| c.into()),
+        }
+    }
+
+    #[test]
+    fn fragments_handle_prefixed_elements() {
+        let fragments = entity_descriptor_fragments(EDUGAIN_XML);
+        assert_eq!(fragments.len(), 2);
+        assert!(fragments[0].starts_with("<md:EntityDescriptor "));
+        assert!(fragments[0].ends_with("</md:EntityDescriptor>"));
+        assert!(fragments[1].contains("idp.example.org"));
+    }
+
+    #[test]
+    fn fragments_inherit_root_namespaces() {
+        let fragments = entity_descriptor_fragments(EDUGAIN_XML);
+        let start_tag = &fragments[0][..fragments[0].find('>').unwrap()];
+        assert!(start_tag.contains(r#"xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata""#));
+        assert!(start_tag.contains(r#"xmlns:mdrpi="urn:oasis:names:tc:SAML:metadata:rpi""#));
+        assert!(start_tag.contains(r#"entityID="https://idp.example.ac.uk/shibboleth""#));
+        // Non-namespace attributes of the root are not copied.
+        assert!(!start_tag.contains("Name="));
+    }
+
+    #[test]
+    fn fragments_leave_standalone_descriptor_untouched() {
+        let xml = r#"<EntityDescriptor xmlns="urn:oasis:names:tc:SAML:2.0:metadata" entityID="https://idp.test"></EntityDescriptor>"#;
+        let fragments = entity_descriptor_fragments(xml);
+        assert_eq!(fragments.len(), 1);
+        assert!(matches!(fragments[0], Cow::Borrowed(f) if f == xml));
+    }
+
+    #[test]
+    fn parse_records_registration_authority() {
+        let entries = parse_idp_entries(EDUGAIN_XML);
+        assert_eq!(entries.len(), 2);
+
+        let uk = entries
+            .iter()
+            .find(|e| e.entity_id == "https://idp.example.ac.uk/shibboleth")
+            .unwrap();
+        assert_eq!(uk.display_name, "Example UK University");
+        assert_eq!(
+            uk.registration_authority.as_deref(),
+            Some("http://ukfederation.org.uk")
+        );
+        assert_eq!(uk.federation.as_deref(), Some("UK federation"));
+        assert_eq!(uk.country.as_deref(), Some("GB"));
+
+        let unknown = entries
+            .iter()
+            .find(|e| e.entity_id == "https://idp.example.org")
+            .unwrap();
+        assert_eq!(
+            unknown.registration_authority.as_deref(),
+            Some("https://federation.example.org/")
+        );
+        assert!(unknown.federation.is_none());
+        assert!(unknown.country.is_none());
+    }
+
+    #[test]
+    fn merge_keeps_first_copy_of_each_entity() {
+        let incommon = vec![entry(
+            "https://idp.cmu.edu",
+            "CMU",
+            Some(("InCommon", "US")),
+        )];
+        let edugain = vec![
+            entry("https://idp.cmu.edu", "CMU via eduGAIN", None),
+            entry(
+                "https://idp.ox.ac.uk",
+                "Oxford",
+                Some(("UK federation", "GB")),
+            ),
+        ];
+
+        let merged = merge([incommon, edugain]);
+        let names: Vec<&str> = merged.iter().map(|e| e.display_name.as_str()).collect();
+        assert_eq!(names, ["CMU", "Oxford"]);
+    }
+
+    #[tokio::test]
+    async fn search_filters_by_federation_and_country() {
+        let index = FederationIndex::new();
+        *index.entries.write().await = vec![
+            entry(
+                "https://a.edu",
+                "Alpha University",
+                Some(("InCommon", "US")),
+            ),
+            entry(
+                "https://a.ac.uk",
+                "Alpha College",
+                Some(("UK federation", "GB")),
+            ),
+            entry("https://a.example", "Alpha Institute", None),
+        ];
+
+        let by_federation = SearchFilter {
+            federation: Some("incommon".into()),
+            country: None,
+        };
+        let results = index.search("alpha", &by_federation, 20).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].entity_id, "https://a.edu");
+
+        let by_authority = SearchFilter {
+            federation: Some("https://UK federation.example".into()),
+            country: None,
+        };
+        let results = index.search("alpha", &by_authority, 20).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].entity_id, "https://a.ac.uk");
+
+        let by_country = SearchFilter {
+            federation: None,
+            country: Some("gb".into()),
+        };
+        let results = index.search("alpha", &by_country, 20).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].entity_id, "https://a.ac.uk");
+
+        let unfiltered = index.search("alpha", &SearchFilter::default(), 20).await;
+        assert_eq!(unfiltered.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn federations_counts_entities() {
+        let index = FederationIndex::new();
+        *index.entries.write().await = vec![
+            entry("https://a.edu", "A", Some(("InCommon", "US"))),
+            entry("https://b.edu", "B", Some(("InCommon", "US"))),
+            entry("https://c.ac.uk", "C", Some(("UK federation", "GB"))),
+            entry("https://d.example", "D", None),
+        ];
+
+        assert_eq!(
+            index.federations().await,
+            [
+                FederationSummary {
+                    name: "InCommon".into(),
+                    country: Some("US".into()),
+                    count: 2,
+                },
+                FederationSummary {
+                    name: "UK federation".into(),
+                    country: Some("GB".into()),
+                    count: 1,
+                },
+            ]
+        );
+    }
+
     #[test]
     fn fragments_empty_input() {
         let fragments = entity_descriptor_fragments("");
@@ -225,19 +591,22 @@ mod tests {
                 EntityEntry {
                     entity_id: "https://a.edu".into(),
                     display_name: "Alpha University".into(),
+                    ..Default::default()
                 },
                 EntityEntry {
                     entity_id: "https://b.edu".into(),
                     display_name: "Beta College".into(),
+                    ..Default::default()
                 },
                 EntityEntry {
                     entity_id: "https://c.edu".into(),
                     display_name: "Alpha Tech".into(),
+                    ..Default::default()
                 },
             ];
         }
 
-        let results = index.search("alpha", 20).await;
+        let results = index.search("alpha", &SearchFilter::default(), 20).await;
         assert_eq!(results.len(), 2);
         assert!(
             results
@@ -255,11 +624,14 @@ mod tests {
                 .map(|i| EntityEntry {
                     entity_id: format!("https://{i}.edu"),
                     display_name: format!("University {i}"),
+                    ..Default::default()
                 })
                 .collect();
         }
 
-        let results = index.search("University", 5).await;
+        let results = index
+            .search("University", &SearchFilter::default(), 5)
+            .await;
         assert_eq!(results.len(), 5);
     }
 
@@ -271,20 +643,19 @@ mod tests {
             *entries = vec![EntityEntry {
                 entity_id: "https://cmu.edu".into(),
                 display_name: "Carnegie Mellon University".into(),
+                ..Default::default()
             }];
         }
 
-        let results = index.search("CARNEGIE", 20).await;
+        let results = index.search("CARNEGIE", &SearchFilter::default(), 20).await;
         assert_eq!(results.len(), 1);
     }
 }
 
-/// Fetches the InCommon aggregate on startup and refreshes it every 6 hours.
-pub async fn federation_index_task(index: FederationIndex) {
+/// Builds the index on startup and refreshes it every 6 hours.
+pub async fn federation_index_task(index: FederationIndex, sources: Arc<MetadataSources>) {
     loop {
-        if let Err(e) = index.refresh().await {
-            tracing::error!(error = %e, "failed to refresh federation index");
-        }
+        index.refresh(&sources).await;
         tokio::time::sleep(REFRESH_INTERVAL).await;
     }
 }
@@ -1,6 +1,9 @@
+pub mod federation;
 pub mod federation_index;
 
+use crate::discovery::federation_index::SearchFilter;
 use crate::error::Error;
+use crate::session::SessionStore;
 use crate::state::AppState;
 use askama::Template;
 use axum::Json;
@@ -45,6 +48,8 @@ pub struct DiscoveryForm {
 #[derive(Deserialize)]
 pub struct SearchParams {
     pub q: String,
+    #[serde(flatten)]
+    pub filter: SearchFilter,
 }
 
 /// Renders the university selection form. The user searches for and selects
@@ -56,6 +61,7 @@ pub async fn discovery_page(
     let _session = state
         .sessions
         .get(&params.session)
+        .await?
         .ok_or_else(|| Error::SessionNotFound(params.session.clone()))?;
 
     Ok(SelectTemplate {
@@ -70,31 +76,35 @@ pub async fn discovery_submit(
     State(state): State<Arc<AppState>>,
     axum::Form(form): axum::Form<DiscoveryForm>,
 ) -> Result<impl IntoResponse, Error> {
-    if !state
+    let session_id = state
         .sessions
         .update_university(&form.session_id, form.entity_id)
-    {
-        return Err(Error::SessionNotFound(form.session_id));
-    }
+        .await?
+        .ok_or(Error::SessionNotFound(form.session_id))?;
 
-    Ok(Redirect::to(&format!(
-        "/sp/initiate?session={}",
-        form.session_id
-    )))
+    // Session IDs are UUIDs or base64url tokens, both safe in a query string.
+    Ok(Redirect::to(&format!("/sp/initiate?session={session_id}")))
 }
 
 const SEARCH_RESULT_LIMIT: usize = 20;
 
-/// Searches InCommon IdP entities by display name, returning matching
-/// institutions as JSON for the discovery UI's typeahead. Results come from
-/// an in-memory index refreshed periodically from the MDQ aggregate.
+/// Searches IdP entities by display name, optionally within one federation
+/// or country, returning matching institutions as JSON for the discovery
+/// UI's typeahead. Results come from an in-memory index refreshed
+/// periodically from the metadata sources.
 pub async fn search_entities(
     State(state): State<Arc<AppState>>,
     Query(params): Query<SearchParams>,
 ) -> Result<Response, Error> {
     let results = state
         .federation_index
-        .search(&params.q, SEARCH_RESULT_LIMIT)
+        .search(&params.q, &params.filter, SEARCH_RESULT_LIMIT)
         .await;
     Ok(Json(results).into_response())
 }
+
+/// Lists the federations represented in the index, with how many IdPs each
+/// registered, for the discovery UI's federation filter.
+pub async fn list_federations(State(state): State<Arc<AppState>>) -> Response {
+    Json(state.federation_index.federations().await).into_response()
+}
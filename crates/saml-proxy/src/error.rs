@@ -9,8 +9,14 @@ pub enum Error {
     InvalidSamlRequest(String),
     #[error("invalid SAML response: {0}")]
     InvalidSamlResponse(String),
-    #[error("MDQ fetch failed: {0}")]
-    MdqFetchFailed(String),
+    #[error("assertion already used: {0}")]
+    ReplayedAssertion(String),
+    #[error("invalid request signature: {0}")]
+    InvalidSignature(String),
+    #[error("unknown service provider: {0}")]
+    UnknownServiceProvider(String),
+    #[error("IdP metadata fetch failed: {0}")]
+    MetadataFetchFailed(String),
     #[error("missing university selection")]
     MissingUniversitySelection,
     #[error(transparent)]
@@ -24,8 +30,11 @@ impl IntoResponse for Error {
             Error::InvalidSamlRequest(_) | Error::MissingUniversitySelection => {
                 StatusCode::BAD_REQUEST
             }
+            Error::InvalidSignature(_)
+            | Error::UnknownServiceProvider(_)
+            | Error::ReplayedAssertion(_) => StatusCode::FORBIDDEN,
             Error::InvalidSamlResponse(_) => StatusCode::BAD_GATEWAY,
-            Error::MdqFetchFailed(_) => StatusCode::BAD_GATEWAY,
+            Error::MetadataFetchFailed(_) => StatusCode::BAD_GATEWAY,
             Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
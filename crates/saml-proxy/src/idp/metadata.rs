@@ -45,7 +45,7 @@ fn build_metadata(state: &AppState) -> anyhow::Result<String> {
     };
 
     let idp_descriptor = IdpSsoDescriptor {
-        want_authn_requests_signed: Some(false),
+        want_authn_requests_signed: Some(state.config.require_signed_requests),
         protocol_support_enumeration: Some("urn:oasis:names:tc:SAML:2.0:protocol".to_string()),
         key_descriptors: vec![key_descriptor],
         name_id_formats: vec![
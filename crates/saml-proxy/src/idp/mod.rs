@@ -1,4 +1,5 @@
 pub mod metadata;
+mod signature;
 pub mod slo;
 pub mod sso;
 
@@ -0,0 +1,305 @@
+use crate::error::Error;
+use crate::registry::RegisteredSp;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use openssl::hash::MessageDigest;
+use openssl::sign::Verifier;
+use openssl::x509::X509;
+use samael::crypto::{Crypto, CryptoProvider};
+use samael::signature::Signature;
+
+// SHA-1 is deliberately absent: SPs still signing with it are rejected.
+const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const RSA_SHA384: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha384";
+const RSA_SHA512: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512";
+
+/// The binding a request arrived on, which decides where its signature is.
+pub(crate) enum Binding<'a> {
+    /// HTTP-Redirect: a detached signature over the raw query string.
+    Redirect {
+        query: &'a str,
+        sig_alg: Option<&'a str>,
+        signature: Option<&'a str>,
+    },
+    /// HTTP-POST: an enveloped XML signature on the root element.
+    Post,
+}
+
+impl Binding<'_> {
+    /// Verifies a request from `sp` against its registered signing
+    /// certificates. An unsigned request passes only if `sp` does not require
+    /// signed requests; a signature that is present is always checked.
+    pub(crate) fn verify(
+        &self,
+        sp: &RegisteredSp,
+        xml: &str,
+        root_id: &str,
+        root_signature: Option<&Signature>,
+    ) -> Result<(), Error> {
+        match (self, root_signature) {
+            (
+                Binding::Redirect {
+                    query,
+                    sig_alg,
+                    signature: Some(signature),
+                },
+                _,
+            ) => {
+                let sig_alg = sig_alg
+                    .ok_or_else(|| Error::InvalidSignature("Signature without SigAlg".into()))?;
+                verify_query(sp, query, sig_alg, signature)
+            }
+            (Binding::Post, Some(signature)) => verify_enveloped(sp, xml, root_id, signature),
+            _ if sp.require_signed_requests => Err(Error::InvalidSignature(format!(
+                "{} must sign its requests",
+                sp.entity_id
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn verify_query(
+    sp: &RegisteredSp,
+    query: &str,
+    sig_alg: &str,
+    signature: &str,
+) -> Result<(), Error> {
+    let digest = match sig_alg {
+        RSA_SHA256 => MessageDigest::sha256(),
+        RSA_SHA384 => MessageDigest::sha384(),
+        RSA_SHA512 => MessageDigest::sha512(),
+        other => {
+            return Err(Error::InvalidSignature(format!(
+                "unsupported SigAlg {other}"
+            )));
+        }
+    };
+
+    let signature = STANDARD
+        .decode(signature)
+        .map_err(|e| Error::InvalidSignature(format!("base64 decode failed: {e}")))?;
+    let octets = signed_octets(query)?;
+
+    let verified = sp.signing_certs.iter().any(|cert| {
+        let Ok(key) = X509::from_der(cert).and_then(|c| c.public_key()) else {
+            return false;
+        };
+        Verifier::new(digest, &key)
+            .and_then(|mut v| v.verify_oneshot(&signature, octets.as_bytes()))
+            .unwrap_or(false)
+    });
+
+    if verified {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature(format!(
+            "query signature does not match any certificate of {}",
+            sp.entity_id
+        )))
+    }
+}
+
+/// Rebuilds the octets an SP signs under the HTTP-Redirect binding from the
+/// parameters exactly as they were URL-encoded on the wire (SAML bindings
+/// 3.4.4.1). Re-encoding the decoded values would not reproduce them.
+pub(crate) fn signed_octets(query: &str) -> Result<String, Error> {
+    let raw = |key: &str| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+    };
+
+    let request = raw("SAMLRequest")
+        .ok_or_else(|| Error::InvalidSignature("query has no SAMLRequest".into()))?;
+    let sig_alg =
+        raw("SigAlg").ok_or_else(|| Error::InvalidSignature("query has no SigAlg".into()))?;
+
+    let mut octets = format!("SAMLRequest={request}");
+    if let Some(relay_state) = raw("RelayState") {
+        octets.push_str("&RelayState=");
+        octets.push_str(relay_state);
+    }
+    octets.push_str("&SigAlg=");
+    octets.push_str(sig_alg);
+    Ok(octets)
+}
+
+fn verify_enveloped(
+    sp: &RegisteredSp,
+    xml: &str,
+    root_id: &str,
+    signature: &Signature,
+) -> Result<(), Error> {
+    // A valid signature over some other element proves nothing about the
+    // request, so it must cover the root (signature wrapping).
+    let expected = format!("#{root_id}");
+    let covers_root = signature
+        .signed_info
+        .reference
+        .iter()
+        .all(|r| r.uri.as_deref() == Some(expected.as_str()));
+    if signature.signed_info.reference.is_empty() || !covers_root {
+        return Err(Error::InvalidSignature(
+            "signature does not reference the request root".into(),
+        ));
+    }
+
+    let verified = sp
+        .signing_certs
+        .iter()
+        .any(|cert| Crypto::verify_signed_xml(xml.as_bytes(), cert, Some("ID")).is_ok());
+
+    if verified {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature(format!(
+            "XML signature does not match any certificate of {}",
+            sp.entity_id
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::sign::Signer;
+    use openssl::x509::X509Builder;
+
+    fn keypair() -> (PKey<Private>, Vec<u8>) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        (key, builder.build().to_der().unwrap())
+    }
+
+    fn sp(cert: Vec<u8>, require_signed_requests: bool) -> RegisteredSp {
+        RegisteredSp {
+            entity_id: "https://sp.example.com".into(),
+            acs_endpoints: vec![],
+            slo_url: None,
+            signing_certs: vec![cert],
+            require_signed_requests,
+        }
+    }
+
+    fn sign(key: &PKey<Private>, octets: &str) -> String {
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        STANDARD.encode(signer.sign_oneshot_to_vec(octets.as_bytes()).unwrap())
+    }
+
+    const SIG_ALG: &str = "http%3A%2F%2Fwww.w3.org%2F2001%2F04%2Fxmldsig-more%23rsa-sha256";
+
+    fn signed_query(key: &PKey<Private>) -> (String, String) {
+        let octets = format!("SAMLRequest=abc%2Bdef&RelayState=xyz&SigAlg={SIG_ALG}");
+        let signature = sign(key, &octets);
+        let query = format!(
+            "SigAlg={SIG_ALG}&RelayState=xyz&SAMLRequest=abc%2Bdef&Signature={}",
+            urlencoding::encode(&signature)
+        );
+        (query, signature)
+    }
+
+    fn redirect<'a>(query: &'a str, signature: Option<&'a str>) -> Binding<'a> {
+        Binding::Redirect {
+            query,
+            sig_alg: Some(RSA_SHA256),
+            signature,
+        }
+    }
+
+    #[test]
+    fn signed_octets_use_canonical_order_and_raw_encoding() {
+        let query = format!("SigAlg={SIG_ALG}&RelayState=a%20b&SAMLRequest=x%2By&Signature=sig");
+        assert_eq!(
+            signed_octets(&query).unwrap(),
+            format!("SAMLRequest=x%2By&RelayState=a%20b&SigAlg={SIG_ALG}")
+        );
+    }
+
+    #[test]
+    fn signed_octets_without_relay_state() {
+        let query = format!("SAMLRequest=x&SigAlg={SIG_ALG}");
+        assert_eq!(
+            signed_octets(&query).unwrap(),
+            format!("SAMLRequest=x&SigAlg={SIG_ALG}")
+        );
+    }
+
+    #[test]
+    fn redirect_signature_verifies() {
+        let (key, cert) = keypair();
+        let (query, signature) = signed_query(&key);
+        redirect(&query, Some(&signature))
+            .verify(&sp(cert, true), "", "_req", None)
+            .unwrap();
+    }
+
+    #[test]
+    fn redirect_signature_from_other_key_is_rejected() {
+        let (key, _) = keypair();
+        let (_, cert) = keypair();
+        let (query, signature) = signed_query(&key);
+        let result = redirect(&query, Some(&signature)).verify(&sp(cert, false), "", "_req", None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn tampered_query_is_rejected() {
+        let (key, cert) = keypair();
+        let (query, signature) = signed_query(&key);
+        let tampered = query.replace("RelayState=xyz", "RelayState=evil");
+        let result =
+            redirect(&tampered, Some(&signature)).verify(&sp(cert, true), "", "_req", None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn sha1_is_rejected() {
+        let (key, cert) = keypair();
+        let (query, signature) = signed_query(&key);
+        let binding = Binding::Redirect {
+            query: &query,
+            sig_alg: Some("http://www.w3.org/2000/09/xmldsig#rsa-sha1"),
+            signature: Some(&signature),
+        };
+        assert!(binding.verify(&sp(cert, true), "", "_req", None).is_err());
+    }
+
+    #[test]
+    fn unsigned_request_rejected_when_required() {
+        let (_, cert) = keypair();
+        let query = "SAMLRequest=abc";
+        assert!(
+            redirect(query, None)
+                .verify(&sp(cert.clone(), true), "", "_req", None)
+                .is_err()
+        );
+        assert!(
+            Binding::Post
+                .verify(&sp(cert, true), "<xml/>", "_req", None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn unsigned_request_allowed_when_not_required() {
+        let (_, cert) = keypair();
+        redirect("SAMLRequest=abc", None)
+            .verify(&sp(cert.clone(), false), "", "_req", None)
+            .unwrap();
+        Binding::Post
+            .verify(&sp(cert, false), "<xml/>", "_req", None)
+            .unwrap();
+    }
+}
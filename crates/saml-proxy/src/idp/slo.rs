@@ -1,3 +1,4 @@
+use super::signature::Binding;
 use crate::error::Error;
 use crate::state::AppState;
 use axum::extract::State;
@@ -20,8 +21,10 @@ pub struct SloForm {
 }
 
 /// Receives a SAML LogoutRequest from a downstream Service Provider (HTTP-POST
-/// binding). Since the proxy has no persistent user sessions, this acknowledges
-/// the logout with a success LogoutResponse posted back to the SP.
+/// binding) and verifies its enveloped signature if present or required.
+/// Since the proxy has no persistent user sessions, this acknowledges the
+/// logout with a success LogoutResponse posted to the SP's registered
+/// SingleLogoutService.
 pub async fn slo_post(
     State(state): State<Arc<AppState>>,
     axum::Form(form): axum::Form<SloForm>,
@@ -42,7 +45,27 @@ pub async fn slo_post(
         .issuer
         .as_ref()
         .and_then(|i| i.value.as_deref())
-        .unwrap_or("unknown");
+        .ok_or_else(|| Error::InvalidSamlRequest("LogoutRequest missing Issuer".into()))?;
+
+    let sp = state
+        .service_providers
+        .get(issuer_value)
+        .ok_or_else(|| Error::UnknownServiceProvider(issuer_value.to_string()))?;
+
+    Binding::Post.verify(
+        sp,
+        &xml,
+        logout_request.id.as_deref().unwrap_or_default(),
+        logout_request.signature.as_ref(),
+    )?;
+
+    // Respond only to the SP's registered endpoint, never to a URL taken
+    // from the request.
+    let response_url = sp.slo_url.as_deref().ok_or_else(|| {
+        Error::InvalidSamlRequest(format!(
+            "{issuer_value} has no HTTP-POST SingleLogoutService"
+        ))
+    })?;
 
     tracing::info!(
         request_id,
@@ -57,7 +80,7 @@ pub async fn slo_post(
         in_response_to: logout_request.id.clone(),
         version: Some("2.0".into()),
         issue_instant: Some(chrono::Utc::now()),
-        destination: logout_request.destination.clone(),
+        destination: Some(response_url.to_string()),
         consent: None,
         issuer: Some(Issuer {
             value: Some(state.config.entity_id.clone()),
@@ -79,17 +102,6 @@ pub async fn slo_post(
 
     let b64 = STANDARD.encode(response_xml.as_bytes());
 
-    let response_url = logout_request
-        .destination
-        .as_deref()
-        .or_else(|| {
-            logout_request
-                .issuer
-                .as_ref()
-                .and_then(|i| i.value.as_deref())
-        })
-        .unwrap_or_default();
-
     let relay_state_input = form
         .relay_state
         .as_ref()
@@ -1,6 +1,8 @@
+use super::signature::Binding;
 use crate::error::Error;
+use crate::session::SessionStore;
 use crate::state::AppState;
-use axum::extract::{Query, State};
+use axum::extract::{Query, RawQuery, State};
 use axum::response::{IntoResponse, Redirect};
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
@@ -16,6 +18,10 @@ pub struct SsoRedirectParams {
     pub saml_request: String,
     #[serde(rename = "RelayState")]
     pub relay_state: Option<String>,
+    #[serde(rename = "SigAlg")]
+    pub sig_alg: Option<String>,
+    #[serde(rename = "Signature")]
+    pub signature: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -28,13 +34,20 @@ pub struct SsoPostParams {
 
 /// HTTP-Redirect binding: receives a deflated, base64-encoded AuthnRequest as
 /// a query parameter from a Service Provider, creates a session, and redirects
-/// the user to the discovery UI to select their university.
+/// the user to the discovery UI to select their university. A signature is
+/// carried in the `SigAlg` and `Signature` query parameters.
 pub async fn sso_redirect(
     State(state): State<Arc<AppState>>,
+    RawQuery(query): RawQuery,
     Query(params): Query<SsoRedirectParams>,
 ) -> Result<impl IntoResponse, Error> {
     let xml = decode_redirect_binding(&params.saml_request)?;
-    let session_id = process_authn_request(&state, &xml, params.relay_state)?;
+    let binding = Binding::Redirect {
+        query: query.as_deref().unwrap_or_default(),
+        sig_alg: params.sig_alg.as_deref(),
+        signature: params.signature.as_deref(),
+    };
+    let session_id = process_authn_request(&state, &xml, params.relay_state, binding).await?;
     Ok(Redirect::to(&format!("/discovery?session={session_id}")))
 }
 
@@ -46,7 +59,7 @@ pub async fn sso_post(
     axum::Form(params): axum::Form<SsoPostParams>,
 ) -> Result<impl IntoResponse, Error> {
     let xml = decode_post_binding(&params.saml_request)?;
-    let session_id = process_authn_request(&state, &xml, params.relay_state)?;
+    let session_id = process_authn_request(&state, &xml, params.relay_state, Binding::Post).await?;
     Ok(Redirect::to(&format!("/discovery?session={session_id}")))
 }
 
@@ -129,29 +142,44 @@ mod tests {
     }
 }
 
-/// Parses the AuthnRequest XML, extracts the SP's ACS URL and entity ID,
-/// and creates a session to track the authentication flow.
-fn process_authn_request(
+/// Parses the AuthnRequest XML, checks that its issuer is a registered
+/// Service Provider and that the request carries a valid signature if one is
+/// present or required, resolves the ACS URL against that SP's metadata, and
+/// creates a session to track the authentication flow.
+async fn process_authn_request(
     state: &AppState,
     xml: &str,
     relay_state: Option<String>,
+    binding: Binding<'_>,
 ) -> Result<String, Error> {
     let authn_request: AuthnRequest = xml
         .parse()
         .map_err(|e| Error::InvalidSamlRequest(format!("failed to parse AuthnRequest: {e}")))?;
 
-    let sp_acs_url = authn_request
-        .assertion_consumer_service_url
-        .clone()
-        .ok_or_else(|| {
-            Error::InvalidSamlRequest("AuthnRequest missing AssertionConsumerServiceURL".into())
-        })?;
+    let sp_entity_id = authn_request
+        .issuer_value()
+        .ok_or_else(|| Error::InvalidSamlRequest("AuthnRequest missing Issuer".into()))?;
 
-    let sp_entity_id = authn_request.issuer_value().unwrap_or_default();
+    let sp = state
+        .service_providers
+        .get(&sp_entity_id)
+        .ok_or_else(|| Error::UnknownServiceProvider(sp_entity_id.clone()))?;
+
+    binding.verify(sp, xml, &authn_request.id, authn_request.signature.as_ref())?;
+
+    // Never trust the ACS URL in the request itself: it decides where the
+    // signed assertion gets posted.
+    let sp_acs_url = sp
+        .resolve_acs(
+            authn_request.assertion_consumer_service_url.as_deref(),
+            authn_request.assertion_consumer_service_index,
+        )?
+        .to_string();
 
     let session_id = state
         .sessions
-        .create(authn_request.id, sp_acs_url, sp_entity_id, relay_state);
+        .create(authn_request.id, sp_acs_url, sp_entity_id, relay_state)
+        .await?;
 
     tracing::info!(session_id, "created session for incoming AuthnRequest");
     Ok(session_id)
@@ -1,11 +1,16 @@
+pub mod attribute_policy;
 pub mod attributes;
 pub mod config;
 pub mod discovery;
 pub mod error;
 pub mod idp;
+pub mod metadata_sources;
+pub mod registry;
+pub mod scopes;
 pub mod session;
 pub mod sp;
 pub mod state;
+mod xml;
 
 use axum::Router;
 use axum::http::{StatusCode, header};
@@ -37,6 +42,10 @@ pub fn app(state: Arc<AppState>) -> Router {
             "/api/entities/search",
             axum::routing::get(discovery::search_entities),
         )
+        .route(
+            "/api/federations",
+            axum::routing::get(discovery::list_federations),
+        )
         .route("/static/discovery.js", axum::routing::get(discovery_js))
         .with_state(state)
         .layer(TraceLayer::new_for_http())
@@ -1,4 +1,5 @@
 use saml_proxy::config::Config;
+use saml_proxy::session::{Replays, Sessions};
 use saml_proxy::state::AppState;
 use std::sync::Arc;
 use tokio::net::TcpListener;
@@ -17,14 +18,18 @@ async fn main() -> anyhow::Result<()> {
 
     let config = Config::from_env()?;
     let addr = format!("{}:{}", config.host, config.port);
-    let state = Arc::new(AppState::new(config)?);
+    let state = Arc::new(AppState::new(config).await?);
 
-    tokio::spawn(saml_proxy::session::session_cleanup_task(
-        state.sessions.clone(),
-    ));
+    if let Sessions::Memory(store) = &state.sessions {
+        tokio::spawn(saml_proxy::session::session_cleanup_task(store.clone()));
+    }
+    if let Replays::Memory(cache) = &state.replays {
+        tokio::spawn(saml_proxy::session::replay_cleanup_task(cache.clone()));
+    }
     tokio::spawn(
         saml_proxy::discovery::federation_index::federation_index_task(
             state.federation_index.clone(),
+            state.metadata.clone(),
         ),
     );
 
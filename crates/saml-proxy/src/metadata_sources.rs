@@ -0,0 +1,660 @@
+use crate::config::MetadataSourceConfig;
+use crate::discovery::federation_index::{
+    EntityEntry, entity_descriptor_fragments, merge, parse_idp_entries, parse_idp_fragment,
+};
+use crate::error::Error;
+use crate::scopes::Scopes;
+use crate::xml;
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use samael::crypto::{Crypto, CryptoProvider};
+use samael::metadata::EntityDescriptor;
+use saml_mdq::{MdqCache, MdqClient};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+const INCOMMON_SIGNING_CERT_PEM: &[u8] = include_bytes!("../certs/inc-md-cert-mdq.pem");
+
+/// How long MDQ responses are reused, both by the client and for scopes.
+const MDQ_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Where university IdP metadata comes from.
+enum Source {
+    /// Queried per entity, with signed responses cached by the client.
+    /// Discovery lists the server's `/entities` aggregate. The client only
+    /// returns parsed EntityDescriptors, so scopes are read from a separate
+    /// request for the same signed document.
+    Mdq {
+        client: MdqClient,
+        base_url: String,
+        signing_cert: Vec<u8>,
+        aggregate_url: String,
+        scopes: DashMap<String, (Instant, Scopes)>,
+        /// The discovery entries from the last successful read of the
+        /// aggregate.
+        entries: RwLock<Vec<EntityEntry>>,
+    },
+    /// A federation aggregate, read again on every index refresh.
+    Aggregate {
+        location: String,
+        signing_cert: Option<Vec<u8>>,
+        entities: RwLock<LocalEntities>,
+    },
+    /// A single EntityDescriptor, read once at startup.
+    Entity(LocalEntities),
+}
+
+/// The configured IdP metadata sources, consulted in priority order: the
+/// first source that knows an entity supplies its metadata, both for login
+/// and for the discovery index.
+pub struct MetadataSources {
+    sources: Vec<(String, Source)>,
+}
+
+impl MetadataSources {
+    pub async fn load(configs: &[MetadataSourceConfig]) -> Result<Self> {
+        anyhow::ensure!(!configs.is_empty(), "no IdP metadata sources configured");
+
+        let mut sources = Vec::with_capacity(configs.len());
+        for config in configs {
+            let (name, source) = match config {
+                MetadataSourceConfig::Mdq {
+                    base_url,
+                    signing_cert_path,
+                } => (
+                    base_url.clone(),
+                    mdq_source(base_url, signing_cert_path.as_deref())?,
+                ),
+                MetadataSourceConfig::Aggregate {
+                    source,
+                    signing_cert_path,
+                } => {
+                    // The IdP keys in an aggregate are trusted for assertion
+                    // signatures, so one fetched over the network must be
+                    // signed as well as served over TLS.
+                    anyhow::ensure!(
+                        !source.starts_with("http://"),
+                        "aggregate {source} must be fetched over https"
+                    );
+                    anyhow::ensure!(
+                        signing_cert_path.is_some() || !source.starts_with("https://"),
+                        "aggregate {source} needs a signing certificate"
+                    );
+                    let signing_cert = signing_cert_path
+                        .as_deref()
+                        .map(read_certificate)
+                        .transpose()?;
+                    let local = load_aggregate(source, signing_cert.as_deref()).await?;
+                    let aggregate = Source::Aggregate {
+                        location: source.clone(),
+                        signing_cert,
+                        entities: RwLock::new(local),
+                    };
+                    (source.clone(), aggregate)
+                }
+                MetadataSourceConfig::Entity { path } => {
+                    let local = local_entities(&read_metadata(path).await?);
+                    if local.entities.len() != 1 {
+                        anyhow::bail!("{path} must contain exactly one IdP EntityDescriptor");
+                    }
+                    (path.clone(), Source::Entity(local))
+                }
+            };
+            tracing::info!(source = name, "loaded IdP metadata source");
+            sources.push((name, source));
+        }
+        Ok(Self { sources })
+    }
+
+    /// Looks up an IdP's metadata in each source in turn. MDQ errors are
+    /// remembered rather than returned, so a lower-priority source can still
+    /// supply the entity.
+    pub async fn fetch_entity(&self, entity_id: &str) -> Result<EntityDescriptor, Error> {
+        let mut last_error = None;
+        for (name, source) in &self.sources {
+            let Source::Mdq { client, .. } = source else {
+                if let Some(entity) = source.local_entity(entity_id) {
+                    return Ok(entity);
+                }
+                continue;
+            };
+            match client.fetch_entity(entity_id).await {
+                Ok(entity) => return Ok(entity),
+                Err(e) => {
+                    tracing::debug!(source = name, entity_id, error = %e, "MDQ lookup failed");
+                    last_error = Some(format!("{name}: {e}"));
+                }
+            }
+        }
+        Err(Error::MetadataFetchFailed(last_error.unwrap_or_else(
+            || format!("{entity_id} is not in any metadata source"),
+        )))
+    }
+
+    /// The `shibmd:Scope`s authorized for an IdP, from the same source
+    /// [`fetch_entity`](Self::fetch_entity) takes its metadata from.
+    pub async fn scopes(&self, entity_id: &str) -> Result<Scopes, Error> {
+        let mut last_error = None;
+        for (name, source) in &self.sources {
+            let Source::Mdq {
+                base_url,
+                signing_cert,
+                scopes,
+                ..
+            } = source
+            else {
+                if let Some(scopes) = source.local_scopes(entity_id) {
+                    return Ok(scopes);
+                }
+                continue;
+            };
+            if let Some(cached) = scopes.get(entity_id)
+                && cached.0.elapsed() < MDQ_CACHE_TTL
+            {
+                return Ok(cached.1.clone());
+            }
+            match mdq_scopes(base_url, signing_cert, entity_id).await {
+                Ok(fetched) => {
+                    scopes.insert(entity_id.to_string(), (Instant::now(), fetched.clone()));
+                    return Ok(fetched);
+                }
+                Err(e) => {
+                    tracing::debug!(source = name, entity_id, error = %e, "MDQ scope lookup failed");
+                    last_error = Some(format!("{name}: {e}"));
+                }
+            }
+        }
+        Err(Error::MetadataFetchFailed(last_error.unwrap_or_else(
+            || format!("{entity_id} is not in any metadata source"),
+        )))
+    }
+
+    /// Every IdP known to any source, for the discovery index. Aggregates are
+    /// read again first, so login sees the same entities as discovery. A
+    /// source that cannot be read is skipped with a warning and keeps the
+    /// entities it last supplied, for login as well as discovery, so one
+    /// federation's outage does not hold back every other source.
+    pub async fn idp_entries(&self) -> Vec<EntityEntry> {
+        let mut aggregates = Vec::with_capacity(self.sources.len());
+        for (name, source) in &self.sources {
+            aggregates.push(match source.reload().await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    tracing::warn!(
+                        source = name,
+                        error = %e,
+                        "failed to read IdP metadata source, keeping its previous entities"
+                    );
+                    source.entries()
+                }
+            });
+        }
+        merge(aggregates)
+    }
+}
+
+impl Source {
+    /// Reads the source's discovery entries again, replacing what login
+    /// reads an aggregate's entities from only once the new copy is good.
+    async fn reload(&self) -> Result<Vec<EntityEntry>> {
+        match self {
+            Source::Entity(local) => Ok(local.entries.clone()),
+            Source::Aggregate {
+                location,
+                signing_cert,
+                entities,
+            } => {
+                let local = load_aggregate(location, signing_cert.as_deref()).await?;
+                let entries = local.entries.clone();
+                *entities.write().expect("metadata lock poisoned") = local;
+                Ok(entries)
+            }
+            Source::Mdq {
+                aggregate_url,
+                entries,
+                ..
+            } => {
+                let fresh = parse_idp_entries(&read_metadata(aggregate_url).await?);
+                *entries.write().expect("metadata lock poisoned") = fresh.clone();
+                Ok(fresh)
+            }
+        }
+    }
+
+    /// The discovery entries from the last successful read.
+    fn entries(&self) -> Vec<EntityEntry> {
+        match self {
+            Source::Entity(local) => local.entries.clone(),
+            Source::Aggregate { entities, .. } => entities
+                .read()
+                .expect("metadata lock poisoned")
+                .entries
+                .clone(),
+            Source::Mdq { entries, .. } => entries.read().expect("metadata lock poisoned").clone(),
+        }
+    }
+
+    /// Looks an entity up in a local source. The aggregate lock is released
+    /// before returning, so it is never held across an await.
+    fn local_entity(&self, entity_id: &str) -> Option<EntityDescriptor> {
+        match self {
+            Source::Entity(local) => local.entities.get(entity_id).cloned(),
+            Source::Aggregate { entities, .. } => entities
+                .read()
+                .expect("metadata lock poisoned")
+                .entities
+                .get(entity_id)
+                .cloned(),
+            Source::Mdq { .. } => None,
+        }
+    }
+
+    fn local_scopes(&self, entity_id: &str) -> Option<Scopes> {
+        match self {
+            Source::Entity(local) => local.scopes.get(entity_id).cloned(),
+            Source::Aggregate { entities, .. } => entities
+                .read()
+                .expect("metadata lock poisoned")
+                .scopes
+                .get(entity_id)
+                .cloned(),
+            Source::Mdq { .. } => None,
+        }
+    }
+}
+
+pub(crate) fn read_certificate(path: &str) -> Result<Vec<u8>> {
+    let pem = std::fs::read(path).with_context(|| format!("failed to read certificate {path}"))?;
+    openssl::x509::X509::from_pem(&pem)
+        .and_then(|cert| cert.to_der())
+        .with_context(|| format!("failed to parse certificate {path}"))
+}
+
+fn mdq_source(base_url: &str, signing_cert_path: Option<&str>) -> Result<Source> {
+    let cert_der = match signing_cert_path {
+        Some(path) => read_certificate(path)?,
+        None => openssl::x509::X509::from_pem(INCOMMON_SIGNING_CERT_PEM)
+            .and_then(|cert| cert.to_der())
+            .context("failed to parse bundled InCommon MDQ certificate")?,
+    };
+
+    let base_url = base_url.trim_end_matches('/');
+    let client = MdqClient::builder(base_url)
+        .cache(MdqCache::new(1000, MDQ_CACHE_TTL))
+        .signing_cert(cert_der.clone())
+        .build()
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    Ok(Source::Mdq {
+        client,
+        base_url: base_url.to_string(),
+        signing_cert: cert_der,
+        aggregate_url: format!("{base_url}/entities"),
+        scopes: DashMap::new(),
+        entries: RwLock::new(Vec::new()),
+    })
+}
+
+/// Reads an entity's scopes from its signed MDQ document.
+async fn mdq_scopes(base_url: &str, signing_cert: &[u8], entity_id: &str) -> Result<Scopes> {
+    let url = format!("{base_url}/entities/{}", mdq_encode(entity_id));
+    let xml = read_metadata(&url).await?;
+    Crypto::verify_signed_xml(xml.as_bytes(), signing_cert, Some("ID"))
+        .map_err(|e| anyhow::anyhow!("signature check failed for {url}: {e}"))?;
+    let signed = xml::signed_content(&xml).with_context(|| format!("{url} is not signed"))?;
+    Ok(Scopes::from_metadata(&signed))
+}
+
+/// Percent-encodes an entity ID as one MDQ path segment.
+fn mdq_encode(entity_id: &str) -> String {
+    entity_id
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(b).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Reads an aggregate, checking its enveloped signature when a signing
+/// certificate is configured. Entities are then read only from the signed
+/// content.
+async fn load_aggregate(location: &str, signing_cert: Option<&[u8]>) -> Result<LocalEntities> {
+    let mut xml = read_metadata(location).await?;
+    if let Some(cert) = signing_cert {
+        Crypto::verify_signed_xml(xml.as_bytes(), cert, Some("ID"))
+            .map_err(|e| anyhow::anyhow!("signature check failed for {location}: {e}"))?;
+        xml = xml::signed_content(&xml).with_context(|| format!("{location} is not signed"))?;
+    }
+
+    let local = local_entities(&xml);
+    if local.entities.is_empty() {
+        anyhow::bail!("no IDPSSODescriptor found in {location}");
+    }
+    tracing::info!(
+        source = location,
+        count = local.entities.len(),
+        "read IdP metadata aggregate"
+    );
+    Ok(local)
+}
+
+/// The IdP entities of a local source, keyed by entity ID, with their
+/// scopes and discovery entries.
+struct LocalEntities {
+    entities: HashMap<String, EntityDescriptor>,
+    scopes: HashMap<String, Scopes>,
+    entries: Vec<EntityEntry>,
+}
+
+/// Collects the IdP entities in `xml`, which may be a single
+/// EntityDescriptor or an EntitiesDescriptor aggregate. Commented-out
+/// entities are not collected.
+fn local_entities(xml: &str) -> LocalEntities {
+    let xml = xml::without_comments(xml);
+    let mut fragments = entity_descriptor_fragments(&xml);
+    if fragments.is_empty() {
+        fragments.push(xml.as_ref().into());
+    }
+
+    let mut entities = HashMap::new();
+    let mut scopes = HashMap::new();
+    let mut entries = Vec::new();
+    for fragment in &fragments {
+        let Some((entity, entry)) = parse_idp_fragment(fragment) else {
+            continue;
+        };
+        entities.insert(entry.entity_id.clone(), entity);
+        scopes.insert(entry.entity_id.clone(), Scopes::from_metadata(fragment));
+        entries.push(entry);
+    }
+
+    LocalEntities {
+        entities,
+        scopes,
+        entries,
+    }
+}
+
+/// Reads metadata from a local file or an http(s) URL.
+pub(crate) async fn read_metadata(source: &str) -> Result<String> {
+    if source.starts_with("https://") || source.starts_with("http://") {
+        Ok(reqwest::get(source)
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("failed to fetch metadata from {source}"))?
+            .text()
+            .await?)
+    } else {
+        tokio::fs::read_to_string(source)
+            .await
+            .with_context(|| format!("failed to read metadata file {source}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGGREGATE_XML: &str = r#"<EntitiesDescriptor xmlns="urn:oasis:names:tc:SAML:2.0:metadata">
+<EntityDescriptor entityID="https://idp.example.edu">
+    <IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+        <SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" Location="https://idp.example.edu/sso"/>
+    </IDPSSODescriptor>
+    <Organization>
+        <OrganizationDisplayName xml:lang="en">Example University</OrganizationDisplayName>
+    </Organization>
+</EntityDescriptor>
+<EntityDescriptor entityID="https://sp.example.com">
+    <SPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+        <AssertionConsumerService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="https://sp.example.com/acs" index="0"/>
+    </SPSSODescriptor>
+</EntityDescriptor>
+</EntitiesDescriptor>"#;
+
+    fn entity_xml(entity_id: &str, name: &str, sso: &str) -> String {
+        format!(
+            r#"<EntityDescriptor xmlns="urn:oasis:names:tc:SAML:2.0:metadata" entityID="{entity_id}">
+    <IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+        <SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" Location="{sso}"/>
+    </IDPSSODescriptor>
+    <Organization>
+        <OrganizationDisplayName xml:lang="en">{name}</OrganizationDisplayName>
+    </Organization>
+</EntityDescriptor>"#
+        )
+    }
+
+    fn local(xml: &str) -> (String, Source) {
+        ("test".into(), Source::Entity(local_entities(xml)))
+    }
+
+    fn sso_location(entity: &EntityDescriptor) -> &str {
+        &entity.idp_sso_descriptors.as_ref().unwrap()[0].single_sign_on_services[0].location
+    }
+
+    #[test]
+    fn local_entities_keeps_idps_only() {
+        let local = local_entities(AGGREGATE_XML);
+        assert_eq!(local.entries.len(), 1);
+        assert!(local.entities.contains_key("https://idp.example.edu"));
+        assert!(!local.entities.contains_key("https://sp.example.com"));
+    }
+
+    #[test]
+    fn local_entities_reads_single_entity() {
+        let local = local_entities(&entity_xml(
+            "https://idp.test",
+            "Test University",
+            "https://idp.test/sso",
+        ));
+        assert_eq!(local.entries[0].display_name, "Test University");
+        assert!(local.entities.contains_key("https://idp.test"));
+    }
+
+    #[tokio::test]
+    async fn fetch_entity_prefers_earlier_sources() {
+        let sources = MetadataSources {
+            sources: vec![
+                local(&entity_xml(
+                    "https://idp.example.edu",
+                    "Override",
+                    "https://override.example.edu/sso",
+                )),
+                local(AGGREGATE_XML),
+            ],
+        };
+
+        let entity = sources
+            .fetch_entity("https://idp.example.edu")
+            .await
+            .unwrap();
+        assert_eq!(sso_location(&entity), "https://override.example.edu/sso");
+    }
+
+    #[tokio::test]
+    async fn fetch_entity_falls_through_to_later_sources() {
+        let sources = MetadataSources {
+            sources: vec![
+                local(&entity_xml(
+                    "https://idp.test",
+                    "Test",
+                    "https://idp.test/sso",
+                )),
+                local(AGGREGATE_XML),
+            ],
+        };
+
+        let entity = sources
+            .fetch_entity("https://idp.example.edu")
+            .await
+            .unwrap();
+        assert_eq!(sso_location(&entity), "https://idp.example.edu/sso");
+        assert!(matches!(
+            sources.fetch_entity("https://unknown.example.edu").await,
+            Err(Error::MetadataFetchFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn idp_entries_merge_in_priority_order() {
+        let sources = MetadataSources {
+            sources: vec![
+                local(&entity_xml(
+                    "https://idp.example.edu",
+                    "Override",
+                    "https://override.example.edu/sso",
+                )),
+                local(AGGREGATE_XML),
+                local(&entity_xml(
+                    "https://idp.test",
+                    "A Test University",
+                    "https://idp.test/sso",
+                )),
+            ],
+        };
+
+        let entries = sources.idp_entries().await;
+        let names: Vec<&str> = entries.iter().map(|e| e.display_name.as_str()).collect();
+        assert_eq!(names, ["A Test University", "Override"]);
+    }
+
+    #[tokio::test]
+    async fn failing_aggregate_keeps_its_last_entities() {
+        let path =
+            std::env::temp_dir().join(format!("saml-proxy-aggregate-{}.xml", std::process::id()));
+        std::fs::write(&path, AGGREGATE_XML).unwrap();
+        let location = path.to_str().unwrap().to_string();
+        let aggregate = Source::Aggregate {
+            location: location.clone(),
+            signing_cert: None,
+            entities: RwLock::new(load_aggregate(&location, None).await.unwrap()),
+        };
+        let sources = MetadataSources {
+            sources: vec![
+                (location, aggregate),
+                local(&entity_xml(
+                    "https://idp.test",
+                    "A Test University",
+                    "https://idp.test/sso",
+                )),
+            ],
+        };
+
+        // The federation now serves something unusable.
+        std::fs::write(&path, "<EntitiesDescriptor/>").unwrap();
+        let entries = sources.idp_entries().await;
+        std::fs::remove_file(&path).unwrap();
+
+        let names: Vec<&str> = entries.iter().map(|e| e.display_name.as_str()).collect();
+        assert_eq!(names, ["A Test University", "Example University"]);
+        let entity = sources
+            .fetch_entity("https://idp.example.edu")
+            .await
+            .unwrap();
+        assert_eq!(sso_location(&entity), "https://idp.example.edu/sso");
+    }
+
+    #[tokio::test]
+    async fn scopes_come_from_the_entity_source() {
+        let scoped = entity_xml(
+            "https://idp.example.edu",
+            "Scoped",
+            "https://idp.example.edu/sso",
+        )
+        .replace(
+            "<SingleSignOnService",
+            r#"<Extensions><shibmd:Scope xmlns:shibmd="urn:mace:shibboleth:metadata:1.0">example.edu</shibmd:Scope></Extensions>
+        <SingleSignOnService"#,
+        );
+        let sources = MetadataSources {
+            sources: vec![local(&scoped), local(AGGREGATE_XML)],
+        };
+
+        let scopes = sources.scopes("https://idp.example.edu").await.unwrap();
+        assert!(scopes.authorizes("example.edu"));
+        assert!(!scopes.authorizes("other.edu"));
+
+        assert!(sources.scopes("https://idp.unknown.edu").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn commented_out_entities_and_scopes_are_ignored() {
+        let xml = entity_xml(
+            "https://idp.example.edu",
+            "Commented",
+            "https://idp.example.edu/sso",
+        )
+        .replace(
+            "<SingleSignOnService",
+            r#"<Extensions><!-- <shibmd:Scope>evil.com</shibmd:Scope> --></Extensions>
+        <SingleSignOnService"#,
+        );
+        let aggregate = AGGREGATE_XML.replace(
+            "</EntitiesDescriptor>",
+            &format!(
+                "<!-- {} --></EntitiesDescriptor>",
+                entity_xml(
+                    "https://idp.evil.example",
+                    "Evil",
+                    "https://idp.evil.example/sso",
+                )
+            ),
+        );
+        let sources = MetadataSources {
+            sources: vec![local(&xml), local(&aggregate)],
+        };
+
+        let scopes = sources.scopes("https://idp.example.edu").await.unwrap();
+        assert!(!scopes.authorizes("evil.com"));
+        assert!(
+            sources
+                .fetch_entity("https://idp.evil.example")
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn entity_without_scopes_has_none() {
+        let sources = MetadataSources {
+            sources: vec![local(AGGREGATE_XML)],
+        };
+        let scopes = sources.scopes("https://idp.example.edu").await.unwrap();
+        assert!(scopes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn remote_aggregates_must_be_signed_and_use_https() {
+        for (source, signing_cert_path) in [
+            ("https://mds.example.org/aggregate.xml", None),
+            ("http://mds.example.org/aggregate.xml", None),
+            (
+                "http://mds.example.org/aggregate.xml",
+                Some("certs/aggregate.pem"),
+            ),
+        ] {
+            let config = MetadataSourceConfig::Aggregate {
+                source: source.into(),
+                signing_cert_path: signing_cert_path.map(String::from),
+            };
+            let error = MetadataSources::load(&[config]).await.err().unwrap();
+            assert!(error.to_string().starts_with("aggregate "), "{error}");
+        }
+    }
+
+    #[test]
+    fn mdq_encodes_entity_ids() {
+        assert_eq!(
+            mdq_encode("https://idp.example.edu/idp/shibboleth"),
+            "https%3A%2F%2Fidp.example.edu%2Fidp%2Fshibboleth"
+        );
+        assert_eq!(
+            mdq_encode("urn:mace:example.edu"),
+            "urn%3Amace%3Aexample.edu"
+        );
+    }
+}
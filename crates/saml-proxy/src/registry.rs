@@ -0,0 +1,483 @@
+use crate::config::SpMetadataSource;
+use crate::discovery::federation_index::entity_descriptor_fragments;
+use crate::error::Error;
+use crate::metadata_sources::{read_certificate, read_metadata};
+use crate::xml;
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use samael::crypto::{Crypto, CryptoProvider};
+use samael::metadata::{EntityDescriptor, HTTP_POST_BINDING};
+use std::collections::HashMap;
+
+/// An Assertion Consumer Service endpoint from an SP's registered metadata.
+/// Only HTTP-POST endpoints are kept, since that is the only binding the
+/// proxy delivers responses with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AcsEndpoint {
+    pub location: String,
+    pub index: u16,
+    pub is_default: Option<bool>,
+}
+
+/// A downstream Service Provider the proxy is willing to issue assertions to.
+#[derive(Clone, Debug)]
+pub struct RegisteredSp {
+    pub entity_id: String,
+    pub acs_endpoints: Vec<AcsEndpoint>,
+    /// HTTP-POST SingleLogoutService URL to send LogoutResponses to.
+    pub slo_url: Option<String>,
+    /// DER certificates from the SP's signing KeyDescriptors.
+    pub signing_certs: Vec<Vec<u8>>,
+    /// Set when the SP's metadata declares `AuthnRequestsSigned="true"` or
+    /// the proxy requires every SP to sign.
+    pub require_signed_requests: bool,
+}
+
+impl RegisteredSp {
+    /// Resolves the ACS URL to deliver the assertion to from the
+    /// AuthnRequest's `AssertionConsumerServiceURL` or
+    /// `AssertionConsumerServiceIndex`, falling back to the default endpoint
+    /// when neither is given. A requested URL must exactly match one of the
+    /// SP's registered locations; anything else is treated as spoofed.
+    pub fn resolve_acs(&self, url: Option<&str>, index: Option<usize>) -> Result<&str, Error> {
+        let endpoint = match (url, index) {
+            (Some(_), Some(_)) => {
+                return Err(Error::InvalidSamlRequest(
+                    "AuthnRequest specifies both AssertionConsumerServiceURL and \
+                     AssertionConsumerServiceIndex"
+                        .into(),
+                ));
+            }
+            (Some(url), None) => self
+                .acs_endpoints
+                .iter()
+                .find(|e| e.location == url)
+                .ok_or_else(|| {
+                    Error::InvalidSamlRequest(format!(
+                        "AssertionConsumerServiceURL {url} is not registered for {}",
+                        self.entity_id
+                    ))
+                })?,
+            (None, Some(index)) => self
+                .acs_endpoints
+                .iter()
+                .find(|e| usize::from(e.index) == index)
+                .ok_or_else(|| {
+                    Error::InvalidSamlRequest(format!(
+                        "AssertionConsumerServiceIndex {index} is not registered for {}",
+                        self.entity_id
+                    ))
+                })?,
+            (None, None) => self.default_acs().ok_or_else(|| {
+                Error::InvalidSamlRequest(format!(
+                    "{} has no HTTP-POST AssertionConsumerService",
+                    self.entity_id
+                ))
+            })?,
+        };
+        Ok(&endpoint.location)
+    }
+
+    /// The endpoint marked `isDefault="true"`, else the first one not marked
+    /// `isDefault="false"`, else the first one (SAML metadata 2.2.3).
+    fn default_acs(&self) -> Option<&AcsEndpoint> {
+        self.acs_endpoints
+            .iter()
+            .find(|e| e.is_default == Some(true))
+            .or_else(|| {
+                self.acs_endpoints
+                    .iter()
+                    .find(|e| e.is_default != Some(false))
+            })
+            .or_else(|| self.acs_endpoints.first())
+    }
+}
+
+/// Service Providers allowed to authenticate through the proxy, keyed by
+/// entity ID and loaded from their SAML metadata. AuthnRequests and
+/// LogoutRequests from any other issuer are rejected.
+#[derive(Clone, Debug, Default)]
+pub struct ServiceProviderRegistry {
+    providers: HashMap<String, RegisteredSp>,
+    require_signed_requests: bool,
+}
+
+impl ServiceProviderRegistry {
+    /// An empty registry. With `require_signed_requests`, every SP must sign
+    /// its AuthnRequests and LogoutRequests regardless of its metadata.
+    pub fn new(require_signed_requests: bool) -> Self {
+        Self {
+            providers: HashMap::new(),
+            require_signed_requests,
+        }
+    }
+
+    /// Loads SP metadata from each source. A source with a signing
+    /// certificate has its signature checked and only its signed content
+    /// read; a URL must be `https://` and have one, so only a local file is
+    /// trusted as-is.
+    pub async fn load(
+        sources: &[SpMetadataSource],
+        require_signed_requests: bool,
+    ) -> anyhow::Result<Self> {
+        let mut registry = Self::new(require_signed_requests);
+        for SpMetadataSource {
+            source,
+            signing_cert_path,
+        } in sources
+        {
+            // SP metadata decides where assertions are delivered and which
+            // keys verify requests, so metadata fetched over the network must
+            // be signed as well as served over TLS.
+            anyhow::ensure!(
+                !source.starts_with("http://"),
+                "SP metadata {source} must be fetched over https"
+            );
+            anyhow::ensure!(
+                signing_cert_path.is_some() || !source.starts_with("https://"),
+                "SP metadata {source} needs a signing certificate"
+            );
+            let mut xml = read_metadata(source).await?;
+            if let Some(path) = signing_cert_path {
+                let cert = read_certificate(path)?;
+                Crypto::verify_signed_xml(xml.as_bytes(), &cert, Some("ID"))
+                    .map_err(|e| anyhow::anyhow!("signature check failed for {source}: {e}"))?;
+                xml =
+                    xml::signed_content(&xml).with_context(|| format!("{source} is not signed"))?;
+            }
+
+            let count = registry.add_metadata(&xml);
+            if count == 0 {
+                anyhow::bail!("no SPSSODescriptor found in {source}");
+            }
+            if let Some(sp) = registry
+                .providers
+                .values()
+                .find(|sp| sp.require_signed_requests && sp.signing_certs.is_empty())
+            {
+                anyhow::bail!(
+                    "{} must sign its requests but has no signing certificate",
+                    sp.entity_id
+                );
+            }
+            tracing::info!(source, count, "loaded service provider metadata");
+        }
+        Ok(registry)
+    }
+
+    /// Registers every SP entity in `xml`, which may be a single
+    /// EntityDescriptor or an EntitiesDescriptor aggregate. Returns how many
+    /// were registered. Commented-out entities, which a signature does not
+    /// cover, are not registered.
+    pub fn add_metadata(&mut self, xml: &str) -> usize {
+        let xml = xml::without_comments(xml);
+        let mut fragments = entity_descriptor_fragments(&xml);
+        if fragments.is_empty() {
+            fragments.push(xml.as_ref().into());
+        }
+
+        let mut count = 0;
+        for fragment in fragments {
+            if !fragment.contains("SPSSODescriptor") {
+                continue;
+            }
+
+            let entity: EntityDescriptor = match fragment.parse() {
+                Ok(e) => e,
+                Err(e) => {
+                    tracing::warn!(error = %e, "skipping unparseable SP EntityDescriptor");
+                    continue;
+                }
+            };
+
+            if let Some(sp) = registered_sp(entity, self.require_signed_requests) {
+                self.providers.insert(sp.entity_id.clone(), sp);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    pub fn get(&self, entity_id: &str) -> Option<&RegisteredSp> {
+        self.providers.get(entity_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.providers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+}
+
+fn registered_sp(entity: EntityDescriptor, require_signed_requests: bool) -> Option<RegisteredSp> {
+    let entity_id = entity.entity_id?;
+    let descriptors = entity.sp_sso_descriptors.unwrap_or_default();
+
+    let acs_endpoints = descriptors
+        .iter()
+        .flat_map(|d| &d.assertion_consumer_services)
+        .filter(|e| e.binding == HTTP_POST_BINDING)
+        .map(|e| AcsEndpoint {
+            location: e.location.clone(),
+            index: e.index,
+            is_default: e.is_default,
+        })
+        .collect();
+
+    let slo_url = descriptors
+        .iter()
+        .flat_map(|d| d.single_logout_services.iter().flatten())
+        .find(|e| e.binding == HTTP_POST_BINDING)
+        .map(|e| {
+            e.response_location
+                .clone()
+                .unwrap_or_else(|| e.location.clone())
+        });
+
+    // A KeyDescriptor without `use` applies to both signing and encryption.
+    let signing_certs = descriptors
+        .iter()
+        .flat_map(|d| d.key_descriptors.iter().flatten())
+        .filter(|k| k.key_use.as_deref().is_none_or(|u| u == "signing"))
+        .filter_map(|k| k.key_info.x509_data.as_ref())
+        .flat_map(|x| &x.certificates)
+        .filter_map(|cert| {
+            let b64: String = cert.split_whitespace().collect();
+            STANDARD.decode(b64).ok()
+        })
+        .collect();
+
+    let require_signed_requests = require_signed_requests
+        || descriptors
+            .iter()
+            .any(|d| d.authn_requests_signed == Some(true));
+
+    Some(RegisteredSp {
+        entity_id,
+        acs_endpoints,
+        slo_url,
+        signing_certs,
+        require_signed_requests,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SP_METADATA: &str = r#"<EntityDescriptor xmlns="urn:oasis:names:tc:SAML:2.0:metadata" entityID="https://sp.example.com">
+    <SPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+        <SingleLogoutService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="https://sp.example.com/slo"/>
+        <AssertionConsumerService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Artifact" Location="https://sp.example.com/artifact" index="0"/>
+        <AssertionConsumerService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="https://sp.example.com/acs" index="1"/>
+        <AssertionConsumerService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="https://sp.example.com/acs2" index="2" isDefault="true"/>
+    </SPSSODescriptor>
+</EntityDescriptor>"#;
+
+    fn sp(endpoints: &[(&str, u16, Option<bool>)]) -> RegisteredSp {
+        RegisteredSp {
+            entity_id: "https://sp.example.com".into(),
+            acs_endpoints: endpoints
+                .iter()
+                .map(|&(location, index, is_default)| AcsEndpoint {
+                    location: location.into(),
+                    index,
+                    is_default,
+                })
+                .collect(),
+            slo_url: None,
+            signing_certs: vec![],
+            require_signed_requests: false,
+        }
+    }
+
+    #[test]
+    fn add_metadata_keeps_post_endpoints_only() {
+        let mut registry = ServiceProviderRegistry::default();
+        assert_eq!(registry.add_metadata(SP_METADATA), 1);
+
+        let sp = registry.get("https://sp.example.com").unwrap();
+        let locations: Vec<_> = sp
+            .acs_endpoints
+            .iter()
+            .map(|e| e.location.as_str())
+            .collect();
+        assert_eq!(
+            locations,
+            ["https://sp.example.com/acs", "https://sp.example.com/acs2"]
+        );
+        assert_eq!(sp.slo_url.as_deref(), Some("https://sp.example.com/slo"));
+    }
+
+    #[test]
+    fn signing_requirement_from_metadata_or_registry() {
+        let signed = SP_METADATA.replace(
+            "<SPSSODescriptor ",
+            r#"<SPSSODescriptor AuthnRequestsSigned="true" "#,
+        );
+
+        let mut registry = ServiceProviderRegistry::default();
+        registry.add_metadata(SP_METADATA);
+        assert!(
+            !registry
+                .get("https://sp.example.com")
+                .unwrap()
+                .require_signed_requests
+        );
+
+        registry.add_metadata(&signed);
+        assert!(
+            registry
+                .get("https://sp.example.com")
+                .unwrap()
+                .require_signed_requests
+        );
+
+        let mut strict = ServiceProviderRegistry::new(true);
+        strict.add_metadata(SP_METADATA);
+        assert!(
+            strict
+                .get("https://sp.example.com")
+                .unwrap()
+                .require_signed_requests
+        );
+    }
+
+    #[test]
+    fn collects_signing_certificates() {
+        let with_keys = SP_METADATA.replace(
+            "<SingleLogoutService",
+            r#"<KeyDescriptor use="signing"><ds:KeyInfo xmlns:ds="http://www.w3.org/2000/09/xmldsig#"><ds:X509Data><ds:X509Certificate>
+            AQID
+            </ds:X509Certificate></ds:X509Data></ds:KeyInfo></KeyDescriptor>
+        <KeyDescriptor use="encryption"><ds:KeyInfo xmlns:ds="http://www.w3.org/2000/09/xmldsig#"><ds:X509Data><ds:X509Certificate>BAUG</ds:X509Certificate></ds:X509Data></ds:KeyInfo></KeyDescriptor>
+        <SingleLogoutService"#,
+        );
+
+        let mut registry = ServiceProviderRegistry::default();
+        registry.add_metadata(&with_keys);
+        let sp = registry.get("https://sp.example.com").unwrap();
+        assert_eq!(sp.signing_certs, [vec![1, 2, 3]]);
+    }
+
+    #[test]
+    fn add_metadata_skips_idp_entities() {
+        let xml = r#"<EntitiesDescriptor xmlns="urn:oasis:names:tc:SAML:2.0:metadata">
+<EntityDescriptor entityID="https://idp.example.edu">
+    <IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+        <SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" Location="https://idp.example.edu/sso"/>
+    </IDPSSODescriptor>
+</EntityDescriptor>
+</EntitiesDescriptor>"#;
+
+        let mut registry = ServiceProviderRegistry::default();
+        assert_eq!(registry.add_metadata(xml), 0);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn unknown_entity_is_not_registered() {
+        let mut registry = ServiceProviderRegistry::default();
+        registry.add_metadata(SP_METADATA);
+        assert!(registry.get("https://attacker.example.com").is_none());
+    }
+
+    #[test]
+    fn resolve_registered_url() {
+        let sp = sp(&[("https://sp.example.com/acs", 0, None)]);
+        let url = sp.resolve_acs(Some("https://sp.example.com/acs"), None);
+        assert_eq!(url.unwrap(), "https://sp.example.com/acs");
+    }
+
+    #[test]
+    fn resolve_rejects_spoofed_url() {
+        let sp = sp(&[("https://sp.example.com/acs", 0, None)]);
+        for spoofed in [
+            "https://attacker.example.com/acs",
+            "https://sp.example.com/acs/../evil",
+            "https://sp.example.com/acs?next=https://attacker.example.com",
+            "https://sp.example.com.attacker.example.com/acs",
+        ] {
+            assert!(sp.resolve_acs(Some(spoofed), None).is_err(), "{spoofed}");
+        }
+    }
+
+    #[test]
+    fn resolve_by_index() {
+        let sp = sp(&[
+            ("https://sp.example.com/acs", 0, None),
+            ("https://sp.example.com/acs2", 5, None),
+        ]);
+        assert_eq!(
+            sp.resolve_acs(None, Some(5)).unwrap(),
+            "https://sp.example.com/acs2"
+        );
+        assert!(sp.resolve_acs(None, Some(1)).is_err());
+    }
+
+    #[test]
+    fn resolve_rejects_url_and_index_together() {
+        let sp = sp(&[("https://sp.example.com/acs", 0, None)]);
+        assert!(
+            sp.resolve_acs(Some("https://sp.example.com/acs"), Some(0))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn resolve_default_prefers_is_default() {
+        let sp = sp(&[
+            ("https://sp.example.com/a", 0, Some(false)),
+            ("https://sp.example.com/b", 1, None),
+            ("https://sp.example.com/c", 2, Some(true)),
+        ]);
+        assert_eq!(
+            sp.resolve_acs(None, None).unwrap(),
+            "https://sp.example.com/c"
+        );
+    }
+
+    #[test]
+    fn resolve_default_skips_explicit_non_default() {
+        let sp = sp(&[
+            ("https://sp.example.com/a", 0, Some(false)),
+            ("https://sp.example.com/b", 1, None),
+        ]);
+        assert_eq!(
+            sp.resolve_acs(None, None).unwrap(),
+            "https://sp.example.com/b"
+        );
+    }
+
+    #[test]
+    fn resolve_without_endpoints_fails() {
+        let sp = sp(&[]);
+        assert!(sp.resolve_acs(None, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn remote_metadata_must_be_signed_https() {
+        for (source, reason) in [
+            (
+                "http://sp.example.com/metadata",
+                "must be fetched over https",
+            ),
+            (
+                "http://sp.example.com/metadata certs/sp-metadata.pem",
+                "must be fetched over https",
+            ),
+            (
+                "https://sp.example.com/metadata",
+                "needs a signing certificate",
+            ),
+        ] {
+            let error = ServiceProviderRegistry::load(&[source.parse().unwrap()], false)
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains(reason), "{source}: {error}");
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use crate::attributes::{
+    EDU_PERSON_PRINCIPAL_NAME, EDU_PERSON_SCOPED_AFFILIATION, PAIRWISE_ID, ProxiedAttribute,
+    SUBJECT_ID,
+};
+use crate::xml::without_comments;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Attributes whose values take the form `value@scope`, where the scope
+/// must be one the asserting IdP is authorized for, with their friendly
+/// names.
+const SCOPED_ATTRIBUTES: &[(&str, &str)] = &[
+    (EDU_PERSON_PRINCIPAL_NAME, "eduPersonPrincipalName"),
+    (EDU_PERSON_SCOPED_AFFILIATION, "eduPersonScopedAffiliation"),
+    (SUBJECT_ID, "subject-id"),
+    (PAIRWISE_ID, "pairwise-id"),
+];
+
+/// Prefix of the names eduPerson attributes had before they were named by
+/// OID, e.g. `urn:mace:dir:attribute-def:eduPersonPrincipalName`.
+const LEGACY_NAME_PREFIX: &str = "urn:mace:dir:attribute-def:";
+
+/// Whether an attribute is a scoped one under any name an SP might know it
+/// by: its OID, its legacy name, its friendly name used as the name, or its
+/// FriendlyName. Every attribute is passed through, so checking the OID
+/// alone would let the same values through unchecked under another name.
+fn is_scoped(name: &str, attr: &ProxiedAttribute) -> bool {
+    let unprefixed = name
+        .get(..LEGACY_NAME_PREFIX.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(LEGACY_NAME_PREFIX))
+        .map_or(name, |_| &name[LEGACY_NAME_PREFIX.len()..]);
+    SCOPED_ATTRIBUTES.iter().any(|(oid, friendly)| {
+        name.eq_ignore_ascii_case(oid)
+            || unprefixed.eq_ignore_ascii_case(friendly)
+            || attr
+                .friendly_name
+                .as_deref()
+                .is_some_and(|f| f.eq_ignore_ascii_case(friendly))
+    })
+}
+
+/// A `shibmd:Scope` from IdP metadata.
+#[derive(Clone, Debug)]
+enum Scope {
+    /// Matched case-insensitively against the whole scope.
+    Literal(String),
+    /// `regexp="true"`: must match the whole scope.
+    Regexp(Regex),
+}
+
+/// The scopes an IdP's metadata authorizes it to assert, from the
+/// `shibmd:Scope` extensions of its EntityDescriptor.
+#[derive(Clone, Debug, Default)]
+pub struct Scopes {
+    scopes: Vec<Scope>,
+}
+
+impl Scopes {
+    /// Reads every `Scope` element, with any namespace prefix, in an
+    /// EntityDescriptor fragment. Comments and CDATA sections are skipped,
+    /// and regular expressions that fail to compile authorize nothing.
+    /// Signed metadata must be reduced to the content its signature covers
+    /// first.
+    pub fn from_metadata(fragment: &str) -> Self {
+        const NAME: &str = "Scope";
+        let fragment = without_comments(fragment);
+        let fragment = fragment.as_ref();
+        let mut scopes = Vec::new();
+        let mut search_from = 0;
+
+        while let Some(found) = fragment[search_from..].find(NAME) {
+            let name_start = search_from + found;
+            let name_end = name_start + NAME.len();
+            search_from = name_end;
+
+            // `<Scope` or `<prefix:Scope`, and not e.g. `<ScopeList`.
+            let Some(open) = fragment[..name_start].rfind('<') else {
+                continue;
+            };
+            let prefix = &fragment[open + 1..name_start];
+            let is_prefix = prefix.is_empty()
+                || (prefix.ends_with(':')
+                    && prefix[..prefix.len() - 1]
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.'));
+            let after = fragment[name_end..].chars().next();
+            if !is_prefix || !matches!(after, Some(c) if c == '>' || c.is_whitespace()) {
+                continue;
+            }
+
+            let Some(tag_len) = fragment[name_end..].find('>') else {
+                break;
+            };
+            let attributes = &fragment[name_end..name_end + tag_len];
+            if attributes.ends_with('/') {
+                continue;
+            }
+            let content_start = name_end + tag_len + 1;
+            let Some(content_len) = fragment[content_start..].find('<') else {
+                break;
+            };
+            let value = unescape(fragment[content_start..content_start + content_len].trim());
+            search_from = content_start + content_len;
+            if value.is_empty() {
+                continue;
+            }
+
+            if is_regexp(attributes) {
+                match Regex::new(&format!("^(?:{value})$")) {
+                    Ok(regex) => scopes.push(Scope::Regexp(regex)),
+                    Err(e) => {
+                        tracing::warn!(scope = value, error = %e, "ignoring invalid regexp scope");
+                    }
+                }
+            } else {
+                scopes.push(Scope::Literal(value));
+            }
+        }
+
+        Self { scopes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    /// Whether the IdP may assert values in `scope`.
+    pub fn authorizes(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| match s {
+            Scope::Literal(literal) => literal.eq_ignore_ascii_case(scope),
+            Scope::Regexp(regex) => regex.is_match(scope),
+        })
+    }
+
+    /// Removes values of scoped attributes whose scope, everything after the
+    /// first `@`, is missing or not authorized, and attributes left without
+    /// values. An IdP whose metadata lists no scopes can assert no scoped
+    /// values at all.
+    pub fn filter(&self, idp_entity_id: &str, attrs: &mut BTreeMap<String, ProxiedAttribute>) {
+        attrs.retain(|name, attr| {
+            if !is_scoped(name, attr) {
+                return true;
+            }
+            attr.values.retain(|value| {
+                let scope = value.split_once('@').map(|(_, scope)| scope);
+                let authorized = scope.is_some_and(|scope| self.authorizes(scope));
+                if !authorized {
+                    tracing::warn!(
+                        idp = idp_entity_id,
+                        attribute = name,
+                        value,
+                        "dropping attribute value outside the IdP's authorized scopes"
+                    );
+                }
+                authorized
+            });
+            !attr.values.is_empty()
+        });
+    }
+}
+
+/// Reads a `regexp` attribute set to `true` or `1` (xs:boolean).
+fn is_regexp(attributes: &str) -> bool {
+    const ATTRIBUTE: &str = "regexp=";
+    let Some(start) = attributes.find(ATTRIBUTE) else {
+        return false;
+    };
+    let value = &attributes[start + ATTRIBUTE.len()..];
+    let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+        return false;
+    };
+    let value = &value[1..];
+    value
+        .find(quote)
+        .is_some_and(|end| matches!(value[..end].trim(), "true" | "1"))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METADATA: &str = r#"<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" xmlns:shibmd="urn:mace:shibboleth:metadata:1.0" entityID="https://idp.example.edu/idp/shibboleth">
+    <md:IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+        <md:Extensions>
+            <shibmd:Scope regexp="false">example.edu</shibmd:Scope>
+            <shibmd:Scope regexp="true">^([a-z0-9-]+\.)?cs\.example\.edu$</shibmd:Scope>
+        </md:Extensions>
+        <md:SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" Location="https://idp.example.edu/sso"/>
+    </md:IDPSSODescriptor>
+</md:EntityDescriptor>"#;
+
+    fn attr(values: &[&str]) -> ProxiedAttribute {
+        ProxiedAttribute {
+            values: values.iter().map(|v| v.to_string()).collect(),
+            ..ProxiedAttribute::default()
+        }
+    }
+
+    #[test]
+    fn reads_literal_and_regexp_scopes() {
+        let scopes = Scopes::from_metadata(METADATA);
+        assert_eq!(scopes.scopes.len(), 2);
+
+        assert!(scopes.authorizes("example.edu"));
+        assert!(scopes.authorizes("EXAMPLE.edu"));
+        assert!(scopes.authorizes("cs.example.edu"));
+        assert!(scopes.authorizes("lab-1.cs.example.edu"));
+
+        assert!(!scopes.authorizes("other.edu"));
+        assert!(!scopes.authorizes("math.example.edu"));
+        assert!(!scopes.authorizes("example.edu.evil.com"));
+        assert!(!scopes.authorizes("cs.example.edu.evil.com"));
+    }
+
+    #[test]
+    fn regexp_must_match_whole_scope() {
+        let scopes = Scopes::from_metadata(
+            r#"<Extensions><Scope regexp="1">example\.edu</Scope></Extensions>"#,
+        );
+        assert!(scopes.authorizes("example.edu"));
+        assert!(!scopes.authorizes("notexample.edu"));
+        assert!(!scopes.authorizes("example.edu.evil.com"));
+    }
+
+    #[test]
+    fn literal_scope_is_not_a_pattern() {
+        let scopes = Scopes::from_metadata("<shibmd:Scope>example.edu</shibmd:Scope>");
+        assert!(scopes.authorizes("example.edu"));
+        assert!(!scopes.authorizes("exampleXedu"));
+    }
+
+    #[test]
+    fn unescapes_scope_text() {
+        let scopes = Scopes::from_metadata(
+            r#"<shibmd:Scope regexp='true'>(a|b)&amp;?\.example\.edu</shibmd:Scope>"#,
+        );
+        assert!(scopes.authorizes("a.example.edu"));
+        assert!(scopes.authorizes("b&.example.edu"));
+    }
+
+    #[test]
+    fn skips_invalid_regexp_and_unrelated_elements() {
+        let scopes = Scopes::from_metadata(
+            r#"<ScopeList>ignored.edu</ScopeList>
+<shibmd:Scope regexp="true">([unclosed</shibmd:Scope>
+<shibmd:Scope/>
+<shibmd:Scope>  </shibmd:Scope>
+<shibmd:Scope> example.edu </shibmd:Scope>"#,
+        );
+        assert_eq!(scopes.scopes.len(), 1);
+        assert!(scopes.authorizes("example.edu"));
+        assert!(!scopes.authorizes("ignored.edu"));
+    }
+
+    #[test]
+    fn skips_scopes_in_comments_and_cdata() {
+        let scopes = Scopes::from_metadata(
+            r#"<md:Extensions>
+    <shibmd:Scope>example.edu</shibmd:Scope>
+    <!-- <shibmd:Scope>evil.com</shibmd:Scope> -->
+    <md:Description><![CDATA[<shibmd:Scope>cdata.example</shibmd:Scope>]]></md:Description>
+</md:Extensions>"#,
+        );
+        assert_eq!(scopes.scopes.len(), 1);
+        assert!(scopes.authorizes("example.edu"));
+        assert!(!scopes.authorizes("evil.com"));
+        assert!(!scopes.authorizes("cdata.example"));
+    }
+
+    #[test]
+    fn no_scopes_in_metadata() {
+        let scopes = Scopes::from_metadata(r#"<EntityDescriptor entityID="x"/>"#);
+        assert!(scopes.is_empty());
+        assert!(!scopes.authorizes("example.edu"));
+    }
+
+    #[test]
+    fn filter_drops_unauthorized_values() {
+        let scopes = Scopes::from_metadata(METADATA);
+        let mut attrs = BTreeMap::from([
+            (
+                EDU_PERSON_SCOPED_AFFILIATION.to_string(),
+                attr(&[
+                    "member@example.edu",
+                    "student@cs.example.edu",
+                    "faculty@other.edu",
+                    "staff",
+                ]),
+            ),
+            (
+                EDU_PERSON_PRINCIPAL_NAME.to_string(),
+                attr(&["alice@other.edu"]),
+            ),
+            (
+                crate::attributes::MAIL.to_string(),
+                attr(&["alice@other.edu"]),
+            ),
+        ]);
+
+        scopes.filter("https://idp.example.edu/idp/shibboleth", &mut attrs);
+
+        assert_eq!(
+            attrs[EDU_PERSON_SCOPED_AFFILIATION].values,
+            ["member@example.edu", "student@cs.example.edu"]
+        );
+        // Left without values, so removed.
+        assert!(!attrs.contains_key(EDU_PERSON_PRINCIPAL_NAME));
+        // Not a scoped attribute.
+        assert_eq!(attrs[crate::attributes::MAIL].values, ["alice@other.edu"]);
+    }
+
+    #[test]
+    fn filter_checks_legacy_and_friendly_names() {
+        let scopes = Scopes::from_metadata(METADATA);
+        let mut attrs = BTreeMap::from([
+            (
+                "urn:mace:dir:attribute-def:eduPersonPrincipalName".to_string(),
+                attr(&["alice@example.edu", "mallory@other.edu"]),
+            ),
+            (
+                "eduPersonScopedAffiliation".to_string(),
+                attr(&["member@other.edu"]),
+            ),
+            (
+                "urn:example:eppn".to_string(),
+                ProxiedAttribute {
+                    friendly_name: Some("eduPersonPrincipalName".into()),
+                    ..attr(&["mallory@other.edu"])
+                },
+            ),
+            ("urn:example:nickname".to_string(), attr(&["al@other.edu"])),
+        ]);
+
+        scopes.filter("https://idp.example.edu/idp/shibboleth", &mut attrs);
+
+        assert_eq!(
+            attrs["urn:mace:dir:attribute-def:eduPersonPrincipalName"].values,
+            ["alice@example.edu"]
+        );
+        assert!(!attrs.contains_key("eduPersonScopedAffiliation"));
+        assert!(!attrs.contains_key("urn:example:eppn"));
+        // Not a scoped attribute under any name.
+        assert_eq!(attrs["urn:example:nickname"].values, ["al@other.edu"]);
+    }
+
+    #[test]
+    fn filter_checks_subject_identifiers() {
+        let scopes = Scopes::from_metadata(METADATA);
+        let mut attrs = BTreeMap::from([
+            (SUBJECT_ID.to_string(), attr(&["a1b2c3@example.edu"])),
+            (PAIRWISE_ID.to_string(), attr(&["x9y8z7@other.edu"])),
+        ]);
+
+        scopes.filter("https://idp.example.edu/idp/shibboleth", &mut attrs);
+
+        assert_eq!(attrs[SUBJECT_ID].values, ["a1b2c3@example.edu"]);
+        assert!(!attrs.contains_key(PAIRWISE_ID));
+    }
+
+    #[test]
+    fn filter_without_scopes_drops_every_scoped_value() {
+        let mut attrs = BTreeMap::from([(
+            EDU_PERSON_PRINCIPAL_NAME.to_string(),
+            attr(&["alice@example.edu"]),
+        )]);
+        Scopes::default().filter("https://idp.example.edu", &mut attrs);
+        assert!(attrs.is_empty());
+    }
+
+    #[test]
+    fn filter_rejects_values_with_several_at_signs() {
+        let scopes = Scopes::from_metadata("<shibmd:Scope>example.edu</shibmd:Scope>");
+        let mut attrs = BTreeMap::from([(
+            EDU_PERSON_PRINCIPAL_NAME.to_string(),
+            attr(&["alice@example.edu@other.edu", "bob@other.edu@example.edu"]),
+        )]);
+        scopes.filter("https://idp.example.edu", &mut attrs);
+        assert!(attrs.is_empty());
+    }
+}
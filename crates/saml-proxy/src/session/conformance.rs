@@ -0,0 +1,218 @@
+//! Behaviour every [`SessionStore`] backend must share, run by each backend's
+//! tests. Updates may hand back a new ID, so each check continues with the ID
+//! the store returned. [`run_replay`] does the same for [`ReplayCache`]s.
+
+use super::{ReplayCache, SessionStore};
+use chrono::Utc;
+use std::time::Duration;
+
+pub(crate) async fn run(store: &impl SessionStore) {
+    create_and_get(store).await;
+    get_nonexistent_returns_none(store).await;
+    update_university(store).await;
+    update_proxy_request_id(store).await;
+    update_nonexistent_returns_none(store).await;
+    remove_returns_session(store).await;
+    sessions_are_independent(store).await;
+}
+
+/// For backends that keep server-side state: a removed session is gone, so
+/// an ID cannot be used to consume the same session twice.
+pub(crate) async fn run_single_use(store: &impl SessionStore) {
+    let id = store
+        .create("req".into(), "acs".into(), "sp".into(), None)
+        .await
+        .unwrap();
+    let id = store
+        .update_university(&id, "https://idp.cmu.edu".into())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert!(store.remove(&id).await.unwrap().is_some());
+
+    // Should be gone now
+    assert!(store.get(&id).await.unwrap().is_none());
+    assert!(store.remove(&id).await.unwrap().is_none());
+    assert!(
+        store
+            .update_proxy_request_id(&id, "proxy_req".into())
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+/// Checks that sessions disappear once `ttl`, the TTL the store was built
+/// with, has passed.
+pub(crate) async fn run_expiry(store: &impl SessionStore, ttl: Duration) {
+    let id = store
+        .create("req".into(), "acs".into(), "sp".into(), None)
+        .await
+        .unwrap();
+    assert!(store.get(&id).await.unwrap().is_some());
+
+    tokio::time::sleep(ttl + Duration::from_millis(100)).await;
+
+    assert!(store.get(&id).await.unwrap().is_none());
+    assert!(
+        store
+            .update_university(&id, "https://idp.cmu.edu".into())
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(store.remove(&id).await.unwrap().is_none());
+}
+
+async fn create_and_get(store: &impl SessionStore) {
+    let id = store
+        .create(
+            "req_123".into(),
+            "https://sp.example.com/acs".into(),
+            "https://sp.example.com".into(),
+            Some("relay".into()),
+        )
+        .await
+        .unwrap();
+
+    let session = store.get(&id).await.unwrap().unwrap();
+    assert_eq!(session.original_request_id, "req_123");
+    assert_eq!(session.sp_acs_url, "https://sp.example.com/acs");
+    assert_eq!(session.sp_entity_id, "https://sp.example.com");
+    assert_eq!(session.relay_state.as_deref(), Some("relay"));
+    assert!(session.selected_university.is_none());
+    assert!(session.proxy_request_id.is_none());
+}
+
+async fn get_nonexistent_returns_none(store: &impl SessionStore) {
+    assert!(store.get("nonexistent").await.unwrap().is_none());
+}
+
+async fn update_university(store: &impl SessionStore) {
+    let id = store
+        .create("req".into(), "acs".into(), "sp".into(), None)
+        .await
+        .unwrap();
+
+    let id = store
+        .update_university(&id, "https://idp.cmu.edu".into())
+        .await
+        .unwrap()
+        .expect("session should exist");
+
+    let session = store.get(&id).await.unwrap().unwrap();
+    assert_eq!(
+        session.selected_university.as_deref(),
+        Some("https://idp.cmu.edu")
+    );
+    assert_eq!(session.original_request_id, "req");
+}
+
+async fn update_proxy_request_id(store: &impl SessionStore) {
+    let id = store
+        .create("req".into(), "acs".into(), "sp".into(), None)
+        .await
+        .unwrap();
+    let id = store
+        .update_university(&id, "https://idp.cmu.edu".into())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let id = store
+        .update_proxy_request_id(&id, "proxy_req_456".into())
+        .await
+        .unwrap()
+        .expect("session should exist");
+
+    let session = store.get(&id).await.unwrap().unwrap();
+    assert_eq!(session.proxy_request_id.as_deref(), Some("proxy_req_456"));
+    // Earlier updates survive later ones.
+    assert_eq!(
+        session.selected_university.as_deref(),
+        Some("https://idp.cmu.edu")
+    );
+}
+
+async fn update_nonexistent_returns_none(store: &impl SessionStore) {
+    assert!(
+        store
+            .update_university("missing", "val".into())
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        store
+            .update_proxy_request_id("missing", "val".into())
+            .await
+            .unwrap()
+            .is_none()
+    );
+    // A failed update must not create the session.
+    assert!(store.get("missing").await.unwrap().is_none());
+}
+
+async fn remove_returns_session(store: &impl SessionStore) {
+    let id = store
+        .create("req".into(), "acs".into(), "sp".into(), None)
+        .await
+        .unwrap();
+    let id = store
+        .update_university(&id, "https://idp.cmu.edu".into())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let session = store.remove(&id).await.unwrap().unwrap();
+    assert_eq!(session.original_request_id, "req");
+    assert_eq!(
+        session.selected_university.as_deref(),
+        Some("https://idp.cmu.edu")
+    );
+}
+
+async fn sessions_are_independent(store: &impl SessionStore) {
+    let a = store
+        .create("a".into(), "acs".into(), "sp".into(), None)
+        .await
+        .unwrap();
+    let b = store
+        .create("b".into(), "acs".into(), "sp".into(), None)
+        .await
+        .unwrap();
+    assert_ne!(a, b);
+
+    let a = store
+        .update_university(&a, "idp-a".into())
+        .await
+        .unwrap()
+        .unwrap();
+    store.remove(&a).await.unwrap();
+
+    let b = store.get(&b).await.unwrap().unwrap();
+    assert_eq!(b.original_request_id, "b");
+    assert!(b.selected_university.is_none());
+}
+
+/// An assertion is accepted once per issuer until it expires. IDs are
+/// random, so the checks can share a persistent backend between runs.
+pub(crate) async fn run_replay(cache: &impl ReplayCache) {
+    let id = format!("_{}", uuid::Uuid::new_v4());
+    let later = Utc::now() + chrono::Duration::minutes(5);
+
+    assert!(cache.insert("idp-a", &id, later).await.unwrap());
+    assert!(!cache.insert("idp-a", &id, later).await.unwrap());
+    // The same ID from another issuer is a different assertion.
+    assert!(cache.insert("idp-b", &id, later).await.unwrap());
+
+    let id = format!("_{}", uuid::Uuid::new_v4());
+    let soon = Utc::now() + chrono::Duration::milliseconds(200);
+    assert!(cache.insert("idp-a", &id, soon).await.unwrap());
+    assert!(!cache.insert("idp-a", &id, soon).await.unwrap());
+
+    // Once expired, the ID is forgotten.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(cache.insert("idp-a", &id, later).await.unwrap());
+}
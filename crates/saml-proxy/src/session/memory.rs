@@ -0,0 +1,181 @@
+use super::{AuthSession, SESSION_TTL, SessionStore};
+use anyhow::Result;
+use chrono::Utc;
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Keeps sessions in a process-local map. Expired entries are dropped lazily
+/// on access and periodically by [`session_cleanup_task`].
+#[derive(Clone)]
+pub struct MemorySessionStore {
+    inner: Arc<DashMap<String, AuthSession>>,
+    ttl: Duration,
+}
+
+impl Default for MemorySessionStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::with_ttl(SESSION_TTL)
+    }
+
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            inner: Arc::new(DashMap::new()),
+            ttl,
+        }
+    }
+
+    fn is_expired(&self, session: &AuthSession) -> bool {
+        let elapsed = Utc::now()
+            .signed_duration_since(session.created_at)
+            .to_std()
+            .unwrap_or(Duration::ZERO);
+        elapsed > self.ttl
+    }
+
+    fn cleanup_expired(&self) {
+        self.inner.retain(|_, session| !self.is_expired(session));
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    async fn create(
+        &self,
+        original_request_id: String,
+        sp_acs_url: String,
+        sp_entity_id: String,
+        relay_state: Option<String>,
+    ) -> Result<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let session = AuthSession::new(original_request_id, sp_acs_url, sp_entity_id, relay_state);
+        self.inner.insert(id.clone(), session);
+        Ok(id)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<AuthSession>> {
+        let Some(entry) = self.inner.get(id) else {
+            return Ok(None);
+        };
+
+        if self.is_expired(&entry) {
+            drop(entry);
+            self.inner.remove(id);
+            return Ok(None);
+        }
+
+        Ok(Some(entry.clone()))
+    }
+
+    async fn update_proxy_request_id(
+        &self,
+        id: &str,
+        request_id: String,
+    ) -> Result<Option<String>> {
+        match self.inner.get_mut(id) {
+            Some(mut entry) if !self.is_expired(&entry) => {
+                entry.proxy_request_id = Some(request_id);
+                Ok(Some(id.to_string()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn update_university(&self, id: &str, entity_id: String) -> Result<Option<String>> {
+        match self.inner.get_mut(id) {
+            Some(mut entry) if !self.is_expired(&entry) => {
+                entry.selected_university = Some(entity_id);
+                Ok(Some(id.to_string()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn remove(&self, id: &str) -> Result<Option<AuthSession>> {
+        Ok(self
+            .inner
+            .remove(id)
+            .map(|(_, session)| session)
+            .filter(|session| !self.is_expired(session)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::conformance;
+
+    #[tokio::test]
+    async fn conforms() {
+        conformance::run(&MemorySessionStore::new()).await;
+        conformance::run_single_use(&MemorySessionStore::new()).await;
+    }
+
+    #[tokio::test]
+    async fn conforms_on_expiry() {
+        let ttl = Duration::from_millis(200);
+        conformance::run_expiry(&MemorySessionStore::with_ttl(ttl), ttl).await;
+    }
+
+    #[tokio::test]
+    async fn expired_session_returns_none_on_get() {
+        let store = MemorySessionStore::new();
+        let id = store
+            .create("req".into(), "acs".into(), "sp".into(), None)
+            .await
+            .unwrap();
+
+        // Backdate the session to make it expired
+        if let Some(mut entry) = store.inner.get_mut(&id) {
+            entry.created_at = Utc::now() - chrono::Duration::minutes(20);
+        }
+
+        assert!(store.get(&id).await.unwrap().is_none());
+        // The expired entry should have been removed
+        assert!(store.inner.get(&id).is_none());
+    }
+
+    #[tokio::test]
+    async fn cleanup_removes_expired_sessions() {
+        let store = MemorySessionStore::new();
+        let fresh_id = store
+            .create("fresh".into(), "acs".into(), "sp".into(), None)
+            .await
+            .unwrap();
+        let old_id = store
+            .create("old".into(), "acs".into(), "sp".into(), None)
+            .await
+            .unwrap();
+
+        if let Some(mut entry) = store.inner.get_mut(&old_id) {
+            entry.created_at = Utc::now() - chrono::Duration::minutes(20);
+        }
+
+        store.cleanup_expired();
+
+        assert!(store.inner.contains_key(&fresh_id));
+        assert!(!store.inner.contains_key(&old_id));
+    }
+}
+
+/// Drops expired sessions from a [`MemorySessionStore`] every 5 minutes. The
+/// Valkey backend needs no equivalent, since keys carry their own TTL.
+pub async fn session_cleanup_task(store: MemorySessionStore) {
+    let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let before = store.inner.len();
+        store.cleanup_expired();
+        let removed = before - store.inner.len();
+        if removed > 0 {
+            tracing::info!(removed, "cleaned up expired sessions");
+        }
+    }
+}
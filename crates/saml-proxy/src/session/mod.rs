@@ -0,0 +1,178 @@
+mod memory;
+mod replay;
+mod token;
+mod valkey;
+
+#[cfg(test)]
+pub(crate) mod conformance;
+
+pub use memory::{MemorySessionStore, session_cleanup_task};
+pub use replay::{MemoryReplayCache, ReplayCache, Replays, ValkeyReplayCache, replay_cleanup_task};
+pub use token::{DEFAULT_MAX_TOKEN_LEN, TokenSessionStore};
+pub use valkey::ValkeySessionStore;
+
+use crate::config::SessionStoreConfig;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+pub const SESSION_TTL: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthSession {
+    pub relay_state: Option<String>,
+    pub original_request_id: String,
+    pub sp_acs_url: String,
+    pub sp_entity_id: String,
+    pub selected_university: Option<String>,
+    pub proxy_request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuthSession {
+    fn new(
+        original_request_id: String,
+        sp_acs_url: String,
+        sp_entity_id: String,
+        relay_state: Option<String>,
+    ) -> Self {
+        Self {
+            relay_state,
+            original_request_id,
+            sp_acs_url,
+            sp_entity_id,
+            selected_university: None,
+            proxy_request_id: None,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Storage for in-flight authentication flows, keyed by an opaque session ID
+/// that travels through the discovery UI and as the upstream RelayState.
+/// Sessions expire [`SESSION_TTL`] after creation; an expired
+/// session behaves exactly like a missing one.
+///
+/// A backend may change a session's ID when it is updated, so callers must
+/// continue with the ID an update returns.
+pub trait SessionStore: Send + Sync {
+    fn create(
+        &self,
+        original_request_id: String,
+        sp_acs_url: String,
+        sp_entity_id: String,
+        relay_state: Option<String>,
+    ) -> impl Future<Output = Result<String>> + Send;
+
+    fn get(&self, id: &str) -> impl Future<Output = Result<Option<AuthSession>>> + Send;
+
+    /// Returns the session's ID after the update, or `None` if the session
+    /// does not exist.
+    fn update_proxy_request_id(
+        &self,
+        id: &str,
+        request_id: String,
+    ) -> impl Future<Output = Result<Option<String>>> + Send;
+
+    /// Returns the session's ID after the update, or `None` if the session
+    /// does not exist.
+    fn update_university(
+        &self,
+        id: &str,
+        entity_id: String,
+    ) -> impl Future<Output = Result<Option<String>>> + Send;
+
+    /// Removes and returns the session. Server-side backends guarantee it can
+    /// be consumed only once; [`TokenSessionStore`] cannot.
+    fn remove(&self, id: &str) -> impl Future<Output = Result<Option<AuthSession>>> + Send;
+}
+
+/// The session backend selected by [`SessionStoreConfig`].
+#[derive(Clone)]
+pub enum Sessions {
+    /// Process-local; every request of a flow must reach the same replica.
+    Memory(MemorySessionStore),
+    /// Shared between replicas, with expiry handled by Valkey itself.
+    Valkey(ValkeySessionStore),
+    /// No server-side state: the session travels inside its own ID.
+    Token(TokenSessionStore),
+}
+
+impl Sessions {
+    pub async fn from_config(config: &SessionStoreConfig) -> Result<Self> {
+        Ok(match config {
+            SessionStoreConfig::Memory => Self::Memory(MemorySessionStore::new()),
+            SessionStoreConfig::Valkey { url } => {
+                Self::Valkey(ValkeySessionStore::connect(url, SESSION_TTL).await?)
+            }
+            SessionStoreConfig::Stateless {
+                keys,
+                max_token_len,
+                ..
+            } => Self::Token(TokenSessionStore::new(keys, SESSION_TTL, *max_token_len)?),
+        })
+    }
+}
+
+impl SessionStore for Sessions {
+    async fn create(
+        &self,
+        original_request_id: String,
+        sp_acs_url: String,
+        sp_entity_id: String,
+        relay_state: Option<String>,
+    ) -> Result<String> {
+        match self {
+            Self::Memory(s) => {
+                s.create(original_request_id, sp_acs_url, sp_entity_id, relay_state)
+                    .await
+            }
+            Self::Valkey(s) => {
+                s.create(original_request_id, sp_acs_url, sp_entity_id, relay_state)
+                    .await
+            }
+            Self::Token(s) => {
+                s.create(original_request_id, sp_acs_url, sp_entity_id, relay_state)
+                    .await
+            }
+        }
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<AuthSession>> {
+        match self {
+            Self::Memory(s) => s.get(id).await,
+            Self::Valkey(s) => s.get(id).await,
+            Self::Token(s) => s.get(id).await,
+        }
+    }
+
+    async fn update_proxy_request_id(
+        &self,
+        id: &str,
+        request_id: String,
+    ) -> Result<Option<String>> {
+        match self {
+            Self::Memory(s) => s.update_proxy_request_id(id, request_id).await,
+            Self::Valkey(s) => s.update_proxy_request_id(id, request_id).await,
+            Self::Token(s) => s.update_proxy_request_id(id, request_id).await,
+        }
+    }
+
+    async fn update_university(&self, id: &str, entity_id: String) -> Result<Option<String>> {
+        match self {
+            Self::Memory(s) => s.update_university(id, entity_id).await,
+            Self::Valkey(s) => s.update_university(id, entity_id).await,
+            Self::Token(s) => s.update_university(id, entity_id).await,
+        }
+    }
+
+    async fn remove(&self, id: &str) -> Result<Option<AuthSession>> {
+        match self {
+            Self::Memory(s) => s.remove(id).await,
+            Self::Valkey(s) => s.remove(id).await,
+            Self::Token(s) => s.remove(id).await,
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use super::Sessions;
+use crate::config::{ReplayCacheConfig, SessionStoreConfig};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use redis::aio::ConnectionManager;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+const KEY_PREFIX: &str = "saml-proxy:assertion:";
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Remembers the assertions the proxy has accepted until they expire, so a
+/// captured SAMLResponse cannot be presented a second time. Assertions are
+/// keyed by issuer and ID, so one IdP cannot block another's IDs.
+pub trait ReplayCache: Send + Sync {
+    /// Records an assertion as consumed until `expires_at`. Like
+    /// `HashSet::insert`, returns `false` if it was already recorded and has
+    /// not yet expired.
+    fn insert(
+        &self,
+        issuer: &str,
+        assertion_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<bool>> + Send;
+}
+
+fn key(issuer: &str, assertion_id: &str) -> String {
+    format!("{issuer} {assertion_id}")
+}
+
+/// Keeps consumed assertion IDs in a process-local map, so replays are only
+/// caught by the replica that accepted the assertion. Expired entries are
+/// overwritten on insert and dropped by [`replay_cleanup_task`].
+#[derive(Clone, Default)]
+pub struct MemoryReplayCache {
+    inner: Arc<DashMap<String, DateTime<Utc>>>,
+}
+
+impl MemoryReplayCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn cleanup_expired(&self) {
+        let now = Utc::now();
+        self.inner.retain(|_, expires_at| *expires_at > now);
+    }
+}
+
+impl ReplayCache for MemoryReplayCache {
+    async fn insert(
+        &self,
+        issuer: &str,
+        assertion_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        match self.inner.entry(key(issuer, assertion_id)) {
+            Entry::Occupied(entry) if *entry.get() > Utc::now() => Ok(false),
+            entry => {
+                entry.insert(expires_at);
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Keeps consumed assertion IDs in Valkey next to the sessions, each key
+/// expiring on its own at the assertion's expiry.
+#[derive(Clone)]
+pub struct ValkeyReplayCache {
+    conn: ConnectionManager,
+}
+
+impl ValkeyReplayCache {
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self { conn }
+    }
+
+    pub async fn connect(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url).context("invalid Valkey URL")?;
+        let conn = ConnectionManager::new(client)
+            .await
+            .context("failed to connect to Valkey")?;
+        Ok(Self::new(conn))
+    }
+}
+
+impl ReplayCache for ValkeyReplayCache {
+    async fn insert(
+        &self,
+        issuer: &str,
+        assertion_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        // SET NX is atomic, so two replicas racing to accept the same
+        // assertion cannot both succeed.
+        let expires_at_ms = expires_at
+            .timestamp_millis()
+            .max(Utc::now().timestamp_millis() + 1);
+        let written: Option<String> = redis::cmd("SET")
+            .arg(format!("{KEY_PREFIX}{}", key(issuer, assertion_id)))
+            .arg("")
+            .arg("NX")
+            .arg("PXAT")
+            .arg(expires_at_ms)
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(written.is_some())
+    }
+}
+
+/// The replay cache backend for a session configuration: Valkey sessions
+/// share their connection, stateless sessions use the cache they name, and
+/// memory sessions keep it in the process like themselves.
+#[derive(Clone)]
+pub enum Replays {
+    Memory(MemoryReplayCache),
+    Valkey(ValkeyReplayCache),
+}
+
+impl Replays {
+    pub async fn from_config(config: &SessionStoreConfig, sessions: &Sessions) -> Result<Self> {
+        Ok(match (config, sessions) {
+            (_, Sessions::Valkey(store)) => {
+                Self::Valkey(ValkeyReplayCache::new(store.connection()))
+            }
+            (
+                SessionStoreConfig::Stateless {
+                    replay_cache: ReplayCacheConfig::Valkey { url },
+                    ..
+                },
+                _,
+            ) => Self::Valkey(ValkeyReplayCache::connect(url).await?),
+            _ => Self::Memory(MemoryReplayCache::new()),
+        })
+    }
+}
+
+impl ReplayCache for Replays {
+    async fn insert(
+        &self,
+        issuer: &str,
+        assertion_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        match self {
+            Self::Memory(c) => c.insert(issuer, assertion_id, expires_at).await,
+            Self::Valkey(c) => c.insert(issuer, assertion_id, expires_at).await,
+        }
+    }
+}
+
+/// Drops expired assertion IDs from a [`MemoryReplayCache`] every 5 minutes.
+pub async fn replay_cleanup_task(cache: MemoryReplayCache) {
+    let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let before = cache.inner.len();
+        cache.cleanup_expired();
+        let removed = before - cache.inner.len();
+        if removed > 0 {
+            tracing::info!(removed, "cleaned up expired assertion IDs");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{SESSION_TTL, ValkeySessionStore, conformance};
+
+    #[tokio::test]
+    async fn memory_conforms() {
+        conformance::run_replay(&MemoryReplayCache::new()).await;
+    }
+
+    #[tokio::test]
+    async fn cleanup_removes_expired_ids() {
+        let cache = MemoryReplayCache::new();
+        let now = Utc::now();
+        cache
+            .insert("idp", "_fresh", now + chrono::Duration::minutes(5))
+            .await
+            .unwrap();
+        cache
+            .insert("idp", "_old", now - chrono::Duration::minutes(5))
+            .await
+            .unwrap();
+
+        cache.cleanup_expired();
+
+        assert!(cache.inner.contains_key(&key("idp", "_fresh")));
+        assert!(!cache.inner.contains_key(&key("idp", "_old")));
+    }
+
+    /// Needs a running Valkey; see the session store tests.
+    #[tokio::test]
+    #[ignore]
+    async fn valkey_conforms() {
+        let url = std::env::var("SAML_PROXY_TEST_VALKEY_URL")
+            .unwrap_or_else(|_| "redis://127.0.0.1:6379".into());
+        let store = ValkeySessionStore::connect(&url, SESSION_TTL)
+            .await
+            .expect("failed to connect to Valkey");
+        conformance::run_replay(&ValkeyReplayCache::new(store.connection())).await;
+    }
+}
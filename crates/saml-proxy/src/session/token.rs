@@ -0,0 +1,319 @@
+use super::{AuthSession, SessionStore};
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::Utc;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use openssl::symm::{Cipher, decrypt_aead, encrypt_aead};
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Tokens longer than this are refused by default. Sessions travel in URLs
+/// and as the upstream RelayState, and many IdPs and proxies truncate or
+/// reject values much beyond 2 KiB.
+pub const DEFAULT_MAX_TOKEN_LEN: usize = 2048;
+
+const VERSION: u8 = 1;
+const KEY_LEN: usize = 32;
+const KEY_ID_LEN: usize = 4;
+const HEADER_LEN: usize = 1 + KEY_ID_LEN;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+/// Upper bound on the decompressed payload, well above any real session.
+const MAX_PLAINTEXT_LEN: u64 = 16 * 1024;
+
+struct SessionKey {
+    id: [u8; KEY_ID_LEN],
+    key: Vec<u8>,
+}
+
+impl SessionKey {
+    fn new(key: &[u8]) -> Result<Self> {
+        anyhow::ensure!(
+            key.len() == KEY_LEN,
+            "session keys must be {KEY_LEN} bytes, got {}",
+            key.len()
+        );
+        let digest = openssl::sha::sha256(key);
+        let mut id = [0; KEY_ID_LEN];
+        id.copy_from_slice(&digest[..KEY_ID_LEN]);
+        Ok(Self {
+            id,
+            key: key.to_vec(),
+        })
+    }
+}
+
+/// Keeps no server-side state: the session is sealed with AES-256-GCM into
+/// the token that serves as its ID, so any replica holding the keys can
+/// continue the flow. Because a token is the session, every update issues a
+/// new one, and [`remove`](SessionStore::remove) cannot stop an earlier
+/// token from being presented again before it expires.
+///
+/// A token is `version || key ID || nonce || ciphertext || tag`, base64url
+/// encoded, with the version and key ID authenticated as associated data.
+/// The key ID is a fingerprint of the key, so keys can be rotated by putting
+/// the new one first: tokens are sealed with the first key and opened with
+/// whichever key they name.
+#[derive(Clone)]
+pub struct TokenSessionStore {
+    keys: Arc<Vec<SessionKey>>,
+    ttl: Duration,
+    max_len: usize,
+}
+
+impl TokenSessionStore {
+    /// `keys` are 32-byte AES keys, newest first.
+    pub fn new(keys: &[Vec<u8>], ttl: Duration, max_len: usize) -> Result<Self> {
+        anyhow::ensure!(!keys.is_empty(), "at least one session key is required");
+        let keys = keys
+            .iter()
+            .map(|k| SessionKey::new(k))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            keys: Arc::new(keys),
+            ttl,
+            max_len,
+        })
+    }
+
+    fn seal(&self, session: &AuthSession) -> Result<String> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&serde_json::to_vec(session)?)?;
+        let payload = encoder.finish()?;
+
+        let key = &self.keys[0];
+        let mut header = [0; HEADER_LEN];
+        header[0] = VERSION;
+        header[1..].copy_from_slice(&key.id);
+
+        let mut nonce = [0; NONCE_LEN];
+        openssl::rand::rand_bytes(&mut nonce)?;
+        let mut tag = [0; TAG_LEN];
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key.key,
+            Some(&nonce),
+            &header,
+            &payload,
+            &mut tag,
+        )?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + NONCE_LEN + ciphertext.len() + TAG_LEN);
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&ciphertext);
+        bytes.extend_from_slice(&tag);
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+
+        anyhow::ensure!(
+            token.len() <= self.max_len,
+            "session token is {} bytes, over the {} byte limit",
+            token.len(),
+            self.max_len
+        );
+        Ok(token)
+    }
+
+    /// Returns `None` for anything that is not a live token sealed with one
+    /// of our keys, which callers treat like an unknown session ID.
+    fn open(&self, token: &str) -> Option<AuthSession> {
+        if token.len() > self.max_len {
+            return None;
+        }
+        let bytes = URL_SAFE_NO_PAD.decode(token).ok()?;
+        if bytes.len() < HEADER_LEN + NONCE_LEN + TAG_LEN || bytes[0] != VERSION {
+            return None;
+        }
+
+        let (header, rest) = bytes.split_at(HEADER_LEN);
+        let key = self.keys.iter().find(|k| k.id == header[1..])?;
+        let (nonce, rest) = rest.split_at(NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+
+        let payload = match decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key.key,
+            Some(nonce),
+            header,
+            ciphertext,
+            tag,
+        ) {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::warn!(error = %e, "rejecting session token that fails authentication");
+                return None;
+            }
+        };
+
+        let mut json = Vec::new();
+        DeflateDecoder::new(&payload[..])
+            .take(MAX_PLAINTEXT_LEN)
+            .read_to_end(&mut json)
+            .ok()?;
+        let session: AuthSession = serde_json::from_slice(&json).ok()?;
+
+        let elapsed = Utc::now()
+            .signed_duration_since(session.created_at)
+            .to_std()
+            .unwrap_or(Duration::ZERO);
+        (elapsed <= self.ttl).then_some(session)
+    }
+
+    fn update(&self, id: &str, update: impl FnOnce(&mut AuthSession)) -> Result<Option<String>> {
+        let Some(mut session) = self.open(id) else {
+            return Ok(None);
+        };
+        update(&mut session);
+        self.seal(&session).map(Some)
+    }
+}
+
+impl SessionStore for TokenSessionStore {
+    async fn create(
+        &self,
+        original_request_id: String,
+        sp_acs_url: String,
+        sp_entity_id: String,
+        relay_state: Option<String>,
+    ) -> Result<String> {
+        let session = AuthSession::new(original_request_id, sp_acs_url, sp_entity_id, relay_state);
+        self.seal(&session)
+            .context("failed to issue stateless session token")
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<AuthSession>> {
+        Ok(self.open(id))
+    }
+
+    async fn update_proxy_request_id(
+        &self,
+        id: &str,
+        request_id: String,
+    ) -> Result<Option<String>> {
+        self.update(id, |s| s.proxy_request_id = Some(request_id))
+    }
+
+    async fn update_university(&self, id: &str, entity_id: String) -> Result<Option<String>> {
+        self.update(id, |s| s.selected_university = Some(entity_id))
+    }
+
+    async fn remove(&self, id: &str) -> Result<Option<AuthSession>> {
+        Ok(self.open(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{SESSION_TTL, conformance};
+
+    fn key(byte: u8) -> Vec<u8> {
+        vec![byte; KEY_LEN]
+    }
+
+    fn store(keys: &[Vec<u8>]) -> TokenSessionStore {
+        TokenSessionStore::new(keys, SESSION_TTL, DEFAULT_MAX_TOKEN_LEN).unwrap()
+    }
+
+    async fn create(store: &TokenSessionStore) -> String {
+        store
+            .create(
+                "_req".into(),
+                "https://sp.example.com/acs".into(),
+                "https://sp.example.com".into(),
+                Some("relay".into()),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn conforms() {
+        conformance::run(&store(&[key(1)])).await;
+    }
+
+    #[tokio::test]
+    async fn conforms_on_expiry() {
+        let ttl = Duration::from_millis(200);
+        let store = TokenSessionStore::new(&[key(1)], ttl, DEFAULT_MAX_TOKEN_LEN).unwrap();
+        conformance::run_expiry(&store, ttl).await;
+    }
+
+    #[tokio::test]
+    async fn token_is_url_safe() {
+        let token = create(&store(&[key(1)])).await;
+        assert!(
+            token
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        );
+    }
+
+    #[tokio::test]
+    async fn tampered_token_is_rejected() {
+        let store = store(&[key(1)]);
+        let token = create(&store).await;
+
+        let mut bytes = URL_SAFE_NO_PAD.decode(&token).unwrap();
+        let last = bytes.len() - TAG_LEN - 1;
+        bytes[last] ^= 1;
+        let tampered = URL_SAFE_NO_PAD.encode(bytes);
+
+        assert!(store.get(&tampered).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn token_from_unknown_key_is_rejected() {
+        let token = create(&store(&[key(1)])).await;
+        assert!(store(&[key(2)]).get(&token).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rotated_key_still_opens_old_tokens() {
+        let old = store(&[key(1)]);
+        let token = create(&old).await;
+
+        let rotated = store(&[key(2), key(1)]);
+        assert!(rotated.get(&token).await.unwrap().is_some());
+
+        // Updates reseal with the new key, which the old store cannot open.
+        let updated = rotated
+            .update_university(&token, "https://idp.cmu.edu".into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(rotated.get(&updated).await.unwrap().is_some());
+        assert!(old.get(&updated).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn oversized_session_is_refused() {
+        let store = TokenSessionStore::new(&[key(1)], SESSION_TTL, 256).unwrap();
+        let relay_state: String = (0..512)
+            .map(|i| char::from(b'a' + (i % 26) as u8))
+            .collect();
+        let result = store
+            .create("_req".into(), "acs".into(), "sp".into(), Some(relay_state))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn oversized_token_is_not_decoded() {
+        let store = store(&[key(1)]);
+        let junk = "A".repeat(DEFAULT_MAX_TOKEN_LEN + 1);
+        assert!(store.get(&junk).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_bad_keys() {
+        assert!(TokenSessionStore::new(&[], SESSION_TTL, DEFAULT_MAX_TOKEN_LEN).is_err());
+        assert!(
+            TokenSessionStore::new(&[vec![0; 16]], SESSION_TTL, DEFAULT_MAX_TOKEN_LEN).is_err()
+        );
+    }
+}
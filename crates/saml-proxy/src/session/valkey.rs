@@ -0,0 +1,148 @@
+use super::{AuthSession, SessionStore};
+use anyhow::{Context, Result};
+use redis::aio::ConnectionManager;
+use std::time::Duration;
+
+const KEY_PREFIX: &str = "saml-proxy:session:";
+
+/// Keeps sessions in Valkey (or Redis) as JSON, so any replica can continue a
+/// flow started on another. Each key expires on its own after the session
+/// TTL; updates keep the remaining TTL rather than extending it.
+#[derive(Clone)]
+pub struct ValkeySessionStore {
+    conn: ConnectionManager,
+    ttl: Duration,
+}
+
+impl ValkeySessionStore {
+    pub async fn connect(url: &str, ttl: Duration) -> Result<Self> {
+        let client = redis::Client::open(url).context("invalid Valkey URL")?;
+        let conn = ConnectionManager::new(client)
+            .await
+            .context("failed to connect to Valkey")?;
+        Ok(Self { conn, ttl })
+    }
+
+    /// The shared connection, for other state kept alongside sessions.
+    pub(crate) fn connection(&self) -> ConnectionManager {
+        self.conn.clone()
+    }
+
+    fn key(id: &str) -> String {
+        format!("{KEY_PREFIX}{id}")
+    }
+
+    /// Applies `update` to a stored session in place. `SET ... XX KEEPTTL`
+    /// writes only if the key still exists, so a session that expires
+    /// between the read and the write is not resurrected.
+    async fn update(
+        &self,
+        id: &str,
+        update: impl FnOnce(&mut AuthSession),
+    ) -> Result<Option<String>> {
+        let Some(mut session) = self.get(id).await? else {
+            return Ok(None);
+        };
+        update(&mut session);
+
+        let written: Option<String> = redis::cmd("SET")
+            .arg(Self::key(id))
+            .arg(serde_json::to_string(&session)?)
+            .arg("XX")
+            .arg("KEEPTTL")
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(written.map(|_| id.to_string()))
+    }
+}
+
+impl SessionStore for ValkeySessionStore {
+    async fn create(
+        &self,
+        original_request_id: String,
+        sp_acs_url: String,
+        sp_entity_id: String,
+        relay_state: Option<String>,
+    ) -> Result<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let session = AuthSession::new(original_request_id, sp_acs_url, sp_entity_id, relay_state);
+
+        let ttl_ms = u64::try_from(self.ttl.as_millis()).unwrap_or(u64::MAX);
+        redis::cmd("SET")
+            .arg(Self::key(&id))
+            .arg(serde_json::to_string(&session)?)
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async::<()>(&mut self.conn.clone())
+            .await?;
+        Ok(id)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<AuthSession>> {
+        let json: Option<String> = redis::cmd("GET")
+            .arg(Self::key(id))
+            .query_async(&mut self.conn.clone())
+            .await?;
+        json.map(|j| serde_json::from_str(&j))
+            .transpose()
+            .context("corrupt session in Valkey")
+    }
+
+    async fn update_proxy_request_id(
+        &self,
+        id: &str,
+        request_id: String,
+    ) -> Result<Option<String>> {
+        self.update(id, |s| s.proxy_request_id = Some(request_id))
+            .await
+    }
+
+    async fn update_university(&self, id: &str, entity_id: String) -> Result<Option<String>> {
+        self.update(id, |s| s.selected_university = Some(entity_id))
+            .await
+    }
+
+    async fn remove(&self, id: &str) -> Result<Option<AuthSession>> {
+        // GETDEL makes removal atomic: two replicas racing to consume the
+        // same session cannot both get it.
+        let json: Option<String> = redis::cmd("GETDEL")
+            .arg(Self::key(id))
+            .query_async(&mut self.conn.clone())
+            .await?;
+        json.map(|j| serde_json::from_str(&j))
+            .transpose()
+            .context("corrupt session in Valkey")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::conformance;
+
+    /// Needs a running Valkey; set `SAML_PROXY_TEST_VALKEY_URL` and run with
+    ///
+    ///     cargo test -p saml-proxy -- --ignored valkey
+    async fn store(ttl: Duration) -> ValkeySessionStore {
+        let url = std::env::var("SAML_PROXY_TEST_VALKEY_URL")
+            .unwrap_or_else(|_| "redis://127.0.0.1:6379".into());
+        ValkeySessionStore::connect(&url, ttl)
+            .await
+            .expect("failed to connect to Valkey")
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn valkey_conforms() {
+        let store = store(Duration::from_secs(60)).await;
+        conformance::run(&store).await;
+        conformance::run_single_use(&store).await;
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn valkey_conforms_on_expiry() {
+        let ttl = Duration::from_millis(200);
+        conformance::run_expiry(&store(ttl).await, ttl).await;
+    }
+}
@@ -1,18 +1,23 @@
 use crate::error::Error;
+use crate::session::{ReplayCache, SessionStore};
 use crate::state::AppState;
 use axum::extract::State;
 use axum::response::{Html, IntoResponse, Response};
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
 use samael::crypto::{CertificateDer, Crypto, CryptoProvider};
-use samael::idp::response_builder::{ResponseAttribute, build_response_template};
-use samael::idp::sp_extractor::RequiredAttribute;
+use samael::idp::response_builder::build_response_template;
+use samael::schema::Assertion;
 use samael::service_provider::ServiceProvider;
 use samael::signature::DigestAlgorithm;
 use samael::traits::ToXml;
 use serde::Deserialize;
 use std::sync::Arc;
 
+/// How old a university's response may be when it arrives.
+const MAX_ISSUE_DELAY: chrono::Duration = chrono::Duration::minutes(5);
+
 #[derive(Deserialize)]
 pub struct AcsForm {
     #[serde(rename = "SAMLResponse")]
@@ -22,9 +27,11 @@ pub struct AcsForm {
 }
 
 /// Receives the SAML Response from a university IdP, validates the signature
-/// and assertions, extracts eduPerson attributes, builds a new SAML Response
-/// addressed to the original Service Provider, and returns an auto-submitting
-/// HTML form that POSTs the response to the SP's ACS URL.
+/// and assertions, extracts eduPerson attributes, drops scoped values outside
+/// the university's metadata scopes, applies the SP's attribute release
+/// policy, builds a new SAML Response addressed to the original Service
+/// Provider, and returns an auto-submitting HTML form that POSTs the response
+/// to the SP's ACS URL.
 pub async fn assertion_consumer_service(
     State(state): State<Arc<AppState>>,
     axum::Form(form): axum::Form<AcsForm>,
@@ -38,6 +45,7 @@ pub async fn assertion_consumer_service(
     let session = state
         .sessions
         .remove(session_id)
+        .await?
         .ok_or_else(|| Error::SessionNotFound(session_id.to_string()))?;
 
     let entity_id = session
@@ -46,12 +54,9 @@ pub async fn assertion_consumer_service(
         .ok_or(Error::MissingUniversitySelection)?;
 
     // Fetch the university's metadata again for signature validation.
-    // The MDQ client caches responses, so this is typically a cache hit.
-    let idp_metadata = state
-        .mdq_client
-        .fetch_entity(entity_id)
-        .await
-        .map_err(|e| Error::MdqFetchFailed(e.to_string()))?;
+    // MDQ responses are cached and local sources are in memory, so this is
+    // typically a cache hit.
+    let idp_metadata = state.metadata.fetch_entity(entity_id).await?;
 
     let proxy_req_id = session
         .proxy_request_id
@@ -71,7 +76,7 @@ pub async fn assertion_consumer_service(
         certificate: Some(CertificateDer::from(state.idp_cert_der.clone())),
         idp_metadata,
         allow_idp_initiated: false,
-        max_issue_delay: chrono::Duration::minutes(5),
+        max_issue_delay: MAX_ISSUE_DELAY,
         ..ServiceProvider::default()
     };
 
@@ -79,6 +84,16 @@ pub async fn assertion_consumer_service(
         .parse_base64_response(&form.saml_response, Some(&[proxy_req_id]))
         .map_err(|e| Error::InvalidSamlResponse(e.to_string()))?;
 
+    // The session alone does not stop a replay: a stateless session token
+    // stays valid after use.
+    if !state
+        .replays
+        .insert(entity_id, &assertion.id, replay_expiry(&assertion))
+        .await?
+    {
+        return Err(Error::ReplayedAssertion(assertion.id));
+    }
+
     let name_id = assertion
         .subject
         .as_ref()
@@ -86,19 +101,15 @@ pub async fn assertion_consumer_service(
         .map(|n| n.value.as_str())
         .unwrap_or("unknown");
 
-    let attrs = crate::attributes::extract_attributes(&assertion);
-
-    // Build ResponseAttribute list from extracted eduPerson attributes.
-    let response_attrs: Vec<ResponseAttribute> = attrs
-        .iter()
-        .map(|(name, value)| ResponseAttribute {
-            required_attribute: RequiredAttribute {
-                name: name.clone(),
-                format: Some("urn:oasis:names:tc:SAML:2.0:attrname-format:uri".into()),
-            },
-            value: value.as_str(),
-        })
-        .collect();
+    // Scoped values the university is not authorized to assert never reach
+    // the release policy.
+    let mut attrs = crate::attributes::extract_attributes(&assertion);
+    state
+        .metadata
+        .scopes(entity_id)
+        .await?
+        .filter(entity_id, &mut attrs);
+    let attrs = state.attribute_policy.apply(&session.sp_entity_id, attrs);
 
     let cert_der = CertificateDer::from(state.idp_cert_der.clone());
 
@@ -110,9 +121,16 @@ pub async fn assertion_consumer_service(
         &state.config.entity_id,
         &session.sp_acs_url,
         &session.original_request_id,
-        &response_attrs,
+        &[],
     );
 
+    // The template takes one value per attribute; replace its attribute
+    // statement with one that carries every value.
+    if let Some(assertion) = unsigned.assertion.as_mut() {
+        assertion.attribute_statements =
+            crate::attributes::attribute_statement(&attrs).map(|stmt| vec![stmt]);
+    }
+
     if let (Some(mut sig), Some(assertion)) =
         (unsigned.signature.take(), unsigned.assertion.as_mut())
     {
@@ -159,3 +177,22 @@ pub async fn assertion_consumer_service(
 
     Ok(Html(html).into_response())
 }
+
+/// How long an assertion must be remembered to catch replays: until the
+/// latest `NotOnOrAfter` in its conditions and subject confirmations, and at
+/// least as long as its issue instant is accepted.
+fn replay_expiry(assertion: &Assertion) -> DateTime<Utc> {
+    let conditions = assertion
+        .conditions
+        .as_ref()
+        .and_then(|c| c.not_on_or_after);
+    let confirmations = assertion
+        .subject
+        .iter()
+        .flat_map(|s| s.subject_confirmations.iter().flatten())
+        .filter_map(|c| c.subject_confirmation_data.as_ref()?.not_on_or_after);
+    conditions
+        .into_iter()
+        .chain(confirmations)
+        .fold(assertion.issue_instant + MAX_ISSUE_DELAY, DateTime::max)
+}
@@ -1,4 +1,5 @@
 use crate::error::Error;
+use crate::session::SessionStore;
 use crate::state::AppState;
 use axum::extract::{Query, State};
 use axum::response::{IntoResponse, Redirect, Response};
@@ -12,7 +13,7 @@ pub struct InitiateParams {
     pub session: String,
 }
 
-/// Fetches the selected university's metadata via MDQ, constructs a SAML
+/// Looks up the selected university's metadata, constructs a SAML
 /// AuthnRequest using samael's ServiceProvider, and redirects the user to the
 /// university's SSO endpoint.
 pub async fn initiate(
@@ -22,21 +23,14 @@ pub async fn initiate(
     let session = state
         .sessions
         .get(&params.session)
+        .await?
         .ok_or_else(|| Error::SessionNotFound(params.session.clone()))?;
 
     let entity_id = session
         .selected_university
-        .as_ref()
-        .ok_or(Error::MissingUniversitySelection)?
-        .clone();
+        .ok_or(Error::MissingUniversitySelection)?;
 
-    drop(session);
-
-    let idp_metadata = state
-        .mdq_client
-        .fetch_entity(&entity_id)
-        .await
-        .map_err(|e| Error::MdqFetchFailed(e.to_string()))?;
+    let idp_metadata = state.metadata.fetch_entity(&entity_id).await?;
 
     let sp = ServiceProvider {
         entity_id: Some(state.config.entity_id.clone()),
@@ -49,26 +43,30 @@ pub async fn initiate(
     let sso_url = sp
         .sso_binding_location(HTTP_REDIRECT_BINDING)
         .ok_or_else(|| {
-            Error::MdqFetchFailed("no HTTP-Redirect SSO endpoint in university metadata".into())
+            Error::MetadataFetchFailed(
+                "no HTTP-Redirect SSO endpoint in university metadata".into(),
+            )
         })?;
 
     let authn_request = sp
         .make_authentication_request(&sso_url)
         .map_err(|e| Error::Internal(anyhow::anyhow!("{e}")))?;
 
-    state
+    let session_id = state
         .sessions
-        .update_proxy_request_id(&params.session, authn_request.id.clone());
+        .update_proxy_request_id(&params.session, authn_request.id.clone())
+        .await?
+        .ok_or(Error::SessionNotFound(params.session))?;
 
     // Use session ID as RelayState so we can look up the session when the
     // university IdP posts the response back to our ACS endpoint.
     let redirect_url = authn_request
-        .redirect(&params.session)
+        .redirect(&session_id)
         .map_err(|e| Error::Internal(anyhow::anyhow!("{e}")))?
         .ok_or_else(|| Error::Internal(anyhow::anyhow!("AuthnRequest has no destination")))?;
 
     tracing::info!(
-        session_id = params.session,
+        session_id,
         university = entity_id,
         "redirecting to university IdP"
     );
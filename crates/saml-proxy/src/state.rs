@@ -1,24 +1,28 @@
+use crate::attribute_policy::AttributePolicy;
 use crate::config::Config;
 use crate::discovery::federation_index::FederationIndex;
-use crate::session::SessionStore;
+use crate::metadata_sources::MetadataSources;
+use crate::registry::ServiceProviderRegistry;
+use crate::session::{Replays, Sessions};
 use anyhow::{Context, Result};
-use saml_mdq::{MdqCache, MdqClient};
-use std::time::Duration;
-
-const MDQ_BASE_URL: &str = "https://mdq.incommon.org";
-const MDQ_SIGNING_CERT_PEM: &[u8] = include_bytes!("../certs/inc-md-cert-mdq.pem");
+use std::sync::Arc;
 
 pub struct AppState {
     pub config: Config,
-    pub sessions: SessionStore,
-    pub mdq_client: MdqClient,
+    pub sessions: Sessions,
+    /// Assertions already accepted, shared between replicas unless sessions
+    /// or the replay cache are configured process-local.
+    pub replays: Replays,
+    pub service_providers: ServiceProviderRegistry,
+    pub attribute_policy: AttributePolicy,
+    pub metadata: Arc<MetadataSources>,
     pub federation_index: FederationIndex,
     pub idp_key_der: Vec<u8>,
     pub idp_cert_der: Vec<u8>,
 }
 
 impl AppState {
-    pub fn new(config: Config) -> Result<Self> {
+    pub async fn new(config: Config) -> Result<Self> {
         let cert_pem =
             std::fs::read(&config.idp_cert_path).context("failed to read IDP certificate")?;
         let key_pem =
@@ -37,23 +41,35 @@ impl AppState {
             .private_key_to_der()
             .context("failed to encode private key as DER")?;
 
-        let mdq_signing_cert = openssl::x509::X509::from_pem(MDQ_SIGNING_CERT_PEM)
-            .context("failed to parse MDQ signing certificate")?;
-        let mdq_signing_cert_der = mdq_signing_cert
-            .to_der()
-            .context("failed to encode MDQ signing certificate as DER")?;
+        let metadata = Arc::new(MetadataSources::load(&config.metadata_sources).await?);
+
+        let service_providers =
+            ServiceProviderRegistry::load(&config.sp_metadata, config.require_signed_requests)
+                .await?;
+
+        let attribute_policy = match &config.attribute_policy {
+            Some(path) => AttributePolicy::load(path)?,
+            None => AttributePolicy::default(),
+        };
+        for sp in attribute_policy.service_providers() {
+            if service_providers.get(sp).is_none() {
+                tracing::warn!(
+                    sp,
+                    "attribute policy names an unregistered service provider"
+                );
+            }
+        }
 
-        let cache = MdqCache::new(1000, Duration::from_secs(3600));
-        let mdq_client = MdqClient::builder(MDQ_BASE_URL)
-            .cache(cache)
-            .signing_cert(mdq_signing_cert_der)
-            .build()
-            .map_err(|e| anyhow::anyhow!(e))?;
+        let sessions = Sessions::from_config(&config.session_store).await?;
+        let replays = Replays::from_config(&config.session_store, &sessions).await?;
 
         Ok(Self {
             config,
-            sessions: SessionStore::new(),
-            mdq_client,
+            sessions,
+            replays,
+            service_providers,
+            attribute_policy,
+            metadata,
             federation_index: FederationIndex::new(),
             idp_key_der,
             idp_cert_der,
@@ -0,0 +1,189 @@
+//! String-level helpers for the metadata the proxy scans without a full XML
+//! parse. XML signatures do not cover comments, so anything read from a
+//! signed document must first go through [`signed_content`].
+
+use anyhow::Result;
+use std::borrow::Cow;
+
+/// Removes comments, and turns CDATA sections into escaped text, so that
+/// neither can be mistaken for markup by a scanner.
+pub(crate) fn without_comments(xml: &str) -> Cow<'_, str> {
+    if !xml.contains("<!--") && !xml.contains("<![CDATA[") {
+        return Cow::Borrowed(xml);
+    }
+
+    let mut out = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some(start) = rest.find("<!") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            out.push_str(
+                &cdata[..end]
+                    .replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;"),
+            );
+            rest = cdata.get(end + 3..).unwrap_or("");
+        } else {
+            // A DOCTYPE or other declaration; kept as is.
+            out.push_str("<!");
+            rest = &rest[2..];
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// The part of a document whose signature has already been verified that
+/// the signature actually covers. The first signature in the document must
+/// reference its root element, or a valid signature over some other element
+/// would vouch for everything around it (signature wrapping). Comments and
+/// the `Signature` elements themselves, whose key info is not signed, are
+/// removed.
+pub(crate) fn signed_content(xml: &str) -> Result<String> {
+    let xml = without_comments(xml);
+
+    let root_id = start_tags(&xml, None)
+        .next()
+        .and_then(|(_, tag)| attribute(tag, "ID"))
+        .ok_or_else(|| anyhow::anyhow!("signed document root has no ID"))?;
+    let reference = start_tags(&xml, Some("Reference"))
+        .next()
+        .and_then(|(_, tag)| attribute(tag, "URI"));
+    anyhow::ensure!(
+        reference.as_deref() == Some(format!("#{root_id}").as_str()),
+        "signature does not reference the document root"
+    );
+
+    let mut out = String::with_capacity(xml.len());
+    let mut rest = &xml[..];
+    while let Some((open, tag)) = start_tags(rest, Some("Signature")).next() {
+        out.push_str(&rest[..open]);
+        let name = tag.split(|c: char| c.is_whitespace() || c == '/').next();
+        let close_tag = format!("</{}>", name.unwrap_or_default());
+        rest = if tag.ends_with('/') {
+            &rest[open + tag.len() + 2..]
+        } else {
+            rest[open..]
+                .find(&close_tag)
+                .map_or("", |end| &rest[open + end + close_tag.len()..])
+        };
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Yields the offset of each element start tag named `name` (with any
+/// namespace prefix), or of every start tag when `name` is `None`, along
+/// with the tag's contents between `<` and `>`.
+fn start_tags<'a>(
+    xml: &'a str,
+    name: Option<&'a str>,
+) -> impl Iterator<Item = (usize, &'a str)> + 'a {
+    let mut search_from = 0;
+    std::iter::from_fn(move || {
+        while let Some(found) = xml[search_from..].find('<') {
+            let open = search_from + found;
+            let tag_len = xml[open..].find('>')?;
+            let tag = &xml[open + 1..open + tag_len];
+            search_from = open + 1;
+            if tag.starts_with(['/', '?', '!']) {
+                continue;
+            }
+            let element = tag
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or_default();
+            let local_name = element.rsplit(':').next().unwrap_or_default();
+            if name.is_none_or(|name| name == local_name) {
+                return Some((open, tag));
+            }
+        }
+        None
+    })
+}
+
+/// Reads an attribute from a start tag's contents.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!("{name}=");
+    let mut search_from = 0;
+    while let Some(found) = tag[search_from..].find(&pattern) {
+        let start = search_from + found;
+        search_from = start + pattern.len();
+        // `ID=` must not match the end of `entityID=`.
+        if !tag[..start].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let value = &tag[search_from..];
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &value[1..];
+        return Some(value[..value.find(quote)?].to_string());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNED: &str = r##"<?xml version="1.0"?>
+<!-- <EntitiesDescriptor ID="_outer"> -->
+<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="https://idp.example.edu" ID="_signed">
+    <ds:Signature xmlns:ds="http://www.w3.org/2000/09/xmldsig#">
+        <ds:SignedInfo><ds:Reference URI="#_signed"/></ds:SignedInfo>
+        <ds:KeyInfo><shibmd:Scope>keyinfo.example</shibmd:Scope></ds:KeyInfo>
+    </ds:Signature>
+    <md:Extensions>
+        <shibmd:Scope>example.edu</shibmd:Scope>
+        <!-- <shibmd:Scope>evil.com</shibmd:Scope> -->
+    </md:Extensions>
+</md:EntityDescriptor>"##;
+
+    #[test]
+    fn removes_comments_and_escapes_cdata() {
+        assert_eq!(
+            without_comments("<a><!-- <b/> -->x<![CDATA[<c>&]]>y<!--unterminated"),
+            "<a>x&lt;c&gt;&amp;y"
+        );
+        assert!(matches!(without_comments("<a>x</a>"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn signed_content_drops_comments_and_signatures() {
+        let content = signed_content(SIGNED).unwrap();
+        assert!(content.contains("<shibmd:Scope>example.edu</shibmd:Scope>"));
+        assert!(!content.contains("evil.com"));
+        assert!(!content.contains("keyinfo.example"));
+        assert!(!content.contains("_outer"));
+    }
+
+    #[test]
+    fn signed_content_requires_a_reference_to_the_root() {
+        // A signed inner element wrapped in an unsigned document.
+        let wrapped = format!(
+            r#"<EntitiesDescriptor ID="_wrapper"><EntityDescriptor entityID="https://evil.example"/>{}</EntitiesDescriptor>"#,
+            SIGNED.trim_start_matches(r#"<?xml version="1.0"?>"#)
+        );
+        assert!(signed_content(&wrapped).is_err());
+
+        let unreferenced = SIGNED.replace(r##"<ds:Reference URI="#_signed"/>"##, "");
+        assert!(signed_content(&unreferenced).is_err());
+        let no_root_id = SIGNED.replace(r#" ID="_signed""#, "");
+        assert!(signed_content(&no_root_id).is_err());
+    }
+
+    #[test]
+    fn reads_attributes_by_whole_name() {
+        let tag = r#"md:EntityDescriptor entityID="https://idp.example.edu" ID='_a'"#;
+        assert_eq!(attribute(tag, "ID").as_deref(), Some("_a"));
+        assert_eq!(
+            attribute(tag, "entityID").as_deref(),
+            Some("https://idp.example.edu")
+        );
+        assert_eq!(attribute(tag, "URI"), None);
+    }
+}
@@ -2,6 +2,7 @@ const MIN_QUERY_LENGTH = 2;
 const DEBOUNCE_MS = 300;
 
 const searchInput = document.querySelector("#search");
+const federationSelect = document.querySelector("#federation");
 const resultsList = document.querySelector("#results");
 const entityIdInput = document.querySelector("#entity_id");
 const submitButton = document.querySelector("#submit-btn");
@@ -10,6 +11,10 @@ if (!(searchInput instanceof HTMLInputElement)) {
   throw new TypeError("#search must be an input element");
 }
 
+if (!(federationSelect instanceof HTMLSelectElement)) {
+  throw new TypeError("#federation must be a select element");
+}
+
 if (!(resultsList instanceof HTMLElement)) {
   throw new TypeError("#results must be a DOM element");
 }
@@ -35,6 +40,14 @@ const selectEntity = (listItem) => {
   submitButton.disabled = false;
 };
 
+const federationLabel = (name, country) => {
+  if (!name) {
+    return "";
+  }
+
+  return country ? `${name} (${country})` : name;
+};
+
 const renderEntities = (entities) => {
   resultsList.textContent = "";
 
@@ -42,6 +55,13 @@ const renderEntities = (entities) => {
     const listItem = document.createElement("li");
     listItem.textContent = entity.display_name;
     listItem.dataset.entityId = entity.entity_id;
+    const badgeText = federationLabel(entity.federation, entity.country);
+    if (badgeText) {
+      const badge = document.createElement("span");
+      badge.className = "badge";
+      badge.textContent = badgeText;
+      listItem.append(badge);
+    }
     listItem.addEventListener("click", () => {
       selectEntity(listItem);
     });
@@ -49,12 +69,33 @@ const renderEntities = (entities) => {
   }
 };
 
-const fetchEntities = (query) =>
-  fetch(`/api/entities/search?q=${encodeURIComponent(query)}`)
+const fetchEntities = (query) => {
+  const params = new URLSearchParams({ q: query });
+  if (federationSelect.value) {
+    params.set("federation", federationSelect.value);
+  }
+
+  return fetch(`/api/entities/search?${params}`)
     .then((response) => response.json())
     .then((entities) => {
       renderEntities(entities);
     });
+};
+
+const fetchFederations = () =>
+  fetch("/api/federations")
+    .then((response) => response.json())
+    .then((federations) => {
+      for (const federation of federations) {
+        const option = document.createElement("option");
+        option.value = federation.name;
+        option.textContent = federationLabel(
+          federation.name,
+          federation.country,
+        );
+        federationSelect.append(option);
+      }
+    });
 
 const onSearchInput = () => {
   globalThis.clearTimeout(debounceTimer);
@@ -70,3 +111,6 @@ const onSearchInput = () => {
 };
 
 searchInput.addEventListener("input", onSearchInput);
+federationSelect.addEventListener("change", onSearchInput);
+
+fetchFederations();
@@ -2,17 +2,42 @@ use axum::http::{self, StatusCode};
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
 use flate2::Compression;
+use flate2::read::DeflateDecoder;
 use flate2::write::DeflateEncoder;
-use saml_proxy::config::Config;
+use openssl::pkey::PKey;
+use openssl::x509::X509;
+use samael::crypto::{CertificateDer, Crypto, CryptoProvider};
+use samael::idp::response_builder::build_response_template;
+use samael::schema::AuthnRequest;
+use samael::signature::DigestAlgorithm;
+use samael::traits::ToXml;
+use saml_proxy::attributes::{
+    EDU_PERSON_PRINCIPAL_NAME, EDU_PERSON_SCOPED_AFFILIATION, ProxiedAttribute, URI_NAME_FORMAT,
+};
+use saml_proxy::config::{Config, MetadataSourceConfig, ReplayCacheConfig, SessionStoreConfig};
 use saml_proxy::discovery::federation_index::EntityEntry;
+use saml_proxy::session::Sessions;
 use saml_proxy::state::AppState;
-use std::io::Write;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tower::ServiceExt;
 
 const AUTHN_REQUEST_XML: &str = r#"<samlp:AuthnRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" ID="_test" Version="2.0" IssueInstant="2026-01-01T00:00:00Z" AssertionConsumerServiceURL="http://localhost:3000/acs"><saml:Issuer xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion">http://localhost:3000</saml:Issuer></samlp:AuthnRequest>"#;
 
+const SP_METADATA_PATH: &str = "tests/fixtures/sp-metadata.xml";
+const IDP_METADATA_PATH: &str = "tests/fixtures/idp-metadata.xml";
+const STAND_IN_IDP_ENTITY_ID: &str = "https://idp.stand-in.test";
+const STAND_IN_IDP_CERT_PATH: &str = "tests/fixtures/stand-in-idp-cert.pem";
+const STAND_IN_IDP_KEY_PATH: &str = "tests/fixtures/stand-in-idp-key.pem";
+
+fn authn_request(issuer: &str, acs_url: &str) -> String {
+    format!(
+        r#"<samlp:AuthnRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" ID="_test" Version="2.0" IssueInstant="2026-01-01T00:00:00Z" AssertionConsumerServiceURL="{acs_url}"><saml:Issuer xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion">{issuer}</saml:Issuer></samlp:AuthnRequest>"#
+    )
+}
+
 fn encode_redirect_binding(xml: &str) -> String {
     let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
     encoder.write_all(xml.as_bytes()).unwrap();
@@ -26,14 +51,51 @@ fn test_config() -> Config {
         entity_id: "http://localhost:8443/saml/idp".into(),
         idp_cert_path: "certs/idp-cert.pem".into(),
         idp_key_path: "certs/idp-key.pem".into(),
+        sp_metadata: vec![SP_METADATA_PATH.parse().unwrap()],
+        require_signed_requests: false,
+        attribute_policy: None,
+        metadata_sources: vec![MetadataSourceConfig::Entity {
+            path: IDP_METADATA_PATH.into(),
+        }],
+        session_store: SessionStoreConfig::Memory,
         host: "127.0.0.1".into(),
         port: 8443,
     }
 }
 
+/// [`test_config`] with stateless sessions sealed under a key of `key_byte`s,
+/// and a replay cache local to the replica.
+fn stateless_config(key_byte: u8) -> Config {
+    stateless_config_with(key_byte, ReplayCacheConfig::Memory)
+}
+
+fn stateless_config_with(key_byte: u8, replay_cache: ReplayCacheConfig) -> Config {
+    Config {
+        session_store: SessionStoreConfig::Stateless {
+            keys: vec![vec![key_byte; 32]],
+            max_token_len: saml_proxy::session::DEFAULT_MAX_TOKEN_LEN,
+            replay_cache,
+        },
+        ..test_config()
+    }
+}
+
 async fn test_app() -> axum::Router {
-    let config = test_config();
-    let state = Arc::new(AppState::new(config).expect("failed to create AppState"));
+    test_app_with(test_config()).await
+}
+
+async fn test_app_with(config: Config) -> axum::Router {
+    test_app_from(test_state(config).await).await
+}
+
+async fn test_state(config: Config) -> AppState {
+    AppState::new(config)
+        .await
+        .expect("failed to create AppState")
+}
+
+async fn test_app_from(state: AppState) -> axum::Router {
+    let state = Arc::new(state);
 
     // Pre-populate the federation index so search works without fetching the
     // real InCommon aggregate.
@@ -43,10 +105,14 @@ async fn test_app() -> axum::Router {
             EntityEntry {
                 entity_id: "https://idp.example.edu".into(),
                 display_name: "Example University".into(),
+                ..Default::default()
             },
             EntityEntry {
                 entity_id: "https://login.cmu.edu/idp/shibboleth".into(),
                 display_name: "Carnegie Mellon University".into(),
+                registration_authority: Some("https://incommon.org".into()),
+                federation: Some("InCommon".into()),
+                country: Some("US".into()),
             },
         ];
     }
@@ -91,6 +157,47 @@ async fn sso_creates_session_and_redirects_to_discovery() {
     );
 }
 
+async fn sso_status(xml: &str) -> StatusCode {
+    let app = test_app().await;
+
+    let encoded = encode_redirect_binding(xml);
+    let uri = format!("/saml/sso?SAMLRequest={}", urlencoding::encode(&encoded));
+
+    app.oneshot(
+        http::Request::builder()
+            .uri(&uri)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+    .status()
+}
+
+#[tokio::test]
+async fn sso_rejects_spoofed_acs_url() {
+    for acs_url in [
+        "https://attacker.example.com/acs",
+        "http://localhost:3000/acs/../steal",
+        "http://localhost:3000.attacker.example.com/acs",
+    ] {
+        let xml = authn_request("http://localhost:3000", acs_url);
+        assert_eq!(sso_status(&xml).await, StatusCode::BAD_REQUEST, "{acs_url}");
+    }
+}
+
+#[tokio::test]
+async fn sso_rejects_unknown_issuer() {
+    let xml = authn_request("https://attacker.example.com", "http://localhost:3000/acs");
+    assert_eq!(sso_status(&xml).await, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn sso_uses_default_acs_when_request_has_none() {
+    let xml = r#"<samlp:AuthnRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" ID="_test" Version="2.0" IssueInstant="2026-01-01T00:00:00Z"><saml:Issuer xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion">http://localhost:3000</saml:Issuer></samlp:AuthnRequest>"#;
+    assert_eq!(sso_status(xml).await, StatusCode::SEE_OTHER);
+}
+
 #[tokio::test]
 async fn full_discovery_flow() {
     let app = test_app().await;
@@ -196,6 +303,361 @@ async fn full_discovery_flow() {
     assert!(initiate_path.contains(session_id));
 }
 
+async fn get(app: &axum::Router, uri: &str) -> http::Response<axum::body::Body> {
+    app.clone()
+        .oneshot(
+            http::Request::builder()
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+async fn post_form(
+    app: &axum::Router,
+    uri: &str,
+    fields: &[(&str, &str)],
+) -> http::Response<axum::body::Body> {
+    let body = fields
+        .iter()
+        .map(|(k, v)| format!("{k}={}", urlencoding::encode(v)))
+        .collect::<Vec<_>>()
+        .join("&");
+    app.clone()
+        .oneshot(
+            http::Request::builder()
+                .method(http::Method::POST)
+                .uri(uri)
+                .header(
+                    http::header::CONTENT_TYPE,
+                    "application/x-www-form-urlencoded",
+                )
+                .body(axum::body::Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+/// Answers the proxy's AuthnRequest the way a university IdP would: a
+/// Response with an assertion signed by the stand-in IdP's key, releasing
+/// the principal name and each of `affiliations`.
+fn stand_in_idp_response(
+    config: &Config,
+    in_response_to: &str,
+    eppn: &str,
+    affiliations: &[&str],
+) -> String {
+    let cert = X509::from_pem(&std::fs::read(STAND_IN_IDP_CERT_PATH).unwrap()).unwrap();
+    let cert_der = CertificateDer::from(cert.to_der().unwrap());
+    let key = PKey::private_key_from_pem(&std::fs::read(STAND_IN_IDP_KEY_PATH).unwrap()).unwrap();
+    let key_der = key.rsa().unwrap().private_key_to_der().unwrap();
+
+    let released = |values: Vec<String>| ProxiedAttribute {
+        name_format: Some(URI_NAME_FORMAT.into()),
+        friendly_name: None,
+        values,
+    };
+    let mut attributes = BTreeMap::from([(
+        EDU_PERSON_PRINCIPAL_NAME.to_string(),
+        released(vec![eppn.to_string()]),
+    )]);
+    if !affiliations.is_empty() {
+        attributes.insert(
+            EDU_PERSON_SCOPED_AFFILIATION.to_string(),
+            released(affiliations.iter().map(|a| a.to_string()).collect()),
+        );
+    }
+
+    let mut response = build_response_template(
+        &cert_der,
+        eppn,
+        &config.entity_id,
+        STAND_IN_IDP_ENTITY_ID,
+        &format!("{}/sp/acs", config.base_url),
+        in_response_to,
+        &[],
+    );
+    if let Some(assertion) = response.assertion.as_mut() {
+        assertion.attribute_statements =
+            saml_proxy::attributes::attribute_statement(&attributes).map(|stmt| vec![stmt]);
+    }
+    if let (Some(mut sig), Some(assertion)) =
+        (response.signature.take(), response.assertion.as_mut())
+    {
+        if let Some(reference) = sig.signed_info.reference.first_mut() {
+            reference.uri = Some(format!("#{}", assertion.id));
+            reference.digest_method.algorithm = DigestAlgorithm::Sha256;
+        }
+        assertion.signature = Some(sig);
+    }
+
+    let signed = Crypto::sign_xml(&response.to_string().unwrap(), &key_der).unwrap();
+    STANDARD.encode(signed)
+}
+
+/// Takes a login from the SP's AuthnRequest through discovery to the stand-in
+/// IdP, and returns the IdP's signed response with the RelayState to post it
+/// back to the proxy with.
+async fn stand_in_login(
+    app: &axum::Router,
+    config: &Config,
+    affiliations: &[&str],
+) -> (String, String) {
+    // SP -> proxy
+    let encoded = encode_redirect_binding(AUTHN_REQUEST_XML);
+    let response = get(
+        app,
+        &format!(
+            "/saml/sso?SAMLRequest={}&RelayState=sp-state",
+            urlencoding::encode(&encoded)
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let session_id = extract_location(&response)
+        .strip_prefix("/discovery?session=")
+        .expect("expected redirect to discovery")
+        .to_string();
+
+    // Discovery
+    let response = post_form(
+        app,
+        "/discovery",
+        &[
+            ("session_id", session_id.as_str()),
+            ("entity_id", STAND_IN_IDP_ENTITY_ID),
+        ],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let initiate_path = extract_location(&response).to_string();
+
+    // Proxy -> stand-in IdP
+    let response = get(app, &initiate_path).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let sso_url = reqwest::Url::parse(extract_location(&response)).unwrap();
+    assert_eq!(sso_url.host_str(), Some("idp.stand-in.test"));
+    let mut query: HashMap<_, _> = sso_url.query_pairs().into_owned().collect();
+    let relay_state = query.remove("RelayState").expect("missing RelayState");
+
+    let mut request_xml = String::new();
+    DeflateDecoder::new(&STANDARD.decode(&query["SAMLRequest"]).unwrap()[..])
+        .read_to_string(&mut request_xml)
+        .unwrap();
+    let proxy_request: AuthnRequest = request_xml.parse().unwrap();
+
+    let saml_response = stand_in_idp_response(
+        config,
+        &proxy_request.id,
+        "alice@stand-in.test",
+        affiliations,
+    );
+    (saml_response, relay_state)
+}
+
+/// Runs a complete login without network access: the university is a
+/// stand-in IdP loaded from a local EntityDescriptor file.
+#[tokio::test]
+async fn full_flow_against_stand_in_idp() {
+    let config = test_config();
+    let app = test_app_with(test_config()).await;
+    let (saml_response, relay_state) = stand_in_login(
+        &app,
+        &config,
+        &[
+            "member@stand-in.test",
+            "student@stand-in.test",
+            "faculty@elsewhere.test",
+        ],
+    )
+    .await;
+
+    // Stand-in IdP -> proxy -> SP
+    let response = post_form(
+        &app,
+        "/sp/acs",
+        &[
+            ("SAMLResponse", saml_response.as_str()),
+            ("RelayState", relay_state.as_str()),
+        ],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = String::from_utf8(
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec(),
+    )
+    .unwrap();
+    assert!(body.contains(r#"action="http://localhost:3000/acs""#));
+    assert!(body.contains(r#"name="RelayState" value="sp-state""#));
+
+    let forwarded = body
+        .split(r#"name="SAMLResponse" value=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("missing SAMLResponse");
+    let forwarded = String::from_utf8(STANDARD.decode(forwarded).unwrap()).unwrap();
+    assert!(forwarded.contains(r#"InResponseTo="_test""#));
+    assert!(forwarded.contains("alice@stand-in.test"));
+    // Every value of a multi-valued attribute is passed on.
+    assert!(forwarded.contains("member@stand-in.test"));
+    assert!(forwarded.contains("student@stand-in.test"));
+    // Outside the scopes in the stand-in IdP's metadata.
+    assert!(!forwarded.contains("faculty@elsewhere.test"));
+
+    // The session was consumed with the response.
+    let response = post_form(
+        &app,
+        "/sp/acs",
+        &[
+            ("SAMLResponse", saml_response.as_str()),
+            ("RelayState", relay_state.as_str()),
+        ],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// A stateless session token survives its use, so only the replay cache
+/// stops the same response from being accepted twice.
+#[tokio::test]
+async fn stateless_mode_rejects_replayed_assertion() {
+    let app = test_app_with(stateless_config(7)).await;
+    let (saml_response, relay_state) = stand_in_login(&app, &stateless_config(7), &[]).await;
+    let form = [
+        ("SAMLResponse", saml_response.as_str()),
+        ("RelayState", relay_state.as_str()),
+    ];
+
+    let response = post_form(&app, "/sp/acs", &form).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = post_form(&app, "/sp/acs", &form).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+/// Posts the response a login on one replica produced to that replica, then
+/// to another holding the same session keys.
+async fn replay_across_replicas(first: AppState, second: AppState) {
+    let config = stateless_config(7);
+    let first = test_app_from(first).await;
+    let second = test_app_from(second).await;
+    let (saml_response, relay_state) = stand_in_login(&first, &config, &[]).await;
+    let form = [
+        ("SAMLResponse", saml_response.as_str()),
+        ("RelayState", relay_state.as_str()),
+    ];
+
+    let response = post_form(&first, "/sp/acs", &form).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The token is just as valid on the second replica; only the shared
+    // replay cache stops it there.
+    let response = post_form(&second, "/sp/acs", &form).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn stateless_replicas_share_replay_cache() {
+    let first = test_state(stateless_config(7)).await;
+    let mut second = test_state(stateless_config(7)).await;
+    second.replays = first.replays.clone();
+    replay_across_replicas(first, second).await;
+}
+
+/// Needs a running Valkey; see the session store tests.
+#[tokio::test]
+#[ignore]
+async fn stateless_replicas_share_valkey_replay_cache() {
+    let url = std::env::var("SAML_PROXY_TEST_VALKEY_URL")
+        .unwrap_or_else(|_| "redis://127.0.0.1:6379".into());
+    let config = || stateless_config_with(7, ReplayCacheConfig::Valkey { url: url.clone() });
+    replay_across_replicas(test_state(config()).await, test_state(config()).await).await;
+}
+
+#[tokio::test]
+async fn stateless_sessions_travel_in_the_token() {
+    let app = test_app_with(stateless_config(7)).await;
+
+    let encoded = encode_redirect_binding(AUTHN_REQUEST_XML);
+    let uri = format!(
+        "/saml/sso?SAMLRequest={}&RelayState=test",
+        urlencoding::encode(&encoded)
+    );
+    let response = app
+        .clone()
+        .oneshot(
+            http::Request::builder()
+                .uri(&uri)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let discovery_path = extract_location(&response).to_string();
+    let token = discovery_path
+        .strip_prefix("/discovery?session=")
+        .expect("unexpected redirect path");
+
+    let response = app
+        .clone()
+        .oneshot(
+            http::Request::builder()
+                .uri(&discovery_path)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Selecting a university reseals the session into a new token.
+    let form_body = format!(
+        "session_id={}&entity_id={}",
+        urlencoding::encode(token),
+        urlencoding::encode("https://login.cmu.edu/idp/shibboleth")
+    );
+    let response = app
+        .clone()
+        .oneshot(
+            http::Request::builder()
+                .method(http::Method::POST)
+                .uri("/discovery")
+                .header(
+                    http::header::CONTENT_TYPE,
+                    "application/x-www-form-urlencoded",
+                )
+                .body(axum::body::Body::from(form_body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let updated = extract_location(&response)
+        .strip_prefix("/sp/initiate?session=")
+        .expect("expected redirect to sp/initiate");
+    assert_ne!(updated, token);
+
+    // A token sealed under another key is not a session.
+    let other_key = test_app_with(stateless_config(8)).await;
+    let response = other_key
+        .oneshot(
+            http::Request::builder()
+                .uri(&discovery_path)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn search_returns_empty_for_no_match() {
     let app = test_app().await;
@@ -221,6 +683,38 @@ async fn search_returns_empty_for_no_match() {
     assert_eq!(body, "[]");
 }
 
+#[tokio::test]
+async fn search_filters_by_federation() {
+    let app = test_app().await;
+
+    let response = get(
+        &app,
+        "/api/entities/search?q=University&federation=InCommon",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let results: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let results = results.as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["display_name"], "Carnegie Mellon University");
+    assert_eq!(results[0]["federation"], "InCommon");
+    assert_eq!(results[0]["country"], "US");
+
+    let response = get(&app, "/api/federations").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let federations: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        federations,
+        serde_json::json!([{ "name": "InCommon", "country": "US", "count": 1 }])
+    );
+}
+
 #[tokio::test]
 async fn discovery_page_rejects_invalid_session() {
     let app = test_app().await;
@@ -327,22 +821,26 @@ async fn manual_discovery_ui() {
         entity_id: format!("{base_url}/saml/idp"),
         idp_cert_path: "certs/idp-cert.pem".into(),
         idp_key_path: "certs/idp-key.pem".into(),
+        sp_metadata: vec![SP_METADATA_PATH.parse().unwrap()],
+        require_signed_requests: false,
+        metadata_sources: vec![MetadataSourceConfig::incommon()],
+        session_store: SessionStoreConfig::Memory,
         host: "127.0.0.1".into(),
         port,
     };
 
-    let state = Arc::new(AppState::new(config).expect("failed to create AppState"));
+    let state = Arc::new(
+        AppState::new(config)
+            .await
+            .expect("failed to create AppState"),
+    );
 
-    tokio::spawn(saml_proxy::session::session_cleanup_task(
-        state.sessions.clone(),
-    ));
+    if let Sessions::Memory(store) = &state.sessions {
+        tokio::spawn(saml_proxy::session::session_cleanup_task(store.clone()));
+    }
 
     eprintln!("  Fetching InCommon federation index...");
-    state
-        .federation_index
-        .refresh()
-        .await
-        .expect("failed to fetch federation index");
+    state.federation_index.refresh(&state.metadata).await;
     let count = state.federation_index.entries().read().await.len();
     eprintln!("  Loaded {count} IdP entities");
 
@@ -0,0 +1,123 @@
+use core::any::type_name;
+use core::time::Duration;
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::Extension;
+use axum::extract::MatchedPath;
+use axum::http::request::Parts;
+use axum::middleware::AddExtension;
+use tower::Layer;
+use tracing::Instrument;
+
+use crate::Policy;
+
+/// One authorization outcome, as seen by a [`DecisionObserver`].
+#[derive(Debug)]
+pub struct Decision<'a> {
+    /// Type name of the policy that was checked, e.g.
+    /// `terrier_server::auth::policies::IsHackathonAdmin`.
+    pub policy: &'static str,
+    /// The matched route template, e.g. `/hackathons/{hackathon_id}/roles`.
+    /// `None` outside a router, e.g. in a fallback.
+    pub route: Option<&'a str>,
+    pub elapsed: Duration,
+    pub allowed: bool,
+    /// The request as the policy left it, for observers that want the
+    /// caller's identity or other extensions.
+    pub parts: &'a Parts,
+}
+
+/// Receives every decision made by an [`Authorized`](crate::Authorized)
+/// extractor or a [`PolicyLayer`](crate::PolicyLayer) on requests that
+/// passed through an [`ObserverLayer`].
+///
+/// Called inline before the request continues, so anything slow (writing
+/// to a database) belongs on a channel or a spawned task.
+pub trait DecisionObserver: Send + Sync + 'static {
+    fn observe(&self, decision: &Decision<'_>);
+}
+
+impl<F> DecisionObserver for F
+where
+    F: Fn(&Decision<'_>) + Send + Sync + 'static,
+{
+    fn observe(&self, decision: &Decision<'_>) {
+        self(decision)
+    }
+}
+
+/// The extensions entry an [`ObserverLayer`] adds.
+#[derive(Clone)]
+pub struct Observer(Arc<dyn DecisionObserver>);
+
+/// Registers a [`DecisionObserver`] for every request under it.
+///
+/// Apply it outside the routes it should watch:
+///
+/// ```ignore
+/// Router::new()
+///     .route("/teams/{id}", delete(delete_team))
+///     .layer(ObserverLayer::new(AuditLog::new(db)))
+/// ```
+#[derive(Clone)]
+pub struct ObserverLayer(Extension<Observer>);
+
+impl ObserverLayer {
+    pub fn new(observer: impl DecisionObserver) -> Self {
+        Self(Extension(Observer(Arc::new(observer))))
+    }
+}
+
+impl<I> Layer<I> for ObserverLayer {
+    type Service = AddExtension<I, Observer>;
+
+    fn layer(&self, inner: I) -> Self::Service {
+        self.0.layer(inner)
+    }
+}
+
+/// Runs `P::check` inside a `slac.check` span and reports the outcome to
+/// the registered observer, if any.
+pub(crate) async fn checked<P: Policy<S>, S: 'static>(
+    parts: &mut Parts,
+    state: &S,
+) -> Result<P::Output, P::Error> {
+    let policy = type_name::<P>();
+    let span = tracing::debug_span!("slac.check", policy, allowed = tracing::field::Empty);
+
+    let start = Instant::now();
+    #[cfg(feature = "testing")]
+    let result = match crate::testing::stubbed::<P, S>(parts) {
+        Some(outcome) => outcome,
+        None => P::check(parts, state).instrument(span.clone()).await,
+    };
+    #[cfg(not(feature = "testing"))]
+    let result = P::check(parts, state).instrument(span.clone()).await;
+    let elapsed = start.elapsed();
+
+    let allowed = result.is_ok();
+    span.record("allowed", allowed);
+    span.in_scope(|| {
+        tracing::debug!(
+            elapsed_us = elapsed.as_micros() as u64,
+            "{}",
+            if allowed { "allowed" } else { "denied" }
+        )
+    });
+
+    if let Some(Observer(observer)) = parts.extensions.get::<Observer>() {
+        observer.observe(&Decision {
+            policy,
+            route: parts
+                .extensions
+                .get::<MatchedPath>()
+                .map(MatchedPath::as_str),
+            elapsed,
+            allowed,
+            parts,
+        });
+    }
+
+    result
+}
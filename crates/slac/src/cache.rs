@@ -0,0 +1,56 @@
+use core::marker::PhantomData;
+
+use axum::http::request::Parts;
+
+use crate::Policy;
+
+/// Memoizes `P` for the rest of the request.
+///
+/// The first check of `Cached<P>` runs `P` and stores its result, pass or
+/// reject, in the request's extensions; later checks of `Cached<P>` in the
+/// same request (from another extractor, a `policy!` variant or a
+/// combinator branch) return a clone of it without running `P` again. The
+/// output and proof type are unchanged, so `Authorized<Cached<P>, S>` can
+/// stand in anywhere `Authorized<P, S>` was used.
+///
+/// Opt-in, because it is only sound for checks whose outcome cannot change
+/// partway through a request, and both `Output` and `Error` must be `Clone`.
+/// Only checks spelled `Cached<P>` share the entry; a bare `P` always runs.
+pub struct Cached<P>(PhantomData<fn() -> P>);
+
+/// The extensions entry. Its type is unique per `(P, S)`, which is what keys
+/// the cache.
+struct Memo<P: Policy<S>, S>(Result<P::Output, P::Error>, PhantomData<fn() -> S>);
+
+impl<P: Policy<S>, S> Clone for Memo<P, S>
+where
+    P::Output: Clone,
+    P::Error: Clone,
+{
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+
+impl<S, P> Policy<S> for Cached<P>
+where
+    S: Send + Sync + 'static,
+    P: Policy<S>,
+    P::Output: Clone + Sync,
+    P::Error: Clone + Sync,
+{
+    type Output = P::Output;
+    type Error = P::Error;
+
+    async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+        if let Some(Memo(result, _)) = parts.extensions.get::<Memo<P, S>>() {
+            return result.clone();
+        }
+
+        let result = P::check(parts, state).await;
+        parts
+            .extensions
+            .insert(Memo::<P, S>(result.clone(), PhantomData));
+        result
+    }
+}
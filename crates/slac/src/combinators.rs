@@ -1,15 +1,24 @@
 use core::marker::PhantomData;
 
 use axum::http::request::Parts;
+use axum::response::IntoResponse;
 
 use crate::Policy;
+use crate::error::{CombineErrors, Denial, SameError};
 
-/// Try `A` first; on rejection, try `B`. Both branches must share the same
-/// `Error` type.
-pub struct Any<A, B>(PhantomData<fn() -> (A, B)>);
+/// Zero-sized, always `Send + Sync` stand-in for the combinators' type
+/// parameters, which are never instantiated.
+type Marker<T> = PhantomData<fn() -> T>;
+
+/// Try `A` first; on rejection, try `B`. By default both branches share the
+/// same `Error` type and `B`'s rejection is returned; give an `E:`
+/// [`CombineErrors`] to aggregate both rejections instead.
+pub struct Any<A, B, E = SameError>(Marker<(A, B, E)>);
 
 /// Require both `A` and `B` to pass; the proof is the pair of their outputs.
-pub struct All<A, B>(PhantomData<fn() -> (A, B)>);
+/// By default both share the same `Error` type; give an `E:`
+/// [`CombineErrors`] to let them differ, converting each via `From`.
+pub struct All<A, B, E = SameError>(Marker<(A, B, E)>);
 
 /// Result of an [`Any<A, B>`] check.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -18,7 +27,7 @@ pub enum Either<L, R> {
     Right(R),
 }
 
-impl<S, A, B> Policy<S> for Any<A, B>
+impl<S, A, B> Policy<S> for Any<A, B, SameError>
 where
     S: Send + Sync,
     A: Policy<S>,
@@ -35,7 +44,29 @@ where
     }
 }
 
-impl<S, A, B> Policy<S> for All<A, B>
+impl<S, A, B, E> Policy<S> for Any<A, B, E>
+where
+    S: Send + Sync,
+    A: Policy<S>,
+    B: Policy<S>,
+    E: CombineErrors + From<A::Error> + From<B::Error> + IntoResponse + Send + 'static,
+{
+    type Output = Either<A::Output, B::Output>;
+    type Error = E;
+
+    async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+        let a = match A::check(parts, state).await {
+            Ok(o) => return Ok(Either::Left(o)),
+            Err(e) => E::from(e),
+        };
+        match B::check(parts, state).await {
+            Ok(o) => Ok(Either::Right(o)),
+            Err(b) => Err(E::combine(vec![a, E::from(b)])),
+        }
+    }
+}
+
+impl<S, A, B> Policy<S> for All<A, B, SameError>
 where
     S: Send + Sync,
     A: Policy<S>,
@@ -50,3 +81,100 @@ where
         Ok((a, b))
     }
 }
+
+impl<S, A, B, E> Policy<S> for All<A, B, E>
+where
+    S: Send + Sync,
+    A: Policy<S>,
+    B: Policy<S>,
+    E: CombineErrors + From<A::Error> + From<B::Error> + IntoResponse + Send + 'static,
+{
+    type Output = (A::Output, B::Output);
+    type Error = E;
+
+    async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+        let a = A::check(parts, state).await.map_err(E::from)?;
+        let b = B::check(parts, state).await.map_err(E::from)?;
+        Ok((a, b))
+    }
+}
+
+/// Passes exactly when `P` denies the caller, and rejects with `E::default()`
+/// when `P` passes, e.g. `Not<IsOnTeam, TeamError>` for "anyone not yet on a
+/// team". `P`'s denial is discarded; any other error from `P` (see
+/// [`Denial`]) is converted into `E` and returned, so the check fails closed.
+pub struct Not<P, E>(PhantomData<fn() -> (P, E)>);
+
+/// Passes only while `C` holds and `P` passes, e.g. `When<BetaFlag, IsMember,
+/// BetaError>` for a route behind a feature flag. When `C` is false the check
+/// rejects with `E::default()` without running `P`, so turning a flag off
+/// closes the route; `P`'s errors are converted into `E`.
+pub struct When<C, P, E>(Marker<(C, P, E)>);
+
+/// Run `P` without rejecting a caller it denies: the proof is `Some` if `P`
+/// passed and `None` if it denied the caller. Lets one handler serve both an
+/// anonymous and an authenticated view. Any other error from `P` (see
+/// [`Denial`]) is returned, so e.g. a database outage is not served as the
+/// anonymous view.
+pub struct Optional<P>(PhantomData<fn() -> P>);
+
+/// A stateful predicate for [`When`]. Like [`Policy`], it is identified by its
+/// type and may inspect the request and app state.
+pub trait Condition<S>: Send + Sync + 'static {
+    fn holds(parts: &mut Parts, state: &S) -> impl Future<Output = bool> + Send;
+}
+
+impl<S, P, E> Policy<S> for Not<P, E>
+where
+    S: Send + Sync,
+    P: Policy<S>,
+    P::Error: Denial,
+    E: From<P::Error> + IntoResponse + Default + Send + 'static,
+{
+    type Output = ();
+    type Error = E;
+
+    async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+        match P::check(parts, state).await {
+            Ok(_) => Err(E::default()),
+            Err(e) if e.is_denial() => Ok(()),
+            Err(e) => Err(E::from(e)),
+        }
+    }
+}
+
+impl<S, C, P, E> Policy<S> for When<C, P, E>
+where
+    S: Send + Sync,
+    C: Condition<S>,
+    P: Policy<S>,
+    E: From<P::Error> + IntoResponse + Default + Send + 'static,
+{
+    type Output = P::Output;
+    type Error = E;
+
+    async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+        if !C::holds(parts, state).await {
+            return Err(E::default());
+        }
+        P::check(parts, state).await.map_err(E::from)
+    }
+}
+
+impl<S, P> Policy<S> for Optional<P>
+where
+    S: Send + Sync,
+    P: Policy<S>,
+    P::Error: Denial,
+{
+    type Output = Option<P::Output>;
+    type Error = P::Error;
+
+    async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+        match P::check(parts, state).await {
+            Ok(output) => Ok(Some(output)),
+            Err(e) if e.is_denial() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use axum::http::StatusCode;
+
+/// Default error mode for the combinators: every branch shares one `Error`
+/// type, [`Any`](crate::Any)-style combinators return the last rejection and
+/// [`All`](crate::All)-style combinators the first.
+pub struct SameError;
+
+/// An error that can stand for several rejections at once.
+///
+/// Naming such a type as the last parameter of a combinator (e.g.
+/// `Any<A, B, ApiError>`) or in `policy! { .. for S, error = ApiError { .. } }`
+/// switches it to aggregation mode: each branch's error is converted with
+/// `From`, so branches may reject with different types, and when every branch
+/// of a disjunction rejects, all of their errors are passed to
+/// [`combine`](CombineErrors::combine) in the order the branches ran.
+pub trait CombineErrors: Sized {
+    /// `errors` is never empty.
+    fn combine(errors: Vec<Self>) -> Self;
+}
+
+/// How specific a rejection is. Higher wins in [`most_specific`].
+pub trait Priority {
+    fn priority(&self) -> u8;
+}
+
+/// Whether a rejection is the policy refusing the caller, as opposed to the
+/// check itself failing. [`Not`](crate::Not) only negates denials and passes
+/// every other error through, so a failed lookup never grants access.
+pub trait Denial {
+    fn is_denial(&self) -> bool;
+}
+
+/// Picks the highest-[`Priority`] error, preferring the earliest on ties.
+/// Combinators try branches in declaration order, which by convention puts
+/// the most privileged tier first, so on a tie its rejection is the one
+/// reported. A ready-made [`CombineErrors::combine`] for prioritized errors.
+pub fn most_specific<E: Priority>(errors: Vec<E>) -> E {
+    let mut errors = errors.into_iter();
+    let first = errors
+        .next()
+        .expect("CombineErrors::combine is never called with no errors");
+    errors.fold(first, |best, e| {
+        if e.priority() > best.priority() {
+            e
+        } else {
+            best
+        }
+    })
+}
+
+/// Missing authentication is the least specific answer (a later branch may
+/// have recognised the caller and refused them for a concrete reason), and
+/// server errors are the most, since masking them hides real failures.
+impl Priority for StatusCode {
+    fn priority(&self) -> u8 {
+        match *self {
+            StatusCode::UNAUTHORIZED => 0,
+            s if s.is_server_error() => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl CombineErrors for StatusCode {
+    fn combine(errors: Vec<Self>) -> Self {
+        most_specific(errors)
+    }
+}
+
+/// Only `401` and `403` are refusals; anything else, from a bad path
+/// parameter to a server error, means the policy could not decide.
+impl Denial for StatusCode {
+    fn is_denial(&self) -> bool {
+        matches!(*self, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+    }
+}
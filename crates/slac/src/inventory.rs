@@ -0,0 +1,129 @@
+//! A listing of every documented route and the policy guarding it.
+//!
+//! Routes are registered through [`secure!`](crate::secure), which records
+//! the policy's type name on each operation, or through
+//! [`public`](crate::openapi::public) for endpoints that are open on
+//! purpose. [`Inventory::from_openapi`] reads those marks back out of the
+//! finished document, and [`Inventory::check`] fails on any route under a
+//! protected prefix that was registered with neither:
+//!
+//! ```ignore
+//! let (router, api) = OpenApiRouter::new()
+//!     .routes(secure!(IsHackathonAdmin; delete_team))
+//!     .routes(public(routes!(health)))
+//!     .split_for_parts();
+//! Inventory::from_openapi(&api).check(&["/hackathons"])?;
+//! ```
+//!
+//! The recorded policy is the one the handlers extract: `secure!` does not
+//! compile unless each of them takes [`Authorized<P, _>`](crate::Authorized)
+//! for the policy it names. Operations only documented with
+//! [`document`](crate::openapi::document) carry no such proof and are
+//! reported like any other unguarded route.
+
+use std::fmt;
+
+use serde::Serialize;
+use utoipa::openapi::OpenApi;
+
+use crate::openapi::{POLICY_EXTENSION, PUBLIC_EXTENSION, operations};
+
+/// One operation in the inventory.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Route {
+    pub method: &'static str,
+    pub path: String,
+    /// Type name of the policy the handler extracts, if the route was
+    /// registered through [`secure!`](crate::secure).
+    pub policy: Option<String>,
+    /// Registered through [`public`](crate::openapi::public).
+    pub public: bool,
+}
+
+impl Route {
+    fn guarded(&self) -> bool {
+        self.policy.is_some() || self.public
+    }
+}
+
+/// Every operation of an OpenAPI document, sorted by path then method.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Inventory {
+    routes: Vec<Route>,
+}
+
+impl Inventory {
+    pub fn from_openapi(api: &OpenApi) -> Self {
+        let mut routes = Vec::new();
+        for (path, item) in &api.paths.paths {
+            for (method, op) in operations(item) {
+                let Some(op) = op else { continue };
+                let extension = |key| op.extensions.as_ref().and_then(|e| e.get(key));
+                routes.push(Route {
+                    method,
+                    path: path.clone(),
+                    policy: extension(POLICY_EXTENSION)
+                        .and_then(|v| v.as_str())
+                        .map(str::to_owned),
+                    public: extension(PUBLIC_EXTENSION)
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false),
+                });
+            }
+        }
+        Self { routes }
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// The inventory as a JSON array of routes.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("inventory serializes to JSON")
+    }
+
+    /// Fails with every route under one of `protected` that is neither
+    /// guarded by a policy nor declared public. A prefix matches whole
+    /// segments only: `/admin` covers `/admin/users` but not `/administer`.
+    pub fn check(&self, protected: &[&str]) -> Result<(), Unguarded> {
+        let routes: Vec<_> = self
+            .routes
+            .iter()
+            .filter(|r| !r.guarded() && protected.iter().any(|p| under(&r.path, p)))
+            .cloned()
+            .collect();
+        if routes.is_empty() {
+            Ok(())
+        } else {
+            Err(Unguarded { routes })
+        }
+    }
+}
+
+fn under(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Routes under a protected prefix without a policy.
+#[derive(Debug)]
+pub struct Unguarded {
+    pub routes: Vec<Route>,
+}
+
+impl fmt::Display for Unguarded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} route(s) without a policy:", self.routes.len())?;
+        for route in &self.routes {
+            write!(f, "\n  {} {}", route.method, route.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Unguarded {}
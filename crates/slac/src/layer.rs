@@ -0,0 +1,153 @@
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use axum::http::Request;
+use axum::http::request::Parts;
+use axum::response::{IntoResponse, Response};
+use tower::{Layer, Service};
+
+use crate::Policy;
+
+/// Runs `P` as tower middleware, for routes that are easier to protect as a
+/// group than handler by handler: nested routers, static assets, WebSocket
+/// upgrades.
+///
+/// A rejection short-circuits with `P::Error`'s response. On success the
+/// output is stored in the request's extensions, where handlers read it back
+/// through `Authorized<Layered<P>, S>` without checking `P` again:
+///
+/// ```ignore
+/// let admin = Router::new()
+///     .route("/stats", get(stats))
+///     .route_layer(PolicyLayer::<IsAdmin, _>::new(state.clone()));
+///
+/// async fn stats(Authorized { data, .. }: Authorized<Layered<IsAdmin>, AppState>) {}
+/// ```
+///
+/// The output must be `Clone + Sync` to live in the extensions.
+pub struct PolicyLayer<P, S = ()> {
+    state: S,
+    _policy: PhantomData<fn() -> P>,
+}
+
+impl<P, S> PolicyLayer<P, S> {
+    /// `state` is what `P::check` runs against, usually the router's state.
+    pub fn new(state: S) -> Self {
+        Self {
+            state,
+            _policy: PhantomData,
+        }
+    }
+}
+
+impl<P, S: Clone> Clone for PolicyLayer<P, S> {
+    fn clone(&self) -> Self {
+        Self::new(self.state.clone())
+    }
+}
+
+impl<P, S: Clone, I> Layer<I> for PolicyLayer<P, S> {
+    type Service = PolicyService<P, S, I>;
+
+    fn layer(&self, inner: I) -> Self::Service {
+        PolicyService {
+            inner,
+            state: self.state.clone(),
+            _policy: PhantomData,
+        }
+    }
+}
+
+/// The service produced by [`PolicyLayer`].
+pub struct PolicyService<P, S, I> {
+    inner: I,
+    state: S,
+    _policy: PhantomData<fn() -> P>,
+}
+
+impl<P, S: Clone, I: Clone> Clone for PolicyService<P, S, I> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            state: self.state.clone(),
+            _policy: PhantomData,
+        }
+    }
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+impl<P, S, I, B> Service<Request<B>> for PolicyService<P, S, I>
+where
+    P: Policy<S>,
+    P::Output: Clone + Sync,
+    S: Clone + Send + Sync + 'static,
+    I: Service<Request<B>, Response = Response> + Clone + Send + 'static,
+    I::Future: Send,
+    B: Send + 'static,
+{
+    type Response = Response;
+    type Error = I::Error;
+    type Future = BoxFuture<Result<Response, I::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        // The clone is not ready; swap it in so the instance `poll_ready`
+        // was called on handles this request.
+        let clone = self.inner.clone();
+        let mut inner = core::mem::replace(&mut self.inner, clone);
+        let state = self.state.clone();
+
+        Box::pin(async move {
+            let (mut parts, body) = request.into_parts();
+            match crate::audit::checked::<P, S>(&mut parts, &state).await {
+                Ok(output) => {
+                    parts.extensions.insert(Proof::<P, S>(output, PhantomData));
+                    inner.call(Request::from_parts(parts, body)).await
+                }
+                Err(error) => Ok(error.into_response()),
+            }
+        })
+    }
+}
+
+/// Reads the proof a [`PolicyLayer<P, S>`] left on the request.
+///
+/// If the layer did not run, for instance because the route was mounted
+/// outside it, `P` is checked here instead, so a misplaced layer costs a
+/// check in the extractor rather than an unguarded handler.
+pub struct Layered<P>(PhantomData<fn() -> P>);
+
+/// The extensions entry. Its type is unique per `(P, S)`, so layers for
+/// different policies do not see each other's proofs.
+struct Proof<P: Policy<S>, S>(P::Output, PhantomData<fn() -> S>);
+
+impl<P: Policy<S>, S> Clone for Proof<P, S>
+where
+    P::Output: Clone,
+{
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+
+impl<S, P> Policy<S> for Layered<P>
+where
+    S: Send + Sync + 'static,
+    P: Policy<S>,
+    P::Output: Clone + Sync,
+{
+    type Output = P::Output;
+    type Error = P::Error;
+
+    async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+        match parts.extensions.get::<Proof<P, S>>() {
+            Some(Proof(output, _)) => Ok(output.clone()),
+            None => P::check(parts, state).await,
+        }
+    }
+}
@@ -5,14 +5,37 @@
 
 #![forbid(unsafe_code)]
 
+mod audit;
+mod cache;
 mod combinators;
+mod error;
+#[cfg(feature = "utoipa")]
+pub mod inventory;
+mod layer;
 mod macros;
+#[cfg(feature = "utoipa")]
+pub mod openapi;
 mod policy;
+mod scope;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod tuple;
 
-pub use combinators::{All, Any, Either};
+pub use audit::{Decision, DecisionObserver, ObserverLayer};
+pub use cache::Cached;
+pub use combinators::{All, Any, Condition, Either, Not, Optional, When};
+pub use error::{CombineErrors, Denial, Priority, SameError, most_specific};
+pub use layer::{Layered, PolicyLayer, PolicyService};
 pub use policy::{Authorized, Policy};
+pub use scope::PathParamError;
+pub use tuple::{
+    AllOf, AnyOf, Either2, Either3, Either4, Either5, Either6, Either7, Either8, Either9, Either10,
+    Either11, Either12,
+};
 
 #[doc(hidden)]
 pub mod __private {
+    pub use crate::scope::path_param;
     pub use axum::http::request::Parts;
+    pub use std::vec::Vec;
 }
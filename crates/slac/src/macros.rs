@@ -4,6 +4,13 @@
 /// all reject, the last error is returned. All variants must share the same
 /// `Error` associated type.
 ///
+/// With an `error = E` clause, `E` must implement
+/// [`CombineErrors`](crate::CombineErrors) and `From` each variant's error.
+/// Variants may then reject with different types, and when all of them do,
+/// every rejection is handed to `E::combine`; with
+/// [`most_specific`](crate::most_specific) this reports the highest-priority
+/// rejection rather than whichever variant happened to run last.
+///
 /// # Syntax
 ///
 /// ```ignore
@@ -13,9 +20,62 @@
 ///         HackathonAdmin = IsHackathonAdmin,
 ///     }
 /// }
+///
+/// policy! {
+///     pub enum SettingsAccess for AppState, error = ApiError {
+///         GlobalAdmin    = IsGlobalAdmin,
+///         HackathonAdmin = IsHackathonAdmin,
+///     }
+/// }
 /// ```
 #[macro_export]
 macro_rules! policy {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident for $state:ty, error = $error:ty {
+            $($variant:ident = $policy:ty),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis enum $name {
+            $(
+                $variant(<$policy as $crate::Policy<$state>>::Output),
+            )+
+        }
+
+        impl $crate::Policy<$state> for $name
+        where
+            $error: $crate::CombineErrors,
+            $(
+                $error: ::core::convert::From<<$policy as $crate::Policy<$state>>::Error>,
+            )+
+        {
+            type Output = Self;
+            type Error = $error;
+
+            async fn check(
+                parts: &mut $crate::__private::Parts,
+                state: &$state,
+            ) -> ::core::result::Result<Self::Output, Self::Error> {
+                let mut errors = $crate::__private::Vec::new();
+
+                $(
+                    match <$policy as $crate::Policy<$state>>::check(parts, state).await {
+                        ::core::result::Result::Ok(o) => {
+                            return ::core::result::Result::Ok(Self::$variant(o));
+                        }
+                        ::core::result::Result::Err(e) => {
+                            errors.push(<$error as ::core::convert::From<_>>::from(e));
+                        }
+                    }
+                )+
+
+                ::core::result::Result::Err(
+                    <$error as $crate::CombineErrors>::combine(errors),
+                )
+            }
+        }
+    };
     (
         $(#[$attr:meta])*
         $vis:vis enum $name:ident for $state:ty {
@@ -75,3 +135,83 @@ macro_rules! policy {
         }
     };
 }
+
+/// Builds a [`Policy`](crate::Policy) for the common "load the resource
+/// named in the path, then check something about it" shape.
+///
+/// The generated check reads the `$param` segment of the matched route,
+/// hands it to the loader, then evaluates each `require` in order,
+/// rejecting with its `else` value on the first that is false. The proof
+/// is the loaded value, or the `output` projection of it.
+///
+/// The loader is an `async fn(&mut Parts, &S, String) -> Result<T, E>`,
+/// usually defined next to the state so it can also resolve the caller. The
+/// declared `error` type must implement `From<E>` and
+/// `From<`[`PathParamError`](crate::PathParamError)`>`.
+///
+/// # Syntax
+///
+/// ```ignore
+/// scoped_policy! {
+///     /// The caller owns the team in the `{team_id}` path segment.
+///     pub struct IsTeamOwner for AppState, error = ApiError {
+///         let team: TeamScope = load_team["team_id"];
+///         require team.owner_id == team.caller.id, else ApiError::Forbidden;
+///         require !team.archived, else ApiError::Conflict("team is archived");
+///         output Team = team.into_model();
+///     }
+/// }
+/// ```
+///
+/// Without `output`, the proof is the loaded `TeamScope` itself.
+#[macro_export]
+macro_rules! scoped_policy {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident for $state:ty, error = $error:ty {
+            let $scope:ident: $loaded:ty = $load:path[$param:literal];
+            $(require $pred:expr, else $deny:expr;)*
+            output $output:ty = $project:expr;
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name;
+
+        impl $crate::Policy<$state> for $name {
+            type Output = $output;
+            type Error = $error;
+
+            async fn check(
+                parts: &mut $crate::__private::Parts,
+                state: &$state,
+            ) -> ::core::result::Result<Self::Output, Self::Error> {
+                let id = $crate::__private::path_param(parts, $param).await?;
+                let $scope: $loaded = $load(parts, state, id).await?;
+                $(
+                    if !$pred {
+                        return ::core::result::Result::Err(
+                            <$error as ::core::convert::From<_>>::from($deny),
+                        );
+                    }
+                )*
+                ::core::result::Result::Ok($project)
+            }
+        }
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident for $state:ty, error = $error:ty {
+            let $scope:ident: $loaded:ty = $load:path[$param:literal];
+            $(require $pred:expr, else $deny:expr;)*
+        }
+    ) => {
+        $crate::scoped_policy! {
+            $(#[$attr])*
+            $vis struct $name for $state, error = $error {
+                let $scope: $loaded = $load[$param];
+                $(require $pred, else $deny;)*
+                output $loaded = $scope;
+            }
+        }
+    };
+}
@@ -0,0 +1,414 @@
+//! OpenAPI security documentation derived from policy types.
+//!
+//! Hand-written `security(...)` annotations drift from the policy a handler
+//! actually checks (RFC 0009, open question 2). Instead, each atomic policy
+//! implements [`DescribePolicy`], the combinators derive their description
+//! from their parts, and [`secure!`](crate::secure) writes the result into
+//! the operations produced by `utoipa_axum::routes!`:
+//!
+//! ```ignore
+//! OpenApiRouter::new()
+//!     .routes(slac::secure!(IsHackathonAdmin; delete_team))
+//! ```
+//!
+//! The policy is named once more next to the handlers, but `secure!` only
+//! compiles when every handler takes [`Authorized<P, _>`](crate::Authorized)
+//! for exactly that policy, so the document cannot drift from the check.
+
+use std::any::type_name;
+use std::collections::{BTreeMap, BTreeSet};
+
+use axum::http::StatusCode;
+use utoipa::openapi::path::{Operation, PathItem, Paths};
+use utoipa::openapi::schema::Schema;
+use utoipa::openapi::security::SecurityRequirement;
+use utoipa::openapi::{RefOr, Response};
+
+use crate::cache::Cached;
+use crate::combinators::{All, Any, Not, Optional, When};
+use crate::layer::Layered;
+use crate::policy::{Authorized, Policy};
+use crate::tuple::{AllOf, AnyOf};
+
+/// One way to satisfy a policy: each named security scheme with the scopes
+/// (or, for non-OAuth schemes, roles) it must carry. Empty means anonymous
+/// access is allowed.
+pub type Requirement = BTreeMap<&'static str, BTreeSet<&'static str>>;
+
+/// How a policy appears in an OpenAPI document.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Security {
+    /// Alternatives, any one of which satisfies the policy. Empty means the
+    /// policy places no security requirement on the operation.
+    pub requirements: Vec<Requirement>,
+    /// Statuses the policy may reject with.
+    pub rejections: BTreeSet<u16>,
+}
+
+impl Security {
+    /// Documents nothing.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Requires authenticating with `scheme`; rejects with 401 otherwise.
+    pub fn scheme(scheme: &'static str) -> Self {
+        Self::scopes(scheme, []).rejects(StatusCode::UNAUTHORIZED)
+    }
+
+    /// Requires authenticating with `scheme` and holding every one of
+    /// `scopes`; rejects with 401 or 403.
+    pub fn scopes(scheme: &'static str, scopes: impl IntoIterator<Item = &'static str>) -> Self {
+        let scopes: BTreeSet<_> = scopes.into_iter().collect();
+        let mut security = Self {
+            requirements: vec![BTreeMap::from([(scheme, scopes.clone())])],
+            rejections: BTreeSet::from([StatusCode::UNAUTHORIZED.as_u16()]),
+        };
+        if !scopes.is_empty() {
+            security.rejections.insert(StatusCode::FORBIDDEN.as_u16());
+        }
+        security
+    }
+
+    /// Adds `status` to the documented rejections.
+    pub fn rejects(mut self, status: StatusCode) -> Self {
+        self.rejections.insert(status.as_u16());
+        self
+    }
+
+    /// Satisfied by either `self` or `other`.
+    pub fn or(mut self, other: Self) -> Self {
+        // A side without requirements admits anyone, and so does the union.
+        if self.requirements.is_empty() || other.requirements.is_empty() {
+            self.requirements.clear();
+        } else {
+            for r in other.requirements {
+                if !self.requirements.contains(&r) {
+                    self.requirements.push(r);
+                }
+            }
+        }
+        self.rejections.extend(other.rejections);
+        self
+    }
+
+    /// Satisfied only by meeting both `self` and `other`.
+    pub fn and(mut self, other: Self) -> Self {
+        self.requirements = match (self.requirements.is_empty(), other.requirements.is_empty()) {
+            (true, _) => other.requirements,
+            (_, true) => self.requirements,
+            _ => {
+                let mut product = Vec::new();
+                for a in &self.requirements {
+                    for b in &other.requirements {
+                        let mut merged = a.clone();
+                        for (scheme, scopes) in b {
+                            merged
+                                .entry(scheme)
+                                .or_default()
+                                .extend(scopes.iter().copied());
+                        }
+                        if !product.contains(&merged) {
+                            product.push(merged);
+                        }
+                    }
+                }
+                product
+            }
+        };
+        self.rejections.extend(other.rejections);
+        self
+    }
+
+    /// Anonymous callers are also let through.
+    pub fn optional(mut self) -> Self {
+        if !self.requirements.is_empty() && !self.requirements.contains(&Requirement::new()) {
+            self.requirements.push(Requirement::new());
+        }
+        self
+    }
+
+    fn to_requirements(&self) -> Vec<SecurityRequirement> {
+        self.requirements
+            .iter()
+            .map(|r| {
+                r.iter()
+                    .fold(SecurityRequirement::default(), |req, (scheme, scopes)| {
+                        req.add(*scheme, scopes.iter().copied())
+                    })
+            })
+            .collect()
+    }
+
+    /// Writes the requirements and rejection responses into `op`. Responses
+    /// the operation already documents are left alone.
+    pub fn apply(&self, op: &mut Operation) {
+        if !self.requirements.is_empty() {
+            op.security = Some(self.to_requirements());
+        }
+        for status in &self.rejections {
+            let reason = StatusCode::from_u16(*status)
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or_default();
+            op.responses
+                .responses
+                .entry(status.to_string())
+                .or_insert_with(|| RefOr::T(Response::new(reason)));
+        }
+    }
+}
+
+/// Implemented by policies that can describe themselves in OpenAPI.
+pub trait DescribePolicy {
+    fn security() -> Security;
+}
+
+/// Operation extension naming the policy [`secure!`](crate::secure)
+/// checked, read back by the [route inventory](crate::inventory).
+pub const POLICY_EXTENSION: &str = "x-slac-policy";
+
+/// Operation extension set by [`public`].
+pub const PUBLIC_EXTENSION: &str = "x-slac-public";
+
+type Routes<R> = (Vec<(String, RefOr<Schema>)>, Paths, R);
+
+pub(crate) fn operations(item: &PathItem) -> [(&'static str, Option<&Operation>); 8] {
+    [
+        ("GET", item.get.as_ref()),
+        ("PUT", item.put.as_ref()),
+        ("POST", item.post.as_ref()),
+        ("DELETE", item.delete.as_ref()),
+        ("OPTIONS", item.options.as_ref()),
+        ("HEAD", item.head.as_ref()),
+        ("PATCH", item.patch.as_ref()),
+        ("TRACE", item.trace.as_ref()),
+    ]
+}
+
+fn operations_mut(paths: &mut Paths) -> impl Iterator<Item = &mut Operation> {
+    paths.paths.values_mut().flat_map(|item| {
+        [
+            &mut item.get,
+            &mut item.put,
+            &mut item.post,
+            &mut item.delete,
+            &mut item.options,
+            &mut item.head,
+            &mut item.patch,
+            &mut item.trace,
+        ]
+        .into_iter()
+        .flatten()
+    })
+}
+
+fn mark(op: &mut Operation, key: &str, value: serde_json::Value) {
+    op.extensions
+        .get_or_insert_with(Default::default)
+        .insert(key.into(), value);
+}
+
+/// Writes `P`'s security requirements and rejections into every operation
+/// in a `utoipa_axum::routes!` group. Nothing ties the handlers to `P`, so
+/// the route inventory still counts these operations as unguarded; register
+/// routes with [`secure!`](crate::secure) instead.
+pub fn document<P: DescribePolicy, R>((schemas, mut paths, router): Routes<R>) -> Routes<R> {
+    let security = P::security();
+    for op in operations_mut(&mut paths) {
+        security.apply(op);
+    }
+    (schemas, paths, router)
+}
+
+/// [`document`], plus the inventory's mark. Only called by
+/// [`secure!`](crate::secure) once it has checked the handlers.
+#[doc(hidden)]
+pub fn secure<P: DescribePolicy, R>(routes: Routes<R>) -> Routes<R> {
+    let (schemas, mut paths, router) = document::<P, R>(routes);
+    for op in operations_mut(&mut paths) {
+        mark(op, POLICY_EXTENSION, type_name::<P>().into());
+    }
+    (schemas, paths, router)
+}
+
+/// Declares every operation in a `utoipa_axum::routes!` group public on
+/// purpose, so the route inventory does not report it as unguarded.
+pub fn public<R>((schemas, mut paths, router): Routes<R>) -> Routes<R> {
+    for op in operations_mut(&mut paths) {
+        mark(op, PUBLIC_EXTENSION, true.into());
+    }
+    (schemas, paths, router)
+}
+
+/// Implemented for every handler that takes `Authorized<P, S>` as one of its
+/// arguments, whatever the others are. `M` records the argument's position
+/// and is always inferred.
+pub trait Extracts<P, M> {}
+
+/// Compiles only if `handler` extracts `Authorized<P, _>`.
+pub fn extracts<P, M, H: Extracts<P, M>>(_handler: &H) {}
+
+macro_rules! extracts {
+    ([$($before:ident),*] []) => {};
+    ([$($before:ident),*] [$current:ident $(, $after:ident)*]) => {
+        impl<F, R, P, S, $($before,)* $($after,)*>
+            Extracts<P, (($($before,)*), ($($after,)*), S, R)> for F
+        where
+            F: FnOnce($($before,)* Authorized<P, S>, $($after,)*) -> R,
+            P: Policy<S>,
+        {
+        }
+
+        extracts!([$($before,)* $current] [$($after),*]);
+    };
+}
+
+// Axum handlers take at most 16 arguments.
+extracts!([][T1]);
+extracts!([] [T1, T2]);
+extracts!([] [T1, T2, T3]);
+extracts!([] [T1, T2, T3, T4]);
+extracts!([] [T1, T2, T3, T4, T5]);
+extracts!([] [T1, T2, T3, T4, T5, T6]);
+extracts!([] [T1, T2, T3, T4, T5, T6, T7]);
+extracts!([] [T1, T2, T3, T4, T5, T6, T7, T8]);
+extracts!([] [T1, T2, T3, T4, T5, T6, T7, T8, T9]);
+extracts!([] [T1, T2, T3, T4, T5, T6, T7, T8, T9, T10]);
+extracts!([] [T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11]);
+extracts!([] [T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12]);
+extracts!([] [T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13]);
+extracts!([] [T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14]);
+extracts!([] [T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15]);
+extracts!([] [T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16]);
+
+/// Builds a `utoipa_axum::routes!` group documented as guarded by a policy,
+/// as by [`openapi::document`](crate::openapi::document), and listed under
+/// it in the [route inventory](crate::inventory), after checking at compile
+/// time that every handler extracts
+/// [`Authorized`](crate::Authorized) for that same policy. The calling crate
+/// must depend on `utoipa_axum`.
+///
+/// ```ignore
+/// OpenApiRouter::new()
+///     .routes(slac::secure!(IsHackathonAdmin; list_teams, teams::delete_team))
+/// ```
+///
+/// A handler that checks a different policy, or none, is rejected:
+///
+/// ```compile_fail
+/// # use slac::openapi::{DescribePolicy, Security};
+/// # use slac::Policy;
+/// # use axum::http::{StatusCode, request::Parts};
+/// struct IsAdmin;
+/// impl Policy<()> for IsAdmin {
+///     type Output = ();
+///     type Error = StatusCode;
+///     async fn check(_: &mut Parts, _: &()) -> Result<(), StatusCode> {
+///         Ok(())
+///     }
+/// }
+/// impl DescribePolicy for IsAdmin {
+///     fn security() -> Security {
+///         Security::scheme("oidc")
+///     }
+/// }
+///
+/// #[utoipa::path(delete, path = "/teams/{id}")]
+/// async fn delete_team() {}
+///
+/// let _: utoipa_axum::router::UtoipaMethodRouter =
+///     slac::secure!(IsAdmin; delete_team);
+/// ```
+#[macro_export]
+macro_rules! secure {
+    ($policy:ty; $($handler:ident $(:: $segment:ident)*),+ $(,)?) => {{
+        $($crate::openapi::extracts::<$policy, _, _>(&$handler $(:: $segment)*);)+
+        $crate::openapi::secure::<$policy, _>(::utoipa_axum::routes!(
+            $($handler $(:: $segment)*),+
+        ))
+    }};
+}
+
+impl<A: DescribePolicy, B: DescribePolicy, E> DescribePolicy for Any<A, B, E> {
+    fn security() -> Security {
+        A::security().or(B::security())
+    }
+}
+
+impl<A: DescribePolicy, B: DescribePolicy, E> DescribePolicy for All<A, B, E> {
+    fn security() -> Security {
+        A::security().and(B::security())
+    }
+}
+
+/// Denials become `None`; `P`'s other rejections still apply.
+impl<P: DescribePolicy> DescribePolicy for Optional<P> {
+    fn security() -> Security {
+        let mut security = P::security().optional();
+        security
+            .rejections
+            .retain(|status| !matches!(status, 401 | 403));
+        security
+    }
+}
+
+/// Documents `P`; like [`Not`], the rejection for a false condition has no
+/// status to document until `E` is turned into a response.
+impl<C, P: DescribePolicy, E> DescribePolicy for When<C, P, E> {
+    fn security() -> Security {
+        P::security()
+    }
+}
+
+/// A negated check has no OpenAPI security form; implement
+/// [`DescribePolicy`] on a wrapper if its rejection should be documented.
+impl<P, E> DescribePolicy for Not<P, E> {
+    fn security() -> Security {
+        Security::none()
+    }
+}
+
+impl<P: DescribePolicy> DescribePolicy for Cached<P> {
+    fn security() -> Security {
+        P::security()
+    }
+}
+
+impl<P: DescribePolicy> DescribePolicy for Layered<P> {
+    fn security() -> Security {
+        P::security()
+    }
+}
+
+macro_rules! describe_tuples {
+    ($first:ident $(, $rest:ident)+) => {
+        impl<Agg, $first: DescribePolicy, $($rest: DescribePolicy),+> DescribePolicy
+            for AnyOf<($first, $($rest,)+), Agg>
+        {
+            fn security() -> Security {
+                $first::security()$(.or($rest::security()))+
+            }
+        }
+
+        impl<Agg, $first: DescribePolicy, $($rest: DescribePolicy),+> DescribePolicy
+            for AllOf<($first, $($rest,)+), Agg>
+        {
+            fn security() -> Security {
+                $first::security()$(.and($rest::security()))+
+            }
+        }
+    };
+}
+
+describe_tuples!(A, B);
+describe_tuples!(A, B, C);
+describe_tuples!(A, B, C, D);
+describe_tuples!(A, B, C, D, E);
+describe_tuples!(A, B, C, D, E, F);
+describe_tuples!(A, B, C, D, E, F, G);
+describe_tuples!(A, B, C, D, E, F, G, H);
+describe_tuples!(A, B, C, D, E, F, G, H, I);
+describe_tuples!(A, B, C, D, E, F, G, H, I, J);
+describe_tuples!(A, B, C, D, E, F, G, H, I, J, K);
+describe_tuples!(A, B, C, D, E, F, G, H, I, J, K, L);
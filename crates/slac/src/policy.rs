@@ -39,12 +39,12 @@ where
 impl<P, S> FromRequestParts<S> for Authorized<P, S>
 where
     P: Policy<S>,
-    S: Send + Sync,
+    S: Send + Sync + 'static,
 {
     type Rejection = P::Error;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let data = P::check(parts, state).await?;
+        let data = crate::audit::checked::<P, S>(parts, state).await?;
         Ok(Self {
             data,
             _proof: PhantomData,
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use axum::extract::{FromRequestParts, Path};
+use axum::http::StatusCode;
+use axum::http::request::Parts;
+use axum::response::{IntoResponse, Response};
+
+/// A [`scoped_policy!`](crate::scoped_policy) could not read its path
+/// segment, usually because the route does not declare it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathParamError {
+    pub name: &'static str,
+    pub reason: String,
+}
+
+impl fmt::Display for PathParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "path parameter `{}`: {}", self.name, self.reason)
+    }
+}
+
+impl std::error::Error for PathParamError {}
+
+impl IntoResponse for PathParamError {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+    }
+}
+
+impl From<PathParamError> for StatusCode {
+    fn from(_: PathParamError) -> Self {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Reads the `name` segment of the matched route.
+#[doc(hidden)]
+pub async fn path_param(parts: &mut Parts, name: &'static str) -> Result<String, PathParamError> {
+    let Path(mut params) = Path::<HashMap<String, String>>::from_request_parts(parts, &())
+        .await
+        .map_err(|e| PathParamError {
+            name,
+            reason: e.body_text(),
+        })?;
+    params.remove(name).ok_or_else(|| PathParamError {
+        name,
+        reason: "not in the matched route".into(),
+    })
+}
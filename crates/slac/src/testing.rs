@@ -0,0 +1,190 @@
+//! Helpers for asserting which callers a router lets through.
+//!
+//! Behind the `testing` feature. Enable it from `[dev-dependencies]` only:
+//! a [`stub`] decides a policy's outcome for any request it is attached to.
+//!
+//! ```ignore
+//! Matrix::new()
+//!     .principal("admin", |req| stub::<IsAdmin, AppState, _>(req, || Ok(admin())))
+//!     .principal("anon", |_| {})
+//!     .expect(Method::DELETE, "/teams/t1", ["admin"])
+//!     .expect(Method::GET, "/teams/t1", ["admin", "anon"])
+//!     .assert(app(state))
+//!     .await;
+//! ```
+
+use core::fmt::Write as _;
+use core::marker::PhantomData;
+use std::sync::Arc;
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::request::Parts;
+use axum::http::{Method, Request, StatusCode};
+use tower::ServiceExt;
+
+use crate::Policy;
+
+type Outcome<P, S> = Result<<P as Policy<S>>::Output, <P as Policy<S>>::Error>;
+
+/// The extensions entry a [`stub`] adds.
+pub(crate) struct Stub<P: Policy<S>, S>(
+    Arc<dyn Fn() -> Outcome<P, S> + Send + Sync>,
+    PhantomData<fn() -> S>,
+);
+
+impl<P: Policy<S>, S> Clone for Stub<P, S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+
+/// Makes every check of `P` on `request` return `outcome()` instead of
+/// running `P`. Only checks through [`Authorized`](crate::Authorized) and
+/// [`PolicyLayer`](crate::PolicyLayer) see the stub; a combinator or
+/// `policy!` enum checks its parts directly, so stub the composite itself.
+pub fn stub<P, S, B>(
+    request: &mut Request<B>,
+    outcome: impl Fn() -> Outcome<P, S> + Send + Sync + 'static,
+) where
+    P: Policy<S>,
+    S: 'static,
+{
+    request
+        .extensions_mut()
+        .insert(Stub::<P, S>(Arc::new(outcome), PhantomData));
+}
+
+pub(crate) fn stubbed<P: Policy<S>, S: 'static>(parts: &Parts) -> Option<Outcome<P, S>> {
+    parts.extensions.get::<Stub<P, S>>().map(|Stub(f, _)| f())
+}
+
+type Prepare = Box<dyn Fn(&mut Request<Body>) + Send + Sync>;
+
+struct Expectation {
+    method: Method,
+    uri: String,
+    allowed: Vec<&'static str>,
+}
+
+/// An allow/deny table of principals against routes.
+///
+/// Each principal is a name and a function that dresses a request up as
+/// that caller, by inserting an identity into its extensions or stubbing
+/// policies. [`assert`](Self::assert) sends every route as every principal
+/// and panics with the cells that differ from the expectation. A caller is
+/// allowed when the route answers with a success or redirect and denied when
+/// it answers with a denial status; anything else, such as a `500`, is a
+/// mismatch whatever was expected.
+pub struct Matrix {
+    principals: Vec<(&'static str, Prepare)>,
+    expectations: Vec<Expectation>,
+    denied: fn(StatusCode) -> bool,
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Self {
+            principals: Vec::new(),
+            expectations: Vec::new(),
+            denied: |status| status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+impl Matrix {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn principal(
+        mut self,
+        name: &'static str,
+        prepare: impl Fn(&mut Request<Body>) + Send + Sync + 'static,
+    ) -> Self {
+        self.principals.push((name, Box::new(prepare)));
+        self
+    }
+
+    /// Expects `method uri` to let exactly the principals in `allowed`
+    /// through.
+    pub fn expect(
+        mut self,
+        method: Method,
+        uri: impl Into<String>,
+        allowed: impl IntoIterator<Item = &'static str>,
+    ) -> Self {
+        self.expectations.push(Expectation {
+            method,
+            uri: uri.into(),
+            allowed: allowed.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Which statuses count as a denial. Defaults to 401 and 403; policies
+    /// that hide resources from outsiders may want 404 included.
+    pub fn denied_when(mut self, denied: fn(StatusCode) -> bool) -> Self {
+        self.denied = denied;
+        self
+    }
+
+    /// Sends every expectation as every principal through `router`.
+    ///
+    /// # Panics
+    ///
+    /// If any cell differs from the expectation, or an expectation names a
+    /// principal that was never declared.
+    pub async fn assert(self, router: Router) {
+        let width = self.principals.iter().map(|(n, _)| n.len()).max();
+        let mut report = String::new();
+        let mut mismatches = 0;
+
+        for e in &self.expectations {
+            for name in &e.allowed {
+                assert!(
+                    self.principals.iter().any(|(n, _)| n == name),
+                    "{} {} allows undeclared principal `{name}`",
+                    e.method,
+                    e.uri,
+                );
+            }
+
+            let mut lines = String::new();
+            for (name, prepare) in &self.principals {
+                let mut request = Request::builder()
+                    .method(e.method.clone())
+                    .uri(&e.uri)
+                    .body(Body::empty())
+                    .unwrap();
+                prepare(&mut request);
+                let status = router.clone().oneshot(request).await.unwrap().status();
+
+                let expected = e.allowed.contains(name);
+                let matched = if expected {
+                    status.is_success() || status.is_redirection()
+                } else {
+                    (self.denied)(status)
+                };
+                if !matched {
+                    mismatches += 1;
+                    let verdict = if expected { "allow" } else { "deny" };
+                    let _ = writeln!(
+                        lines,
+                        "    {name:<w$}  expected {verdict}, got {status}",
+                        w = width.unwrap_or_default(),
+                    );
+                }
+            }
+            if !lines.is_empty() {
+                let _ = write!(report, "  {} {}\n{lines}", e.method, e.uri);
+            }
+        }
+
+        let cells = self.principals.len() * self.expectations.len();
+        assert!(
+            mismatches == 0,
+            "authorization matrix mismatch ({mismatches} of {cells} cells):\n{report}"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use core::marker::PhantomData;
+
+use axum::http::request::Parts;
+
+use crate::Policy;
+use crate::error::{CombineErrors, SameError};
+use axum::response::IntoResponse;
+
+/// Try each policy in the tuple in order and take the first that passes.
+/// By default all members share the first member's `Error` type and, if
+/// every member rejects, the last rejection is returned; give an `E:`
+/// [`CombineErrors`] to aggregate every rejection instead.
+///
+/// The n-ary form of [`Any`](crate::Any): `AnyOf<(A, B, C)>` yields a flat
+/// [`Either3`] instead of nested [`Either`](crate::Either)s. Implemented for
+/// tuples of 2 to 12 policies.
+pub struct AnyOf<T, E = SameError>(PhantomData<fn() -> (T, E)>);
+
+/// Require every policy in the tuple to pass, in order, stopping at the first
+/// rejection. The proof is the flat tuple of their outputs. As with
+/// [`AnyOf`], an `E:` [`CombineErrors`] lets members reject with different
+/// types.
+///
+/// The n-ary form of [`All`](crate::All). Implemented for tuples of 2 to 12
+/// policies.
+pub struct AllOf<T, E = SameError>(PhantomData<fn() -> (T, E)>);
+
+macro_rules! tuple_policies {
+    ($(#[$doc:meta])* $either:ident; $first:ident $(, $rest:ident)+) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $either<$first, $($rest),+> {
+            $first($first),
+            $($rest($rest),)+
+        }
+
+        impl<S, $first, $($rest),+> Policy<S> for AnyOf<($first, $($rest,)+), SameError>
+        where
+            S: Send + Sync,
+            $first: Policy<S>,
+            $($rest: Policy<S, Error = $first::Error>,)+
+        {
+            type Output = $either<$first::Output, $($rest::Output),+>;
+            type Error = $first::Error;
+
+            async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+                let err = match $first::check(parts, state).await {
+                    Ok(o) => return Ok($either::$first(o)),
+                    Err(e) => e,
+                };
+                $(
+                    let err = match $rest::check(parts, state).await {
+                        Ok(o) => return Ok($either::$rest(o)),
+                        Err(e) => {
+                            drop(err);
+                            e
+                        }
+                    };
+                )+
+                Err(err)
+            }
+        }
+
+        impl<S, Agg, $first, $($rest),+> Policy<S> for AnyOf<($first, $($rest,)+), Agg>
+        where
+            S: Send + Sync,
+            $first: Policy<S>,
+            $($rest: Policy<S>,)+
+            Agg: CombineErrors + From<$first::Error> $(+ From<$rest::Error>)+,
+            Agg: IntoResponse + Send + 'static,
+        {
+            type Output = $either<$first::Output, $($rest::Output),+>;
+            type Error = Agg;
+
+            async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+                let mut errors = Vec::new();
+                match $first::check(parts, state).await {
+                    Ok(o) => return Ok($either::$first(o)),
+                    Err(e) => errors.push(Agg::from(e)),
+                }
+                $(
+                    match $rest::check(parts, state).await {
+                        Ok(o) => return Ok($either::$rest(o)),
+                        Err(e) => errors.push(Agg::from(e)),
+                    }
+                )+
+                Err(Agg::combine(errors))
+            }
+        }
+
+        impl<S, $first, $($rest),+> Policy<S> for AllOf<($first, $($rest,)+), SameError>
+        where
+            S: Send + Sync,
+            $first: Policy<S>,
+            $($rest: Policy<S, Error = $first::Error>,)+
+        {
+            type Output = ($first::Output, $($rest::Output,)+);
+            type Error = $first::Error;
+
+            async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+                Ok((
+                    $first::check(parts, state).await?,
+                    $($rest::check(parts, state).await?,)+
+                ))
+            }
+        }
+
+        impl<S, Agg, $first, $($rest),+> Policy<S> for AllOf<($first, $($rest,)+), Agg>
+        where
+            S: Send + Sync,
+            $first: Policy<S>,
+            $($rest: Policy<S>,)+
+            Agg: CombineErrors + From<$first::Error> $(+ From<$rest::Error>)+,
+            Agg: IntoResponse + Send + 'static,
+        {
+            type Output = ($first::Output, $($rest::Output,)+);
+            type Error = Agg;
+
+            async fn check(parts: &mut Parts, state: &S) -> Result<Self::Output, Self::Error> {
+                Ok((
+                    $first::check(parts, state).await.map_err(Agg::from)?,
+                    $($rest::check(parts, state).await.map_err(Agg::from)?,)+
+                ))
+            }
+        }
+    };
+}
+
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over two policies.
+    Either2; A, B
+);
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over three policies.
+    Either3; A, B, C
+);
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over four policies.
+    Either4; A, B, C, D
+);
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over five policies.
+    Either5; A, B, C, D, E
+);
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over six policies.
+    Either6; A, B, C, D, E, F
+);
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over seven policies.
+    Either7; A, B, C, D, E, F, G
+);
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over eight policies.
+    Either8; A, B, C, D, E, F, G, H
+);
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over nine policies.
+    Either9; A, B, C, D, E, F, G, H, I
+);
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over ten policies.
+    Either10; A, B, C, D, E, F, G, H, I, J
+);
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over eleven policies.
+    Either11; A, B, C, D, E, F, G, H, I, J, K
+);
+tuple_policies!(
+    /// Result of an [`AnyOf`] check over twelve policies.
+    Either12; A, B, C, D, E, F, G, H, I, J, K, L
+);
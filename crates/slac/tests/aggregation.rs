@@ -0,0 +1,204 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum::http::request::Parts;
+use axum::response::{IntoResponse, Response};
+use common::*;
+use slac::{All, Any, AnyOf, Authorized, CombineErrors, Policy, policy};
+
+/// Rejects with 401, like an authentication check would for an anonymous
+/// caller.
+struct IsSignedIn;
+impl Policy<AppState> for IsSignedIn {
+    type Output = ();
+    type Error = StatusCode;
+
+    async fn check(_parts: &mut Parts, state: &AppState) -> Result<Self::Output, Self::Error> {
+        if state.inner.is_member || state.inner.is_admin {
+            Ok(())
+        } else {
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+/// A policy with its own rejection type.
+struct HasInvite;
+struct NoInvite;
+
+impl Policy<AppState> for HasInvite {
+    type Output = ();
+    type Error = NoInvite;
+
+    async fn check(_parts: &mut Parts, state: &AppState) -> Result<Self::Output, Self::Error> {
+        if state.inner.is_owner {
+            Ok(())
+        } else {
+            Err(NoInvite)
+        }
+    }
+}
+
+impl IntoResponse for NoInvite {
+    fn into_response(self) -> Response {
+        (StatusCode::FORBIDDEN, "no invite").into_response()
+    }
+}
+
+/// Reports every reason access was denied.
+#[derive(Debug)]
+struct Denied(Vec<String>);
+
+impl From<StatusCode> for Denied {
+    fn from(s: StatusCode) -> Self {
+        Self(vec![s.as_u16().to_string()])
+    }
+}
+
+impl From<NoInvite> for Denied {
+    fn from(_: NoInvite) -> Self {
+        Self(vec!["no invite".into()])
+    }
+}
+
+impl CombineErrors for Denied {
+    fn combine(errors: Vec<Self>) -> Self {
+        Self(errors.into_iter().flat_map(|e| e.0).collect())
+    }
+}
+
+impl IntoResponse for Denied {
+    fn into_response(self) -> Response {
+        (StatusCode::FORBIDDEN, self.0.join(",")).into_response()
+    }
+}
+
+policy! {
+    enum LastWins for AppState {
+        Admin = IsAdmin,
+        SignedIn = IsSignedIn,
+    }
+}
+
+policy! {
+    enum MostSpecific for AppState, error = StatusCode {
+        Admin = IsAdmin,
+        SignedIn = IsSignedIn,
+    }
+}
+
+policy! {
+    enum Mixed for AppState, error = Denied {
+        Admin = IsAdmin,
+        Invited = HasInvite,
+    }
+}
+
+#[tokio::test]
+async fn policy_macro_default_returns_last_error() {
+    async fn handler(_: Authorized<LastWins, AppState>) -> &'static str {
+        "ok"
+    }
+
+    let (status, _) = send(route(AppState::anon(), handler)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn policy_macro_prefers_forbidden_over_unauthorized() {
+    async fn handler(_: Authorized<MostSpecific, AppState>) -> &'static str {
+        "ok"
+    }
+
+    let state = AppState::anon();
+    let (status, _) = send(route(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(state.calls(), (1, 0, 0));
+}
+
+#[tokio::test]
+async fn policy_macro_still_takes_first_match() {
+    async fn handler(Authorized { data, .. }: Authorized<MostSpecific, AppState>) -> String {
+        match data {
+            MostSpecific::Admin(p) => p.label.to_string(),
+            MostSpecific::SignedIn(()) => "signed in".to_string(),
+        }
+    }
+
+    let (status, body) = send(route(AppState::member(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "signed in");
+}
+
+#[tokio::test]
+async fn policy_macro_accepts_heterogeneous_errors() {
+    async fn handler(Authorized { data, .. }: Authorized<Mixed, AppState>) -> &'static str {
+        match data {
+            Mixed::Admin(_) => "admin",
+            Mixed::Invited(()) => "invited",
+        }
+    }
+
+    let (status, body) = send(route(AppState::owner(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "invited");
+
+    let (status, body) = send(route(AppState::anon(), handler)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body, "403,no invite");
+}
+
+#[tokio::test]
+async fn any_aggregates_both_rejections() {
+    async fn handler(
+        _: Authorized<Any<IsAdmin, IsSignedIn, StatusCode>, AppState>,
+    ) -> &'static str {
+        "ok"
+    }
+
+    let (status, _) = send(route(AppState::anon(), handler)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn any_of_collects_every_rejection() {
+    async fn handler(
+        _: Authorized<AnyOf<(IsAdmin, IsOwner, HasInvite), Denied>, AppState>,
+    ) -> &'static str {
+        "ok"
+    }
+
+    let (status, body) = send(route(AppState::anon(), handler)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body, "403,404,no invite");
+}
+
+#[tokio::test]
+async fn all_converts_heterogeneous_errors() {
+    async fn handler(_: Authorized<All<IsMember, HasInvite, Denied>, AppState>) -> &'static str {
+        "ok"
+    }
+
+    let (status, body) = send(route(AppState::member(), handler)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body, "no invite");
+}
+
+#[test]
+fn most_specific_prefers_server_errors_then_earliest() {
+    use slac::most_specific;
+
+    let pick = |codes: &[StatusCode]| most_specific(codes.to_vec());
+    assert_eq!(
+        pick(&[StatusCode::UNAUTHORIZED, StatusCode::NOT_FOUND]),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        pick(&[StatusCode::FORBIDDEN, StatusCode::NOT_FOUND]),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        pick(&[StatusCode::FORBIDDEN, StatusCode::INTERNAL_SERVER_ERROR]),
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+}
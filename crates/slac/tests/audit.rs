@@ -0,0 +1,80 @@
+mod common;
+
+use std::any::type_name;
+use std::sync::{Arc, Mutex};
+
+use axum::Router;
+use axum::http::StatusCode;
+use axum::routing::get;
+use common::*;
+use slac::{Authorized, Decision, ObserverLayer, PolicyLayer};
+
+type Log = Arc<Mutex<Vec<(&'static str, Option<String>, bool)>>>;
+
+fn observed(router: Router<AppState>, state: AppState) -> (Router, Log) {
+    let log = Log::default();
+    let sink = log.clone();
+    let router = router
+        .layer(ObserverLayer::new(move |d: &Decision<'_>| {
+            sink.lock()
+                .unwrap()
+                .push((d.policy, d.route.map(str::to_owned), d.allowed));
+        }))
+        .with_state(state);
+    (router, log)
+}
+
+#[tokio::test]
+async fn extractor_decisions_are_observed() {
+    async fn handler(
+        _: Authorized<IsMember, AppState>,
+        _: Authorized<DashboardAccess, AppState>,
+    ) -> &'static str {
+        "ok"
+    }
+
+    let (router, log) = observed(Router::new().route("/", get(handler)), AppState::member());
+    let (status, _) = send(router).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            (type_name::<IsMember>(), Some("/".into()), true),
+            (type_name::<DashboardAccess>(), Some("/".into()), true),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn denials_are_observed() {
+    async fn handler(_: Authorized<IsAdmin, AppState>) -> &'static str {
+        "ok"
+    }
+
+    let (router, log) = observed(Router::new().route("/", get(handler)), AppState::member());
+    let (status, _) = send(router).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        *log.lock().unwrap(),
+        [(type_name::<IsAdmin>(), Some("/".into()), false)]
+    );
+}
+
+#[tokio::test]
+async fn layer_decisions_are_observed() {
+    async fn handler() -> &'static str {
+        "ok"
+    }
+
+    let state = AppState::admin();
+    let router = Router::new()
+        .route("/", get(handler))
+        .route_layer(PolicyLayer::<IsAdmin, _>::new(state.clone()));
+    let (router, log) = observed(router, state);
+    let (status, _) = send(router).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        *log.lock().unwrap(),
+        [(type_name::<IsAdmin>(), Some("/".into()), true)]
+    );
+}
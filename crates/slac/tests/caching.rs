@@ -0,0 +1,89 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::*;
+use slac::{Any, Authorized, Cached, Optional, policy};
+
+policy! {
+    enum CachedDashboard for AppState {
+        Admin  = Cached<IsAdmin>,
+        Member = Cached<IsMember>,
+    }
+}
+
+#[tokio::test]
+async fn uncached_policies_run_once_per_use() {
+    async fn handler(
+        _: Authorized<IsAdmin, AppState>,
+        _: Authorized<DashboardAccess, AppState>,
+    ) -> &'static str {
+        "ok"
+    }
+
+    let state = AppState::admin();
+    let (status, _) = send(route(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(state.calls(), (2, 0, 0));
+}
+
+#[tokio::test]
+async fn cached_success_is_shared_across_extractors() {
+    async fn handler(
+        Authorized { data, .. }: Authorized<Cached<IsAdmin>, AppState>,
+        Authorized { data: access, .. }: Authorized<CachedDashboard, AppState>,
+    ) -> String {
+        match access {
+            CachedDashboard::Admin(p) => format!("{}={}", data.label, p.label),
+            CachedDashboard::Member(_) => "member".into(),
+        }
+    }
+
+    let state = AppState::admin();
+    let (status, body) = send(route(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "admin=admin");
+    assert_eq!(state.calls(), (1, 0, 0));
+}
+
+#[tokio::test]
+async fn cached_rejection_is_shared_across_extractors() {
+    async fn handler(
+        Authorized { data, .. }: Authorized<Optional<Cached<IsAdmin>>, AppState>,
+        _: Authorized<Any<Cached<IsAdmin>, Cached<IsMember>>, AppState>,
+    ) -> &'static str {
+        if data.is_some() { "admin" } else { "member" }
+    }
+
+    let state = AppState::member();
+    let (status, body) = send(route(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "member");
+    assert_eq!(state.calls(), (1, 1, 0));
+}
+
+#[tokio::test]
+async fn cached_rejection_still_rejects() {
+    async fn handler(
+        _: Authorized<Optional<Cached<IsAdmin>>, AppState>,
+        _: Authorized<Cached<IsAdmin>, AppState>,
+    ) -> &'static str {
+        "ok"
+    }
+
+    let state = AppState::anon();
+    let (status, _) = send(route(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(state.calls(), (1, 0, 0));
+}
+
+#[tokio::test]
+async fn cache_does_not_outlive_the_request() {
+    async fn handler(_: Authorized<Cached<IsAdmin>, AppState>) -> &'static str {
+        "ok"
+    }
+
+    let state = AppState::admin();
+    send(route(state.clone(), handler)).await;
+    send(route(state.clone(), handler)).await;
+    assert_eq!(state.calls(), (2, 0, 0));
+}
@@ -0,0 +1,180 @@
+//! Shared state, policies and request helpers for the integration tests.
+
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode, request::Parts};
+use axum::routing::get;
+use slac::{Policy, policy};
+use tower::ServiceExt;
+
+#[derive(Clone, Default)]
+pub struct AppState {
+    pub inner: Arc<AppStateInner>,
+}
+
+#[derive(Default)]
+pub struct AppStateInner {
+    pub is_admin: bool,
+    pub is_member: bool,
+    pub is_owner: bool,
+    pub admin_calls: AtomicUsize,
+    pub member_calls: AtomicUsize,
+    pub owner_calls: AtomicUsize,
+}
+
+impl AppState {
+    pub fn admin() -> Self {
+        Self {
+            inner: Arc::new(AppStateInner {
+                is_admin: true,
+                is_member: true,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn member() -> Self {
+        Self {
+            inner: Arc::new(AppStateInner {
+                is_member: true,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Owns the resource without being a member or admin.
+    pub fn owner() -> Self {
+        Self {
+            inner: Arc::new(AppStateInner {
+                is_owner: true,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Holds every role.
+    pub fn everything() -> Self {
+        Self {
+            inner: Arc::new(AppStateInner {
+                is_admin: true,
+                is_member: true,
+                is_owner: true,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn anon() -> Self {
+        Self::default()
+    }
+
+    pub fn calls(&self) -> (usize, usize, usize) {
+        (
+            self.inner.admin_calls.load(Ordering::Relaxed),
+            self.inner.member_calls.load(Ordering::Relaxed),
+            self.inner.owner_calls.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminProof {
+    pub label: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberProof {
+    pub label: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnerProof {
+    pub label: &'static str,
+}
+
+pub struct IsAdmin;
+impl Policy<AppState> for IsAdmin {
+    type Output = AdminProof;
+    type Error = StatusCode;
+
+    async fn check(_parts: &mut Parts, state: &AppState) -> Result<Self::Output, Self::Error> {
+        state.inner.admin_calls.fetch_add(1, Ordering::Relaxed);
+        if state.inner.is_admin {
+            Ok(AdminProof { label: "admin" })
+        } else {
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
+
+pub struct IsMember;
+impl Policy<AppState> for IsMember {
+    type Output = MemberProof;
+    type Error = StatusCode;
+
+    async fn check(_parts: &mut Parts, state: &AppState) -> Result<Self::Output, Self::Error> {
+        state.inner.member_calls.fetch_add(1, Ordering::Relaxed);
+        if state.inner.is_member {
+            Ok(MemberProof { label: "member" })
+        } else {
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
+
+/// Rejects with 404 rather than 403, so tests can tell which branch's error
+/// was returned.
+pub struct IsOwner;
+impl Policy<AppState> for IsOwner {
+    type Output = OwnerProof;
+    type Error = StatusCode;
+
+    async fn check(_parts: &mut Parts, state: &AppState) -> Result<Self::Output, Self::Error> {
+        state.inner.owner_calls.fetch_add(1, Ordering::Relaxed);
+        if state.inner.is_owner {
+            Ok(OwnerProof { label: "owner" })
+        } else {
+            Err(StatusCode::NOT_FOUND)
+        }
+    }
+}
+
+policy! {
+    pub enum DashboardAccess for AppState {
+        Admin  = IsAdmin,
+        Member = IsMember,
+    }
+}
+
+policy! {
+    #[derive(Debug)]
+    pub enum DebuggableAccess for AppState {
+        Admin  = IsAdmin,
+        Member = IsMember,
+    }
+}
+
+pub async fn send(router: Router) -> (StatusCode, String) {
+    let response = router
+        .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+pub fn route<H, T>(state: AppState, handler: H) -> Router
+where
+    H: axum::handler::Handler<T, AppState>,
+    T: 'static,
+{
+    Router::new().route("/", get(handler)).with_state(state)
+}
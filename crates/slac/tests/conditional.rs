@@ -0,0 +1,198 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum::http::request::Parts;
+use axum::response::{IntoResponse, Response};
+use common::*;
+use slac::{Authorized, Condition, Not, Optional, Policy, When};
+
+#[derive(Default)]
+enum JoinError {
+    #[default]
+    AlreadyMember,
+    Other(StatusCode),
+}
+
+impl From<StatusCode> for JoinError {
+    fn from(status: StatusCode) -> Self {
+        JoinError::Other(status)
+    }
+}
+
+impl IntoResponse for JoinError {
+    fn into_response(self) -> Response {
+        match self {
+            JoinError::AlreadyMember => (StatusCode::CONFLICT, "already a member").into_response(),
+            JoinError::Other(status) => status.into_response(),
+        }
+    }
+}
+
+/// Fails as if the membership lookup could not reach the database.
+struct MembershipUnavailable;
+
+impl Policy<AppState> for MembershipUnavailable {
+    type Output = ();
+    type Error = StatusCode;
+
+    async fn check(_parts: &mut Parts, _state: &AppState) -> Result<(), StatusCode> {
+        Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// Rejection for [`When`] tests: the route is hidden while its flag is off.
+#[derive(Default)]
+enum FlagError {
+    #[default]
+    Disabled,
+    Other(StatusCode),
+}
+
+impl From<StatusCode> for FlagError {
+    fn from(status: StatusCode) -> Self {
+        FlagError::Other(status)
+    }
+}
+
+impl IntoResponse for FlagError {
+    fn into_response(self) -> Response {
+        match self {
+            FlagError::Disabled => (StatusCode::NOT_FOUND, "disabled").into_response(),
+            FlagError::Other(status) => status.into_response(),
+        }
+    }
+}
+
+/// Stands in for a feature flag: on whenever the caller owns the resource.
+struct OwnerFlag;
+
+impl Condition<AppState> for OwnerFlag {
+    async fn holds(_parts: &mut Parts, state: &AppState) -> bool {
+        state.inner.is_owner
+    }
+}
+
+#[tokio::test]
+async fn not_passes_when_inner_rejects() {
+    async fn handler(_: Authorized<Not<IsMember, JoinError>, AppState>) -> &'static str {
+        "join"
+    }
+
+    let (status, body) = send(route(AppState::anon(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "join");
+}
+
+#[tokio::test]
+async fn not_rejects_with_supplied_error_when_inner_passes() {
+    async fn handler(_: Authorized<Not<IsMember, JoinError>, AppState>) -> &'static str {
+        "join"
+    }
+
+    let (status, body) = send(route(AppState::member(), handler)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body, "already a member");
+}
+
+#[tokio::test]
+async fn not_propagates_inner_server_errors() {
+    async fn handler(
+        _: Authorized<Not<MembershipUnavailable, JoinError>, AppState>,
+    ) -> &'static str {
+        "join"
+    }
+
+    let (status, _) = send(route(AppState::anon(), handler)).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn not_propagates_inner_errors_other_than_denials() {
+    async fn handler(_: Authorized<Not<IsOwner, JoinError>, AppState>) -> &'static str {
+        "join"
+    }
+
+    // IsOwner rejects with 404, which is not a refusal of the caller.
+    let (status, _) = send(route(AppState::anon(), handler)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+async fn gated(
+    Authorized { data, .. }: Authorized<When<OwnerFlag, IsAdmin, FlagError>, AppState>,
+) -> impl IntoResponse {
+    format!("enforced:{}", data.label)
+}
+
+#[tokio::test]
+async fn when_rejects_without_running_inner_if_condition_false() {
+    let state = AppState::anon();
+    let (status, body) = send(route(state.clone(), gated)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body, "disabled");
+    assert_eq!(state.calls(), (0, 0, 0));
+
+    // Even a caller the inner policy would admit is turned away.
+    let (status, _) = send(route(AppState::admin(), gated)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn when_enforces_inner_if_condition_true() {
+    let state = AppState::owner();
+    let (status, _) = send(route(state.clone(), gated)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(state.calls(), (1, 0, 0));
+
+    let (status, body) = send(route(AppState::everything(), gated)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "enforced:admin");
+}
+
+async fn maybe_member(
+    Authorized { data, .. }: Authorized<Optional<IsMember>, AppState>,
+) -> impl IntoResponse {
+    match data {
+        Some(p) => format!("hello {}", p.label),
+        None => "hello stranger".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn optional_yields_some_on_pass() {
+    let (status, body) = send(route(AppState::member(), maybe_member)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "hello member");
+}
+
+#[tokio::test]
+async fn optional_yields_none_instead_of_rejecting() {
+    let (status, body) = send(route(AppState::anon(), maybe_member)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "hello stranger");
+}
+
+#[tokio::test]
+async fn optional_propagates_inner_server_errors() {
+    async fn handler(
+        Authorized { data, .. }: Authorized<Optional<MembershipUnavailable>, AppState>,
+    ) -> &'static str {
+        match data {
+            Some(()) => "member view",
+            None => "anonymous view",
+        }
+    }
+
+    let (status, _) = send(route(AppState::anon(), handler)).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn optional_propagates_inner_errors_other_than_denials() {
+    async fn handler(_: Authorized<Optional<IsOwner>, AppState>) -> &'static str {
+        "view"
+    }
+
+    // IsOwner rejects with 404, which is not a refusal of the caller.
+    let (status, _) = send(route(AppState::anon(), handler)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
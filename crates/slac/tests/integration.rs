@@ -1,127 +1,12 @@
+mod common;
+
 use std::convert::Infallible;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::Ordering;
 
-use axum::Router;
-use axum::body::Body;
-use axum::http::{Request, StatusCode, request::Parts};
+use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::routing::get;
-use slac::{All, Any, Authorized, Either, Policy, policy};
-use tower::ServiceExt;
-
-#[derive(Clone, Default)]
-struct AppState {
-    inner: Arc<AppStateInner>,
-}
-
-#[derive(Default)]
-struct AppStateInner {
-    is_admin: bool,
-    is_member: bool,
-    admin_calls: AtomicUsize,
-    member_calls: AtomicUsize,
-}
-
-impl AppState {
-    fn admin() -> Self {
-        Self {
-            inner: Arc::new(AppStateInner {
-                is_admin: true,
-                is_member: true,
-                ..Default::default()
-            }),
-        }
-    }
-
-    fn member() -> Self {
-        Self {
-            inner: Arc::new(AppStateInner {
-                is_member: true,
-                ..Default::default()
-            }),
-        }
-    }
-
-    fn anon() -> Self {
-        Self::default()
-    }
-}
-
-#[derive(Debug, PartialEq, Eq)]
-struct AdminProof {
-    label: &'static str,
-}
-
-#[derive(Debug, PartialEq, Eq)]
-struct MemberProof {
-    label: &'static str,
-}
-
-struct IsAdmin;
-impl Policy<AppState> for IsAdmin {
-    type Output = AdminProof;
-    type Error = StatusCode;
-
-    async fn check(_parts: &mut Parts, state: &AppState) -> Result<Self::Output, Self::Error> {
-        state.inner.admin_calls.fetch_add(1, Ordering::Relaxed);
-        if state.inner.is_admin {
-            Ok(AdminProof { label: "admin" })
-        } else {
-            Err(StatusCode::FORBIDDEN)
-        }
-    }
-}
-
-struct IsMember;
-impl Policy<AppState> for IsMember {
-    type Output = MemberProof;
-    type Error = StatusCode;
-
-    async fn check(_parts: &mut Parts, state: &AppState) -> Result<Self::Output, Self::Error> {
-        state.inner.member_calls.fetch_add(1, Ordering::Relaxed);
-        if state.inner.is_member {
-            Ok(MemberProof { label: "member" })
-        } else {
-            Err(StatusCode::FORBIDDEN)
-        }
-    }
-}
-
-policy! {
-    pub enum DashboardAccess for AppState {
-        Admin  = IsAdmin,
-        Member = IsMember,
-    }
-}
-
-policy! {
-    #[derive(Debug)]
-    pub enum DebuggableAccess for AppState {
-        Admin  = IsAdmin,
-        Member = IsMember,
-    }
-}
-
-async fn send(router: Router) -> (StatusCode, String) {
-    let response = router
-        .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
-        .await
-        .unwrap();
-    let status = response.status();
-    let bytes = axum::body::to_bytes(response.into_body(), 1024)
-        .await
-        .unwrap();
-    (status, String::from_utf8(bytes.to_vec()).unwrap())
-}
-
-fn route<H, T>(state: AppState, handler: H) -> Router
-where
-    H: axum::handler::Handler<T, AppState>,
-    T: 'static,
-{
-    Router::new().route("/", get(handler)).with_state(state)
-}
+use common::*;
+use slac::{All, Any, Authorized, Either};
 
 #[tokio::test]
 async fn atomic_pass() {
@@ -0,0 +1,127 @@
+#![cfg(feature = "utoipa")]
+
+mod common;
+
+use common::*;
+use slac::inventory::Inventory;
+use slac::openapi::{DescribePolicy, Security, document, public};
+use slac::{Any, Authorized, secure};
+use utoipa::openapi::path::{OperationBuilder, PathItem, Paths, PathsBuilder};
+use utoipa::openapi::{HttpMethod, OpenApi};
+
+impl DescribePolicy for IsAdmin {
+    fn security() -> Security {
+        Security::scopes("oidc", ["admin"])
+    }
+}
+
+impl DescribePolicy for IsMember {
+    fn security() -> Security {
+        Security::scopes("oidc", ["member"])
+    }
+}
+
+fn paths(path: &str, methods: &[HttpMethod]) -> Paths {
+    let mut builder = PathsBuilder::new();
+    for method in methods {
+        builder = builder.path(
+            path,
+            PathItem::new(method.clone(), OperationBuilder::new().build()),
+        );
+    }
+    builder.build()
+}
+
+#[utoipa::path(get, path = "/teams/{id}")]
+async fn get_team(_: Authorized<Any<IsAdmin, IsMember>, AppState>) {}
+
+#[utoipa::path(delete, path = "/teams/{id}")]
+async fn delete_team(_: Authorized<IsAdmin, AppState>) {}
+
+#[utoipa::path(post, path = "/teams")]
+async fn create_team(_: Authorized<IsAdmin, AppState>) {}
+
+#[utoipa::path(get, path = "/admin/users")]
+async fn list_users(_: Authorized<IsAdmin, AppState>) {}
+
+fn api(groups: impl IntoIterator<Item = Paths>) -> OpenApi {
+    let mut api = OpenApi::new(Default::default(), Paths::new());
+    for paths in groups {
+        api.paths.merge(paths);
+    }
+    api
+}
+
+#[test]
+fn lists_every_operation_with_its_policy() {
+    let (_, admin, _) = secure!(IsAdmin; delete_team);
+    let (_, either, _) = secure!(Any<IsAdmin, IsMember>; get_team);
+    let (_, health, ()) = public((Vec::new(), paths("/health", &[HttpMethod::Get]), ()));
+
+    let inventory = Inventory::from_openapi(&api([admin, either, health]));
+    let listed: Vec<_> = inventory
+        .routes()
+        .iter()
+        .map(|r| (r.method, r.path.as_str(), r.policy.is_some(), r.public))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ("GET", "/health", false, true),
+            ("GET", "/teams/{id}", true, false),
+            ("DELETE", "/teams/{id}", true, false),
+        ]
+    );
+    assert!(
+        inventory.routes()[2]
+            .policy
+            .as_deref()
+            .unwrap()
+            .ends_with("IsAdmin")
+    );
+}
+
+#[test]
+fn report_is_a_json_array() {
+    let (_, admin, _) = secure!(IsAdmin; create_team);
+    let report = Inventory::from_openapi(&api([admin])).to_json();
+    assert_eq!(report[0]["method"], "POST");
+    assert_eq!(report[0]["path"], "/teams");
+    assert!(report[0]["policy"].as_str().unwrap().ends_with("IsAdmin"));
+    assert_eq!(report[0]["public"], false);
+}
+
+#[test]
+fn check_reports_unguarded_routes_under_protected_prefixes() {
+    let (_, admin, _) = secure!(IsAdmin; list_users);
+    let unguarded = paths("/admin/users", &[HttpMethod::Delete]);
+    let elsewhere = paths("/administer", &[HttpMethod::Get]);
+    let inventory = Inventory::from_openapi(&api([admin, unguarded, elsewhere]));
+
+    let err = inventory.check(&["/admin/"]).unwrap_err();
+    assert_eq!(err.routes.len(), 1);
+    assert_eq!(
+        (err.routes[0].method, err.routes[0].path.as_str()),
+        ("DELETE", "/admin/users")
+    );
+    assert!(err.to_string().contains("DELETE /admin/users"));
+
+    inventory.check(&["/teams"]).unwrap();
+}
+
+#[test]
+fn public_routes_pass_the_check() {
+    let (_, open, ()) = public((Vec::new(), paths("/admin/login", &[HttpMethod::Post]), ()));
+    Inventory::from_openapi(&api([open]))
+        .check(&["/admin"])
+        .unwrap();
+}
+
+#[test]
+fn documented_but_unchecked_routes_are_unguarded() {
+    let (_, documented, ()) =
+        document::<IsAdmin, _>((Vec::new(), paths("/admin/users", &[HttpMethod::Get]), ()));
+    let inventory = Inventory::from_openapi(&api([documented]));
+    assert_eq!(inventory.routes()[0].policy, None);
+    assert_eq!(inventory.check(&["/admin"]).unwrap_err().routes.len(), 1);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use axum::Router;
+use axum::http::StatusCode;
+use axum::routing::get;
+use common::*;
+use slac::{Authorized, Layered, PolicyLayer};
+
+fn layered<H, T>(state: AppState, handler: H) -> Router
+where
+    H: axum::handler::Handler<T, AppState>,
+    T: 'static,
+{
+    Router::new()
+        .route("/", get(handler))
+        .route_layer(PolicyLayer::<IsAdmin, _>::new(state.clone()))
+        .with_state(state)
+}
+
+#[tokio::test]
+async fn layer_rejects_before_the_handler() {
+    async fn handler() -> &'static str {
+        unreachable!("the layer should have rejected")
+    }
+
+    let state = AppState::member();
+    let (status, _) = send(layered(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(state.calls(), (1, 0, 0));
+}
+
+#[tokio::test]
+async fn handler_reads_the_layer_proof_without_rechecking() {
+    async fn handler(
+        Authorized { data, .. }: Authorized<Layered<IsAdmin>, AppState>,
+    ) -> &'static str {
+        data.label
+    }
+
+    let state = AppState::admin();
+    let (status, body) = send(layered(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "admin");
+    assert_eq!(state.calls(), (1, 0, 0));
+}
+
+#[tokio::test]
+async fn proof_of_another_policy_is_checked_normally() {
+    async fn handler(
+        Authorized { data, .. }: Authorized<Layered<IsMember>, AppState>,
+    ) -> &'static str {
+        data.label
+    }
+
+    let state = AppState::admin();
+    let (status, body) = send(layered(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "member");
+    assert_eq!(state.calls(), (1, 1, 0));
+}
+
+#[tokio::test]
+async fn missing_layer_falls_back_to_checking() {
+    async fn handler(_: Authorized<Layered<IsAdmin>, AppState>) -> &'static str {
+        "ok"
+    }
+
+    let state = AppState::member();
+    let (status, _) = send(route(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(state.calls(), (1, 0, 0));
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "utoipa")]
+
+mod common;
+
+use axum::http::StatusCode;
+use common::*;
+use slac::openapi::{DescribePolicy, Security};
+use slac::{All, Any, AnyOf, Optional};
+use utoipa::openapi::HttpMethod;
+use utoipa::openapi::path::{OperationBuilder, PathItem, Paths, PathsBuilder};
+
+impl DescribePolicy for IsAdmin {
+    fn security() -> Security {
+        Security::scopes("oidc", ["admin"])
+    }
+}
+
+impl DescribePolicy for IsMember {
+    fn security() -> Security {
+        Security::scopes("oidc", ["member"])
+    }
+}
+
+impl DescribePolicy for IsOwner {
+    fn security() -> Security {
+        Security::scheme("api_key").rejects(StatusCode::NOT_FOUND)
+    }
+}
+
+fn paths() -> Paths {
+    PathsBuilder::new()
+        .path(
+            "/",
+            PathItem::new(HttpMethod::Get, OperationBuilder::new().build()),
+        )
+        .build()
+}
+
+fn document<P: DescribePolicy>() -> serde_json::Value {
+    let (_, paths, ()) = slac::openapi::document::<P, _>((Vec::new(), paths(), ()));
+    serde_json::to_value(&paths.paths["/"].get).unwrap()
+}
+
+#[test]
+fn atomic_policy_documents_requirement_and_responses() {
+    let op = document::<IsAdmin>();
+    assert_eq!(op["security"], serde_json::json!([{ "oidc": ["admin"] }]));
+    assert_eq!(op["responses"]["401"]["description"], "Unauthorized");
+    assert_eq!(op["responses"]["403"]["description"], "Forbidden");
+}
+
+#[test]
+fn any_of_lists_alternatives() {
+    let op = document::<AnyOf<(IsAdmin, IsMember, IsOwner)>>();
+    assert_eq!(
+        op["security"],
+        serde_json::json!([
+            { "oidc": ["admin"] },
+            { "oidc": ["member"] },
+            { "api_key": [] },
+        ])
+    );
+    assert!(op["responses"]["404"].is_object());
+}
+
+#[test]
+fn all_merges_requirements() {
+    let op = document::<All<IsAdmin, All<IsMember, IsOwner>>>();
+    assert_eq!(
+        op["security"],
+        serde_json::json!([{ "oidc": ["admin", "member"], "api_key": [] }])
+    );
+}
+
+#[test]
+fn optional_allows_anonymous_and_never_rejects() {
+    let op = document::<Optional<IsAdmin>>();
+    assert_eq!(
+        op["security"],
+        serde_json::json!([{ "oidc": ["admin"] }, {}])
+    );
+    assert!(op["responses"].get("401").is_none());
+}
+
+#[test]
+fn existing_responses_are_kept() {
+    let mut paths = paths();
+    let op = paths.paths.get_mut("/").unwrap().get.as_mut().unwrap();
+    op.responses.responses.insert(
+        "403".into(),
+        utoipa::openapi::RefOr::T(utoipa::openapi::Response::new("Not an organizer")),
+    );
+
+    let (_, paths, ()) = slac::openapi::document::<IsAdmin, _>((Vec::new(), paths, ()));
+    let op = serde_json::to_value(&paths.paths["/"].get).unwrap();
+    assert_eq!(op["responses"]["403"]["description"], "Not an organizer");
+}
+
+mod teams {
+    use super::*;
+    use slac::Authorized;
+
+    #[utoipa::path(get, path = "/teams/{id}")]
+    pub async fn get_team(
+        _: axum::extract::Path<String>,
+        _: Authorized<Any<IsAdmin, IsMember>, AppState>,
+    ) {
+    }
+}
+
+#[utoipa::path(delete, path = "/teams/{id}")]
+async fn delete_team(_: slac::Authorized<IsAdmin, AppState>) {}
+
+#[test]
+fn secure_macro_documents_handlers_that_extract_the_policy() {
+    let (_, paths, _) = slac::secure!(IsAdmin; delete_team);
+    let op = serde_json::to_value(&paths.paths["/teams/{id}"].delete).unwrap();
+    assert_eq!(op["security"], serde_json::json!([{ "oidc": ["admin"] }]));
+
+    let (_, paths, _): utoipa_axum::router::UtoipaMethodRouter<AppState> =
+        slac::secure!(Any<IsAdmin, IsMember>; teams::get_team);
+    let op = serde_json::to_value(&paths.paths["/teams/{id}"].get).unwrap();
+    assert_eq!(
+        op["security"],
+        serde_json::json!([{ "oidc": ["admin"] }, { "oidc": ["member"] }])
+    );
+}
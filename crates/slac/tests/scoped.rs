@@ -0,0 +1,104 @@
+mod common;
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode, request::Parts};
+use axum::routing::get;
+use common::*;
+use slac::{Authorized, scoped_policy};
+use tower::ServiceExt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Doc {
+    id: String,
+    owned: bool,
+    archived: bool,
+}
+
+async fn load_doc(_parts: &mut Parts, state: &AppState, id: String) -> Result<Doc, StatusCode> {
+    if id == "missing" {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Doc {
+        archived: id.starts_with("old-"),
+        owned: state.inner.is_owner,
+        id,
+    })
+}
+
+scoped_policy! {
+    struct CanReadDoc for AppState, error = StatusCode {
+        let doc: Doc = load_doc["doc_id"];
+    }
+}
+
+scoped_policy! {
+    struct CanEditDoc for AppState, error = StatusCode {
+        let doc: Doc = load_doc["doc_id"];
+        require doc.owned, else StatusCode::FORBIDDEN;
+        require !doc.archived, else StatusCode::CONFLICT;
+        output String = doc.id;
+    }
+}
+
+async fn get_doc(router: Router<AppState>, state: AppState, uri: &str) -> (StatusCode, String) {
+    let response = router
+        .with_state(state)
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+async fn edit(Authorized { data, .. }: Authorized<CanEditDoc, AppState>) -> String {
+    data
+}
+
+fn docs() -> Router<AppState> {
+    Router::new().route("/docs/{doc_id}", get(edit))
+}
+
+#[tokio::test]
+async fn proof_is_the_loaded_value_without_output() {
+    async fn read(Authorized { data, .. }: Authorized<CanReadDoc, AppState>) -> String {
+        format!("{data:?}")
+    }
+
+    let router = Router::new().route("/docs/{doc_id}", get(read));
+    let (status, body) = get_doc(router, AppState::anon(), "/docs/a").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, r#"Doc { id: "a", owned: false, archived: false }"#);
+}
+
+#[tokio::test]
+async fn proof_is_projected_with_output() {
+    let (status, body) = get_doc(docs(), AppState::owner(), "/docs/a").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "a");
+}
+
+#[tokio::test]
+async fn requirements_reject_in_order() {
+    let (status, _) = get_doc(docs(), AppState::anon(), "/docs/old-a").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = get_doc(docs(), AppState::owner(), "/docs/old-a").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn loader_errors_are_returned() {
+    let (status, _) = get_doc(docs(), AppState::owner(), "/docs/missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn undeclared_param_is_a_bad_request() {
+    let router = Router::new().route("/files/{file_id}", get(edit));
+    let (status, _) = get_doc(router, AppState::owner(), "/files/a").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
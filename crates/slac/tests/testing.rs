@@ -0,0 +1,143 @@
+#![cfg(feature = "testing")]
+
+mod common;
+
+use std::panic::AssertUnwindSafe;
+
+use axum::Router;
+use axum::http::{Method, StatusCode};
+use axum::routing::get;
+use common::*;
+use slac::Authorized;
+use slac::testing::{Matrix, stub};
+
+async fn admin_only(_: Authorized<IsAdmin, AppState>) -> &'static str {
+    "ok"
+}
+
+async fn dashboard(_: Authorized<DashboardAccess, AppState>) -> &'static str {
+    "ok"
+}
+
+async fn owned(_: Authorized<IsOwner, AppState>) -> &'static str {
+    "ok"
+}
+
+async fn broken(_: Authorized<IsAdmin, AppState>) -> StatusCode {
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/admin", get(admin_only))
+        .route("/dashboard", get(dashboard))
+        .route("/owned", get(owned))
+        .route("/broken", get(broken))
+        .with_state(state)
+}
+
+fn principals() -> Matrix {
+    Matrix::new()
+        .principal("admin", |req| {
+            stub::<IsAdmin, AppState, _>(req, || Ok(AdminProof { label: "stub" }));
+            stub::<DashboardAccess, AppState, _>(req, || {
+                Ok(DashboardAccess::Admin(AdminProof { label: "stub" }))
+            });
+        })
+        .principal("member", |req| {
+            stub::<DashboardAccess, AppState, _>(req, || {
+                Ok(DashboardAccess::Member(MemberProof { label: "stub" }))
+            });
+        })
+        .principal("anon", |_| {})
+}
+
+#[tokio::test]
+async fn matrix_passes_when_outcomes_match() {
+    let state = AppState::anon();
+    principals()
+        .expect(Method::GET, "/admin", ["admin"])
+        .expect(Method::GET, "/dashboard", ["admin", "member"])
+        .assert(app(state.clone()))
+        .await;
+
+    // Only the checks no principal stubbed reached the state.
+    assert_eq!(state.calls(), (3, 1, 0));
+}
+
+#[tokio::test]
+async fn stubs_override_the_real_state() {
+    Matrix::new()
+        .principal("admin", |req| {
+            stub::<IsAdmin, AppState, _>(req, || Ok(AdminProof { label: "stub" }));
+        })
+        .principal("banned", |req| {
+            stub::<IsAdmin, AppState, _>(req, || Err(StatusCode::UNAUTHORIZED));
+        })
+        .expect(Method::GET, "/admin", ["admin"])
+        .assert(app(AppState::everything()))
+        .await;
+}
+
+#[tokio::test]
+async fn denial_statuses_are_configurable() {
+    Matrix::new()
+        .principal("owner", |req| {
+            stub::<IsOwner, AppState, _>(req, || Ok(OwnerProof { label: "stub" }));
+        })
+        .principal("anon", |_| {})
+        .denied_when(|status| status == StatusCode::NOT_FOUND)
+        .expect(Method::GET, "/owned", ["owner"])
+        .assert(app(AppState::anon()))
+        .await;
+}
+
+#[tokio::test]
+async fn mismatches_are_reported_per_cell() {
+    let result = tokio::spawn(AssertUnwindSafe(async {
+        principals()
+            .expect(Method::GET, "/admin", ["admin", "member"])
+            .expect(Method::GET, "/dashboard", ["admin"])
+            .assert(app(AppState::anon()))
+            .await;
+    }))
+    .await;
+
+    let panic = result.unwrap_err().into_panic();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert_eq!(
+        message,
+        "authorization matrix mismatch (2 of 6 cells):\n\
+         \x20 GET /admin\n\
+         \x20   member  expected allow, got 403 Forbidden\n\
+         \x20 GET /dashboard\n\
+         \x20   member  expected deny, got 200 OK\n"
+    );
+}
+
+#[tokio::test]
+async fn server_errors_are_never_allowed() {
+    let result = tokio::spawn(AssertUnwindSafe(async {
+        Matrix::new()
+            .principal("admin", |req| {
+                stub::<IsAdmin, AppState, _>(req, || Ok(AdminProof { label: "stub" }));
+            })
+            .principal("anon", |req| {
+                stub::<IsAdmin, AppState, _>(req, || Err(StatusCode::INTERNAL_SERVER_ERROR));
+            })
+            .expect(Method::GET, "/broken", ["admin"])
+            .assert(app(AppState::anon()))
+            .await;
+    }))
+    .await;
+
+    let panic = result.unwrap_err().into_panic();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert_eq!(
+        message,
+        "authorization matrix mismatch (2 of 2 cells):\n\
+         \x20 GET /broken\n\
+         \x20   admin  expected allow, got 500 Internal Server Error\n\
+         \x20   anon   expected deny, got 500 Internal Server Error\n"
+    );
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use common::*;
+use slac::{AllOf, AnyOf, Authorized, Either3};
+
+type AnyRole = AnyOf<(IsAdmin, IsMember, IsOwner)>;
+
+async fn any_role(Authorized { data, .. }: Authorized<AnyRole, AppState>) -> impl IntoResponse {
+    match data {
+        Either3::A(p) => format!("a:{}", p.label),
+        Either3::B(p) => format!("b:{}", p.label),
+        Either3::C(p) => format!("c:{}", p.label),
+    }
+}
+
+#[tokio::test]
+async fn any_of_takes_first_match() {
+    let state = AppState::admin();
+    let (status, body) = send(route(state.clone(), any_role)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "a:admin");
+    assert_eq!(state.calls(), (1, 0, 0));
+}
+
+#[tokio::test]
+async fn any_of_falls_through_to_middle() {
+    let state = AppState::member();
+    let (status, body) = send(route(state.clone(), any_role)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "b:member");
+    assert_eq!(state.calls(), (1, 1, 0));
+}
+
+#[tokio::test]
+async fn any_of_falls_through_to_last() {
+    let state = AppState::owner();
+    let (status, body) = send(route(state.clone(), any_role)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "c:owner");
+    assert_eq!(state.calls(), (1, 1, 1));
+}
+
+#[tokio::test]
+async fn any_of_returns_last_error_when_all_fail() {
+    let state = AppState::anon();
+    let (status, _) = send(route(state.clone(), any_role)).await;
+    // IsOwner rejects with 404, the others with 403.
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(state.calls(), (1, 1, 1));
+}
+
+#[tokio::test]
+async fn all_of_yields_flat_tuple() {
+    async fn handler(
+        Authorized { data, .. }: Authorized<AllOf<(IsAdmin, IsMember, IsOwner)>, AppState>,
+    ) -> impl IntoResponse {
+        let (a, m, o) = data;
+        format!("{}+{}+{}", a.label, m.label, o.label)
+    }
+
+    let (status, body) = send(route(AppState::everything(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "admin+member+owner");
+}
+
+#[tokio::test]
+async fn all_of_short_circuits_on_first_failure() {
+    async fn handler(_: Authorized<AllOf<(IsMember, IsAdmin, IsOwner)>, AppState>) -> &'static str {
+        "ok"
+    }
+
+    let state = AppState::member();
+    let (status, _) = send(route(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(state.calls(), (1, 1, 0));
+}
+
+#[tokio::test]
+async fn twelve_way_any_of_compiles_and_runs() {
+    type Wide = AnyOf<(
+        IsAdmin,
+        IsAdmin,
+        IsAdmin,
+        IsAdmin,
+        IsAdmin,
+        IsAdmin,
+        IsAdmin,
+        IsAdmin,
+        IsAdmin,
+        IsAdmin,
+        IsAdmin,
+        IsOwner,
+    )>;
+
+    async fn handler(Authorized { data, .. }: Authorized<Wide, AppState>) -> impl IntoResponse {
+        match data {
+            slac::Either12::L(p) => p.label.to_string(),
+            _ => "other".to_string(),
+        }
+    }
+
+    let state = AppState::owner();
+    let (status, body) = send(route(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "owner");
+    assert_eq!(state.calls(), (11, 0, 1));
+}
+
+#[tokio::test]
+async fn twelve_way_all_of_compiles_and_runs() {
+    type Wide = AllOf<(
+        IsAdmin,
+        IsMember,
+        IsOwner,
+        IsAdmin,
+        IsMember,
+        IsOwner,
+        IsAdmin,
+        IsMember,
+        IsOwner,
+        IsAdmin,
+        IsMember,
+        IsOwner,
+    )>;
+
+    async fn handler(Authorized { data, .. }: Authorized<Wide, AppState>) -> impl IntoResponse {
+        data.11.label
+    }
+
+    let state = AppState::everything();
+    let (status, body) = send(route(state.clone(), handler)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "owner");
+    assert_eq!(state.calls(), (4, 4, 4));
+}
@@ -1,12 +1,24 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20260314_213956_create_initial_tables;
+mod m20261018_120000_create_email_tables;
+mod m20261018_130000_create_messaging_tables;
+mod m20261018_140000_create_user_hackathon_roles;
+mod m20261018_150000_add_hackathon_branding;
+mod m20261018_160000_add_hackathon_lifecycle;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20260314_213956_create_initial_tables::Migration)]
+        vec![
+            Box::new(m20260314_213956_create_initial_tables::Migration),
+            Box::new(m20261018_120000_create_email_tables::Migration),
+            Box::new(m20261018_130000_create_messaging_tables::Migration),
+            Box::new(m20261018_140000_create_user_hackathon_roles::Migration),
+            Box::new(m20261018_150000_add_hackathon_branding::Migration),
+            Box::new(m20261018_160000_add_hackathon_lifecycle::Migration),
+        ]
     }
 }
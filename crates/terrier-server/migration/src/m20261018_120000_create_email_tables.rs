@@ -0,0 +1,218 @@
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(EmailTemplateKind::Enum)
+                    .values([
+                        EmailTemplateKind::ApplicationDecision,
+                        EmailTemplateKind::TeamInvite,
+                        EmailTemplateKind::RsvpReminder,
+                        EmailTemplateKind::JudgingInstructions,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(EmailStatus::Enum)
+                    .values([EmailStatus::Pending, EmailStatus::Sent, EmailStatus::Failed])
+                    .to_owned(),
+            )
+            .await?;
+
+        // Per-hackathon overrides of the default templates shipped with the
+        // server. A hackathon has at most one override per kind; kinds
+        // without a row fall back to the built-in template.
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailTemplate::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EmailTemplate::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(EmailTemplate::HackathonId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EmailTemplate::Kind)
+                            .custom(EmailTemplateKind::Enum)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(EmailTemplate::Subject).string().not_null())
+                    .col(ColumnDef::new(EmailTemplate::TextBody).text().not_null())
+                    .col(ColumnDef::new(EmailTemplate::HtmlBody).text().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-email-template-hackathon")
+                            .from(EmailTemplate::Table, EmailTemplate::HackathonId)
+                            .to(Hackathon::Table, Hackathon::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-email-template-hackathon-kind")
+                    .table(EmailTemplate::Table)
+                    .col(EmailTemplate::HackathonId)
+                    .col(EmailTemplate::Kind)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Messages are rendered at enqueue time so that later template edits
+        // do not change what a recipient was promised. HackathonId is
+        // nullable for platform-level mail that is not tied to an event.
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailOutbox::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EmailOutbox::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(EmailOutbox::HackathonId).string())
+                    .col(ColumnDef::new(EmailOutbox::Recipient).string().not_null())
+                    .col(ColumnDef::new(EmailOutbox::Subject).string().not_null())
+                    .col(ColumnDef::new(EmailOutbox::TextBody).text().not_null())
+                    .col(ColumnDef::new(EmailOutbox::HtmlBody).text().not_null())
+                    .col(
+                        ColumnDef::new(EmailOutbox::Status)
+                            .custom(EmailStatus::Enum)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(EmailOutbox::Attempts).integer().not_null())
+                    .col(ColumnDef::new(EmailOutbox::LastError).text())
+                    .col(
+                        ColumnDef::new(EmailOutbox::NextAttemptAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EmailOutbox::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(EmailOutbox::SentAt).date_time())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-email-outbox-hackathon")
+                            .from(EmailOutbox::Table, EmailOutbox::HackathonId)
+                            .to(Hackathon::Table, Hackathon::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The worker polls for due pending rows, so index exactly that.
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-email-outbox-status-next-attempt")
+                    .table(EmailOutbox::Table)
+                    .col(EmailOutbox::Status)
+                    .col(EmailOutbox::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EmailOutbox::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(EmailTemplate::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(EmailStatus::Enum).to_owned())
+            .await?;
+        manager
+            .drop_type(Type::drop().name(EmailTemplateKind::Enum).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------
+// Iden Enums
+// -------------------------------------------------------------
+
+#[derive(DeriveIden)]
+enum Hackathon {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum EmailTemplateKind {
+    #[sea_orm(iden = "email_template_kind")]
+    Enum,
+    ApplicationDecision,
+    TeamInvite,
+    RsvpReminder,
+    JudgingInstructions,
+}
+
+#[derive(DeriveIden)]
+enum EmailStatus {
+    #[sea_orm(iden = "email_status")]
+    Enum,
+    Pending,
+    Sent,
+    Failed,
+}
+
+#[derive(DeriveIden)]
+enum EmailTemplate {
+    Table,
+    Id,
+    HackathonId,
+    Kind,
+    Subject,
+    TextBody,
+    HtmlBody,
+}
+
+#[derive(DeriveIden)]
+enum EmailOutbox {
+    Table,
+    Id,
+    HackathonId,
+    Recipient,
+    Subject,
+    TextBody,
+    HtmlBody,
+    Status,
+    Attempts,
+    LastError,
+    NextAttemptAt,
+    CreatedAt,
+    SentAt,
+}
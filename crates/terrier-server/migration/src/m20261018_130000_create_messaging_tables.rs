@@ -0,0 +1,497 @@
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // ====================================================================
+        // 1. Columns needed to address and filter audiences
+        // ====================================================================
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(ApplicationStatus::Enum)
+                    .values([
+                        ApplicationStatus::Pending,
+                        ApplicationStatus::Accepted,
+                        ApplicationStatus::Waitlisted,
+                        ApplicationStatus::Rejected,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        // Users authenticate through Keycloak, which owns the address; the
+        // OIDC layer copies it here from verified tokens so judges and
+        // sponsors can be emailed.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::Email).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Applicants were not previously tied to a hackathon, so existing rows
+        // keep a NULL HackathonId and never match a hackathon's audience.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Applicant::Table)
+                    .add_column(ColumnDef::new(Applicant::HackathonId).string())
+                    .add_column(
+                        ColumnDef::new(Applicant::Status)
+                            .custom(ApplicationStatus::Enum)
+                            .not_null()
+                            .default(Expr::cust("'pending'")),
+                    )
+                    .add_column(ColumnDef::new(Applicant::RsvpAt).date_time())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-applicant-hackathon")
+                            .from_tbl(Applicant::Table)
+                            .from_col(Applicant::HackathonId)
+                            .to_tbl(Hackathon::Table)
+                            .to_col(Hackathon::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sponsor::Table)
+                    .add_column(ColumnDef::new(Sponsor::HackathonId).string())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-sponsor-hackathon")
+                            .from_tbl(Sponsor::Table)
+                            .from_col(Sponsor::HackathonId)
+                            .to_tbl(Hackathon::Table)
+                            .to_col(Hackathon::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Judges may be assigned to a single track of a hackathon; NULL keeps
+        // an assignment to the hackathon as a whole.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(JudgeAssignment::Table)
+                    .add_column(ColumnDef::new(JudgeAssignment::TrackId).string())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-ja-track")
+                            .from_tbl(JudgeAssignment::Table)
+                            .from_col(JudgeAssignment::TrackId)
+                            .to_tbl(Track::Table)
+                            .to_col(Track::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // ====================================================================
+        // 2. Segments, bulk messages and their recipients
+        // ====================================================================
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(BulkMessageStatus::Enum)
+                    .values([
+                        BulkMessageStatus::Scheduled,
+                        BulkMessageStatus::Sent,
+                        BulkMessageStatus::Cancelled,
+                        BulkMessageStatus::Failed,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(RecipientKind::Enum)
+                    .values([
+                        RecipientKind::Applicant,
+                        RecipientKind::Hacker,
+                        RecipientKind::Judge,
+                        RecipientKind::Sponsor,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        // Audience is stored as JSON (see `messaging::audience::Audience` in
+        // terrier-server) so new filters do not need a migration.
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessageSegment::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MessageSegment::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MessageSegment::HackathonId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(MessageSegment::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(MessageSegment::Audience)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MessageSegment::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-message-segment-hackathon")
+                            .from(MessageSegment::Table, MessageSegment::HackathonId)
+                            .to(Hackathon::Table, Hackathon::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The audience is copied from the segment (if any) when the message
+        // is created, so editing or deleting a segment never changes who a
+        // scheduled message goes to.
+        manager
+            .create_table(
+                Table::create()
+                    .table(BulkMessage::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BulkMessage::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(BulkMessage::HackathonId).string().not_null())
+                    .col(ColumnDef::new(BulkMessage::SegmentId).string())
+                    .col(ColumnDef::new(BulkMessage::CreatedBy).string().not_null())
+                    .col(
+                        ColumnDef::new(BulkMessage::Audience)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(BulkMessage::Subject).string().not_null())
+                    .col(ColumnDef::new(BulkMessage::TextBody).text().not_null())
+                    .col(ColumnDef::new(BulkMessage::HtmlBody).text())
+                    .col(
+                        ColumnDef::new(BulkMessage::Status)
+                            .custom(BulkMessageStatus::Enum)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BulkMessage::ScheduledAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    // Dispatch attempts, retried with backoff like the email
+                    // outbox until the message is marked failed.
+                    .col(
+                        ColumnDef::new(BulkMessage::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(BulkMessage::LastError).text())
+                    .col(
+                        ColumnDef::new(BulkMessage::NextAttemptAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BulkMessage::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(BulkMessage::SentAt).date_time())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bulk-message-hackathon")
+                            .from(BulkMessage::Table, BulkMessage::HackathonId)
+                            .to(Hackathon::Table, Hackathon::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bulk-message-segment")
+                            .from(BulkMessage::Table, BulkMessage::SegmentId)
+                            .to(MessageSegment::Table, MessageSegment::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bulk-message-user")
+                            .from(BulkMessage::Table, BulkMessage::CreatedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-bulk-message-status-next-attempt-at")
+                    .table(BulkMessage::Table)
+                    .col(BulkMessage::Status)
+                    .col(BulkMessage::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // One row per resolved recipient. Delivery status lives on the linked
+        // outbox row; EmailOutboxId is NULL when the recipient had no address.
+        manager
+            .create_table(
+                Table::create()
+                    .table(BulkMessageRecipient::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BulkMessageRecipient::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(BulkMessageRecipient::BulkMessageId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BulkMessageRecipient::Kind)
+                            .custom(RecipientKind::Enum)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BulkMessageRecipient::EntityId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(BulkMessageRecipient::Email).string())
+                    .col(ColumnDef::new(BulkMessageRecipient::EmailOutboxId).string())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bmr-bulk-message")
+                            .from(
+                                BulkMessageRecipient::Table,
+                                BulkMessageRecipient::BulkMessageId,
+                            )
+                            .to(BulkMessage::Table, BulkMessage::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bmr-email-outbox")
+                            .from(
+                                BulkMessageRecipient::Table,
+                                BulkMessageRecipient::EmailOutboxId,
+                            )
+                            .to(EmailOutbox::Table, EmailOutbox::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BulkMessageRecipient::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(BulkMessage::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(MessageSegment::Table).to_owned())
+            .await?;
+        manager
+            .drop_type(Type::drop().name(RecipientKind::Enum).to_owned())
+            .await?;
+        manager
+            .drop_type(Type::drop().name(BulkMessageStatus::Enum).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(JudgeAssignment::Table)
+                    .drop_foreign_key(Alias::new("fk-ja-track"))
+                    .drop_column(JudgeAssignment::TrackId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sponsor::Table)
+                    .drop_foreign_key(Alias::new("fk-sponsor-hackathon"))
+                    .drop_column(Sponsor::HackathonId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Applicant::Table)
+                    .drop_foreign_key(Alias::new("fk-applicant-hackathon"))
+                    .drop_column(Applicant::HackathonId)
+                    .drop_column(Applicant::Status)
+                    .drop_column(Applicant::RsvpAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Email)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_type(Type::drop().name(ApplicationStatus::Enum).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------
+// Iden Enums
+// -------------------------------------------------------------
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+    Email,
+}
+
+#[derive(DeriveIden)]
+enum Applicant {
+    Table,
+    HackathonId,
+    Status,
+    RsvpAt,
+}
+
+#[derive(DeriveIden)]
+enum Sponsor {
+    Table,
+    HackathonId,
+}
+
+#[derive(DeriveIden)]
+enum JudgeAssignment {
+    Table,
+    TrackId,
+}
+
+#[derive(DeriveIden)]
+enum Track {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Hackathon {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum EmailOutbox {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ApplicationStatus {
+    #[sea_orm(iden = "application_status")]
+    Enum,
+    Pending,
+    Accepted,
+    Waitlisted,
+    Rejected,
+}
+
+#[derive(DeriveIden)]
+enum BulkMessageStatus {
+    #[sea_orm(iden = "bulk_message_status")]
+    Enum,
+    Scheduled,
+    Sent,
+    Cancelled,
+    Failed,
+}
+
+#[derive(DeriveIden)]
+enum RecipientKind {
+    #[sea_orm(iden = "recipient_kind")]
+    Enum,
+    Applicant,
+    Hacker,
+    Judge,
+    Sponsor,
+}
+
+#[derive(DeriveIden)]
+enum MessageSegment {
+    Table,
+    Id,
+    HackathonId,
+    Name,
+    Audience,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum BulkMessage {
+    Table,
+    Id,
+    HackathonId,
+    SegmentId,
+    CreatedBy,
+    Audience,
+    Subject,
+    TextBody,
+    HtmlBody,
+    Status,
+    ScheduledAt,
+    Attempts,
+    LastError,
+    NextAttemptAt,
+    CreatedAt,
+    SentAt,
+}
+
+#[derive(DeriveIden)]
+enum BulkMessageRecipient {
+    Table,
+    Id,
+    BulkMessageId,
+    Kind,
+    EntityId,
+    Email,
+    EmailOutboxId,
+}
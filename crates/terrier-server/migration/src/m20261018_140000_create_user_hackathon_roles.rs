@@ -0,0 +1,239 @@
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(HackathonRole::Enum)
+                    .values([
+                        HackathonRole::Organizer,
+                        HackathonRole::Admin,
+                        HackathonRole::Volunteer,
+                        HackathonRole::Judge,
+                        HackathonRole::Sponsor,
+                        HackathonRole::Hacker,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        // A user may hold several roles in the same hackathon (an organizer
+        // who also judges), but each at most once.
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserHackathonRoles::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserHackathonRoles::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(UserHackathonRoles::UserId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserHackathonRoles::HackathonId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserHackathonRoles::Role)
+                            .custom(HackathonRole::Enum)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserHackathonRoles::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user-hackathon-roles-user")
+                            .from(UserHackathonRoles::Table, UserHackathonRoles::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user-hackathon-roles-hackathon")
+                            .from(UserHackathonRoles::Table, UserHackathonRoles::HackathonId)
+                            .to(Hackathon::Table, Hackathon::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-user-hackathon-roles-unique")
+                    .table(UserHackathonRoles::Table)
+                    .col(UserHackathonRoles::HackathonId)
+                    .col(UserHackathonRoles::UserId)
+                    .col(UserHackathonRoles::Role)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // The old role column was free text. Compare it case-insensitively,
+        // and refuse to drop it while it holds a value that maps to no role,
+        // rather than silently revoking that user's access.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                DO $$
+                DECLARE unmapped text;
+                BEGIN
+                    SELECT string_agg(DISTINCT role, ', ') INTO unmapped
+                    FROM "user"
+                    WHERE lower(trim(role)) NOT IN
+                        ('organizer', 'admin', 'volunteer', 'judge', 'sponsor', 'hacker');
+                    IF unmapped IS NOT NULL THEN
+                        RAISE EXCEPTION 'user.role values with no hackathon role: %', unmapped;
+                    END IF;
+                END
+                $$
+                "#,
+            )
+            .await?;
+
+        // Backfill from what the old schema can tell us. The global role had
+        // no hackathon attached, so staff roles are granted on every existing
+        // hackathon to preserve current access; participant roles come from
+        // the rows that already tie a user to a specific hackathon.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                INSERT INTO user_hackathon_roles (id, user_id, hackathon_id, role, created_at)
+                SELECT gen_random_uuid()::text, user_id, hackathon_id, role::hackathon_role, now()
+                FROM (
+                    SELECT u.id AS user_id, h.id AS hackathon_id, lower(trim(u.role)) AS role
+                    FROM "user" u CROSS JOIN hackathon h
+                    WHERE lower(trim(u.role)) IN ('organizer', 'admin', 'volunteer')
+                    UNION
+                    SELECT j.user_id, ja.hackathon_id, 'judge'
+                    FROM judge j JOIN judge_assignment ja ON ja.judge_id = j.id
+                    UNION
+                    SELECT s.user_id, s.hackathon_id, 'sponsor'
+                    FROM sponsor s
+                    WHERE s.user_id IS NOT NULL AND s.hackathon_id IS NOT NULL
+                    UNION
+                    SELECT hk.user_id, t.hackathon_id, 'hacker'
+                    FROM hacker hk JOIN team t ON t.id = hk.team_id
+                    WHERE hk.user_id IS NOT NULL
+                ) AS grants
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Role)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(
+                        ColumnDef::new(User::Role)
+                            .string()
+                            .not_null()
+                            .default("hacker"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Collapse back to the single most privileged role each user held
+        // anywhere.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                UPDATE "user" u SET role = r.role
+                FROM (
+                    SELECT DISTINCT ON (user_id) user_id, role::text AS role
+                    FROM user_hackathon_roles
+                    ORDER BY user_id, array_position(
+                        ARRAY['admin', 'organizer', 'volunteer', 'judge', 'sponsor', 'hacker'],
+                        role::text
+                    )
+                ) AS r
+                WHERE u.id = r.user_id
+                "#,
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(UserHackathonRoles::Table).to_owned())
+            .await?;
+        manager
+            .drop_type(Type::drop().name(HackathonRole::Enum).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------
+// Iden Enums
+// -------------------------------------------------------------
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+    Role,
+}
+
+#[derive(DeriveIden)]
+enum Hackathon {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum HackathonRole {
+    #[sea_orm(iden = "hackathon_role")]
+    Enum,
+    Organizer,
+    Admin,
+    Volunteer,
+    Judge,
+    Sponsor,
+    Hacker,
+}
+
+#[derive(DeriveIden)]
+enum UserHackathonRoles {
+    Table,
+    Id,
+    UserId,
+    HackathonId,
+    Role,
+    CreatedAt,
+}
@@ -0,0 +1,123 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Hackathon::Table)
+                    .add_column(ColumnDef::new(Hackathon::Slug).string())
+                    .add_column(
+                        ColumnDef::new(Hackathon::Timezone)
+                            .string()
+                            .not_null()
+                            .default("UTC"),
+                    )
+                    .add_column(ColumnDef::new(Hackathon::Description).text())
+                    .add_column(ColumnDef::new(Hackathon::LogoUrl).string())
+                    .add_column(ColumnDef::new(Hackathon::PrimaryColor).string())
+                    .add_column(ColumnDef::new(Hackathon::AccentColor).string())
+                    .add_column(
+                        ColumnDef::new(Hackathon::SocialLinks)
+                            .json_binary()
+                            .not_null()
+                            .default(Expr::cust("'[]'::jsonb")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing hackathons get a slug derived from their name, suffixed
+        // with a hash of the id so that two events with the same name do not
+        // collide. The result must pass `validate_slug` in terrier-server:
+        // the name part is cut to 40 characters and falls back to
+        // "hackathon" when nothing of it is left, and the hex suffix keeps
+        // the slug lowercase and at least 3 characters long. Organizers can
+        // rename it afterwards.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                UPDATE hackathon
+                SET slug = coalesce(
+                        nullif(trim(both '-' from left(
+                            trim(both '-' from lower(regexp_replace(name, '[^a-zA-Z0-9]+', '-', 'g'))),
+                            40
+                        )), ''),
+                        'hackathon'
+                    ) || '-' || left(md5(id), 12)
+                "#,
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Hackathon::Table)
+                    .modify_column(ColumnDef::new(Hackathon::Slug).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-hackathon-slug")
+                    .table(Hackathon::Table)
+                    .col(Hackathon::Slug)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-hackathon-slug")
+                    .table(Hackathon::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Hackathon::Table)
+                    .drop_column(Hackathon::Slug)
+                    .drop_column(Hackathon::Timezone)
+                    .drop_column(Hackathon::Description)
+                    .drop_column(Hackathon::LogoUrl)
+                    .drop_column(Hackathon::PrimaryColor)
+                    .drop_column(Hackathon::AccentColor)
+                    .drop_column(Hackathon::SocialLinks)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------
+// Iden Enums
+// -------------------------------------------------------------
+
+#[derive(DeriveIden)]
+enum Hackathon {
+    Table,
+    Slug,
+    Timezone,
+    Description,
+    LogoUrl,
+    PrimaryColor,
+    AccentColor,
+    SocialLinks,
+}
@@ -0,0 +1,110 @@
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(HackathonPhase::Enum)
+                    .values([
+                        HackathonPhase::Applications,
+                        HackathonPhase::CheckIn,
+                        HackathonPhase::Hacking,
+                        HackathonPhase::Judging,
+                        HackathonPhase::Closed,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        // Each `*_at` column is when the hackathon enters that phase; NULL
+        // means the transition is only ever made by hand. `PhaseLocked` is set
+        // by a manual override and stops the scheduler from moving the phase.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Hackathon::Table)
+                    .add_column(
+                        ColumnDef::new(Hackathon::Phase)
+                            .custom(HackathonPhase::Enum)
+                            .not_null()
+                            .default(Expr::cust("'applications'")),
+                    )
+                    .add_column(
+                        ColumnDef::new(Hackathon::PhaseLocked)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(ColumnDef::new(Hackathon::CheckInAt).date_time())
+                    .add_column(ColumnDef::new(Hackathon::HackingAt).date_time())
+                    .add_column(ColumnDef::new(Hackathon::JudgingAt).date_time())
+                    .add_column(ColumnDef::new(Hackathon::ClosedAt).date_time())
+                    .to_owned(),
+            )
+            .await?;
+
+        // The existing event dates are the best guess at when hacking starts
+        // and when the event is over.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"UPDATE hackathon SET hacking_at = start_date, closed_at = end_date"#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Hackathon::Table)
+                    .drop_column(Hackathon::Phase)
+                    .drop_column(Hackathon::PhaseLocked)
+                    .drop_column(Hackathon::CheckInAt)
+                    .drop_column(Hackathon::HackingAt)
+                    .drop_column(Hackathon::JudgingAt)
+                    .drop_column(Hackathon::ClosedAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_type(Type::drop().name(HackathonPhase::Enum).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------
+// Iden Enums
+// -------------------------------------------------------------
+
+#[derive(DeriveIden)]
+enum Hackathon {
+    Table,
+    Phase,
+    PhaseLocked,
+    CheckInAt,
+    HackingAt,
+    JudgingAt,
+    ClosedAt,
+}
+
+#[derive(DeriveIden)]
+enum HackathonPhase {
+    #[sea_orm(iden = "hackathon_phase")]
+    Enum,
+    Applications,
+    CheckIn,
+    Hacking,
+    Judging,
+    Closed,
+}
@@ -0,0 +1,129 @@
+//! Organizer decisions on hackathon applications.
+//!
+//! Recording a decision updates the applicant's status and, in the same
+//! transaction, queues the `application_decision` email, so an applicant is
+//! notified exactly when their status changes.
+
+use crate::auth::{Auth, policies::IsHackathonOrganizer};
+use crate::entities::{
+    applicant,
+    prelude::*,
+    sea_orm_active_enums::{ApplicationStatus, EmailTemplateKind},
+};
+use crate::error::Error;
+use crate::state::AppState;
+use axum::Json;
+use axum::extract::{Path, State};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QuerySelect, Set,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use slac::secure;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+
+pub fn router() -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::new().routes(secure!(IsHackathonOrganizer; decide))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct Decision {
+    /// `accepted`, `waitlisted` or `rejected`.
+    pub status: ApplicationStatus,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Application {
+    pub applicant_id: String,
+    pub status: ApplicationStatus,
+    /// The queued notification, or `None` if the status was already set.
+    pub email_id: Option<String>,
+}
+
+/// Variables for the `application_decision` template.
+#[derive(Serialize)]
+struct DecisionEmail<'a> {
+    first_name: &'a str,
+    accepted: bool,
+    status: &'a ApplicationStatus,
+}
+
+/// Sets an applicant's status and emails them the decision. Repeating the
+/// current status is a no-op, so retries do not send a second email.
+#[utoipa::path(
+    put,
+    path = "/hackathons/{hackathon_id}/applicants/{applicant_id}/decision",
+    tag = "applications",
+    params(("hackathon_id" = String, Path), ("applicant_id" = String, Path)),
+    request_body = Decision,
+    responses((status = OK, body = Application), (status = NOT_FOUND)),
+)]
+pub async fn decide(
+    Auth {
+        data: (user, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<(String, String)>,
+    Json(body): Json<Decision>,
+) -> Result<Json<Application>, Error> {
+    let (_, applicant_id) = path;
+    if body.status == ApplicationStatus::Pending {
+        return Err(Error::BadRequest(
+            "a decision must accept, waitlist or reject".into(),
+        ));
+    }
+
+    let txn = state.db.begin().await?;
+    // Locking the row keeps two organizers deciding at once from both
+    // seeing the old status and each sending an email.
+    let applicant = Applicant::find_by_id(&applicant_id)
+        .filter(applicant::Column::HackathonId.eq(&hackathon.id))
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("applicant {applicant_id}")))?;
+
+    if applicant.status == body.status {
+        return Ok(Json(Application {
+            applicant_id,
+            status: body.status,
+            email_id: None,
+        }));
+    }
+
+    let mut active = applicant.into_active_model();
+    active.status = Set(body.status.clone());
+    let applicant = active.update(&txn).await?;
+
+    let email_id = state
+        .mailer
+        .enqueue(
+            &txn,
+            &hackathon,
+            EmailTemplateKind::ApplicationDecision,
+            applicant.email.clone(),
+            DecisionEmail {
+                first_name: &applicant.first_name,
+                accepted: applicant.status == ApplicationStatus::Accepted,
+                status: &applicant.status,
+            },
+        )
+        .await?;
+    txn.commit().await?;
+
+    tracing::info!(
+        hackathon = %hackathon.id,
+        user = %user.id,
+        applicant = %applicant.id,
+        status = ?applicant.status,
+        "application decided"
+    );
+    Ok(Json(Application {
+        applicant_id: applicant.id,
+        status: applicant.status,
+        email_id: Some(email_id),
+    }))
+}
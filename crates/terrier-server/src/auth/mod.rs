@@ -0,0 +1,62 @@
+//! Authentication context and Terrier's atomic [`slac::Policy`] impls.
+//!
+//! See `rfcs/0009-slac.md`. Identity comes from Keycloak; the [`oidc`] layer
+//! in front of these routes inserts a [`CurrentUser`] into the request
+//! extensions, and everything here only decides what that user may do.
+
+pub mod oidc;
+pub mod policies;
+
+use crate::error::Error;
+use crate::state::AppState;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use std::sync::Arc;
+
+/// Name of the OpenAPI security scheme for Keycloak-issued bearer tokens.
+/// Role policies document the hackathon role they need as a scope on it.
+pub const OIDC_SCHEME: &str = "oidc";
+
+pub type Auth<P> = slac::Authorized<P, Arc<AppState>>;
+
+/// The authenticated caller, as resolved by the [`oidc`] layer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CurrentUser {
+    pub id: String,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CurrentUser>()
+            .cloned()
+            .ok_or(Error::Unauthorized)
+    }
+}
+
+/// Writes every authorization decision to the `terrier::audit` tracing
+/// target, which deployments route to their audit log sink.
+pub struct AuditLog;
+
+impl slac::DecisionObserver for AuditLog {
+    fn observe(&self, decision: &slac::Decision<'_>) {
+        let user = decision
+            .parts
+            .extensions
+            .get::<CurrentUser>()
+            .map(|u| u.id.as_str());
+        tracing::info!(
+            target: "terrier::audit",
+            policy = decision.policy,
+            method = %decision.parts.method,
+            route = decision.route,
+            user,
+            allowed = decision.allowed,
+            elapsed_us = decision.elapsed.as_micros() as u64,
+            "authorization decision"
+        );
+    }
+}
@@ -0,0 +1,382 @@
+//! The OIDC layer: verifies the Keycloak access token a request carries as
+//! `Authorization: Bearer` and inserts its subject as the [`CurrentUser`].
+
+use crate::auth::CurrentUser;
+use crate::config::OidcConfig;
+use crate::entities::{prelude::*, user};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+
+/// Unknown key IDs trigger a refetch of the realm's keys, so Keycloak can
+/// rotate them, but at most this often.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+/// The holder of a verified token.
+#[derive(Debug)]
+pub struct Identity {
+    pub user: CurrentUser,
+    /// The address Keycloak has verified for the user, if any.
+    pub email: Option<String>,
+}
+
+/// Checks access tokens against the signing keys Keycloak publishes for the
+/// realm, fetched on first use.
+pub struct TokenVerifier {
+    issuer: String,
+    client_id: String,
+    jwks_url: String,
+    http: reqwest::Client,
+    keys: RwLock<JwkSet>,
+    fetched_at: Mutex<Option<Instant>>,
+}
+
+impl TokenVerifier {
+    pub fn new(config: &OidcConfig) -> Self {
+        Self {
+            issuer: config.issuer.clone(),
+            client_id: config.client_id.clone(),
+            jwks_url: config.jwks_url(),
+            http: reqwest::Client::new(),
+            keys: RwLock::new(JwkSet { keys: Vec::new() }),
+            fetched_at: Mutex::new(None),
+        }
+    }
+
+    /// The user a token was issued to, if it is signed by the realm, names
+    /// Terrier as its audience and has not expired.
+    pub async fn verify(&self, token: &str) -> Result<Identity> {
+        let header = jsonwebtoken::decode_header(token).context("malformed token")?;
+        let kid = header.kid.context("token has no key ID")?;
+        let jwk = match self.key(&kid).await {
+            Some(jwk) => jwk,
+            None => {
+                self.refresh().await?;
+                self.key(&kid)
+                    .await
+                    .with_context(|| format!("unknown signing key {kid}"))?
+            }
+        };
+
+        // A key that names its algorithm only verifies that algorithm.
+        let algorithm = match jwk.common.key_algorithm {
+            Some(alg) => alg.to_string().parse::<Algorithm>()?,
+            None => header.alg,
+        };
+        anyhow::ensure!(
+            header.alg == algorithm,
+            "token algorithm does not match its key"
+        );
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.client_id]);
+
+        let key = DecodingKey::from_jwk(&jwk)?;
+        let claims = jsonwebtoken::decode::<Claims>(token, &key, &validation)?.claims;
+        Ok(Identity {
+            user: CurrentUser { id: claims.sub },
+            email: claims.email.filter(|_| claims.email_verified),
+        })
+    }
+
+    async fn key(&self, kid: &str) -> Option<Jwk> {
+        self.keys.read().await.find(kid).cloned()
+    }
+
+    async fn refresh(&self) -> Result<()> {
+        let mut fetched_at = self.fetched_at.lock().await;
+        if fetched_at.is_some_and(|at| at.elapsed() < MIN_REFRESH_INTERVAL) {
+            return Ok(());
+        }
+        *fetched_at = Some(Instant::now());
+
+        let keys: JwkSet = self
+            .http
+            .get(&self.jwks_url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("failed to fetch signing keys from {}", self.jwks_url))?
+            .json()
+            .await
+            .context("invalid signing key set")?;
+        *self.keys.write().await = keys;
+        Ok(())
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// What [`authenticate`] needs from the app state.
+pub trait Authenticator: Send + Sync + 'static {
+    fn tokens(&self) -> &TokenVerifier;
+    fn db(&self) -> &DatabaseConnection;
+}
+
+impl Authenticator for AppState {
+    fn tokens(&self) -> &TokenVerifier {
+        &self.tokens
+    }
+
+    fn db(&self) -> &DatabaseConnection {
+        &self.db
+    }
+}
+
+/// Copies the user's verified address from Keycloak, which owns it, so
+/// judges and sponsors can be emailed. The row is only written when the
+/// address changed.
+async fn store_email(db: &DatabaseConnection, identity: &Identity) -> Result<(), DbErr> {
+    let Some(email) = &identity.email else {
+        return Ok(());
+    };
+    User::update_many()
+        .col_expr(user::Column::Email, Expr::value(email.clone()))
+        .filter(user::Column::Id.eq(&identity.user.id))
+        .filter(
+            Condition::any()
+                .add(user::Column::Email.is_null())
+                .add(user::Column::Email.ne(email.clone())),
+        )
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Inserts the [`CurrentUser`] for a valid bearer token. Requests without
+/// one, or with one that fails verification, continue anonymously, so public
+/// routes still answer and guarded ones reject them with `401`.
+pub async fn authenticate<S: Authenticator>(
+    State(state): State<Arc<S>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if let Some(token) = bearer_token(request.headers()) {
+        match state.tokens().verify(token).await {
+            Ok(identity) => {
+                if let Err(e) = store_email(state.db(), &identity).await {
+                    tracing::warn!(user = identity.user.id, error = %e, "failed to store email");
+                }
+                request.extensions_mut().insert(identity.user);
+            }
+            Err(e) => tracing::debug!(error = %e, "rejected bearer token"),
+        }
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use jsonwebtoken::{EncodingKey, Header};
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Transaction};
+    use tower::ServiceExt;
+
+    const ISSUER: &str = "https://keycloak.test/realms/terrier";
+    const CLIENT_ID: &str = "terrier";
+    const SECRET: &[u8] = b"terrier-test-signing-secret";
+
+    fn verifier() -> TokenVerifier {
+        let mut verifier = TokenVerifier::new(&OidcConfig {
+            issuer: ISSUER.into(),
+            client_id: CLIENT_ID.into(),
+        });
+        // Already fetched, so unknown keys are not looked up over the network.
+        *verifier.fetched_at.get_mut() = Some(Instant::now());
+        *verifier.keys.get_mut() = serde_json::from_value(serde_json::json!({
+            "keys": [{
+                "kty": "oct",
+                "kid": "k1",
+                "alg": "HS256",
+                "k": "dGVycmllci10ZXN0LXNpZ25pbmctc2VjcmV0",
+            }]
+        }))
+        .unwrap();
+        verifier
+    }
+
+    fn token(kid: &str, claims: serde_json::Value) -> String {
+        signed_token(kid, claims, SECRET)
+    }
+
+    fn signed_token(kid: &str, claims: serde_json::Value, secret: &[u8]) -> String {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(kid.into());
+        jsonwebtoken::encode(&header, &claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    fn claims() -> serde_json::Value {
+        serde_json::json!({
+            "sub": "u1",
+            "iss": ISSUER,
+            "aud": [CLIENT_ID, "account"],
+            "exp": chrono::Utc::now().timestamp() + 300,
+        })
+    }
+
+    #[tokio::test]
+    async fn accepts_tokens_for_terrier() {
+        let identity = verifier().verify(&token("k1", claims())).await.unwrap();
+        assert_eq!(identity.user.id, "u1");
+        assert_eq!(identity.email, None);
+    }
+
+    #[tokio::test]
+    async fn keeps_only_verified_email() {
+        let mut verified = claims();
+        verified["email"] = "judge@example.edu".into();
+        verified["email_verified"] = true.into();
+        let mut unverified = verified.clone();
+        unverified["email_verified"] = false.into();
+
+        let verifier = verifier();
+        let identity = verifier.verify(&token("k1", verified)).await.unwrap();
+        assert_eq!(identity.email.as_deref(), Some("judge@example.edu"));
+        let identity = verifier.verify(&token("k1", unverified)).await.unwrap();
+        assert_eq!(identity.email, None);
+    }
+
+    #[tokio::test]
+    async fn stores_verified_email_on_the_user() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection();
+        let identity = Identity {
+            user: CurrentUser { id: "u1".into() },
+            email: Some("judge@example.edu".into()),
+        };
+        store_email(&db, &identity).await.unwrap();
+        // Without an address there is nothing to store.
+        store_email(
+            &db,
+            &Identity {
+                email: None,
+                ..identity
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"UPDATE "user" SET "email" = $1 WHERE "user"."id" = $2 AND ("user"."email" IS NULL OR "user"."email" <> $3)"#,
+                [
+                    "judge@example.edu".into(),
+                    "u1".into(),
+                    "judge@example.edu".into(),
+                ],
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_tokens() {
+        let verifier = verifier();
+        let mut other_issuer = claims();
+        other_issuer["iss"] = "https://keycloak.test/realms/other".into();
+        let mut other_audience = claims();
+        other_audience["aud"] = "account".into();
+        let mut expired = claims();
+        expired["exp"] = (chrono::Utc::now().timestamp() - 3600).into();
+
+        for token in [
+            token("k1", other_issuer),
+            token("k1", other_audience),
+            token("k1", expired),
+            token("k2", claims()),
+            signed_token("k1", claims(), b"forged"),
+            "not a token".into(),
+        ] {
+            assert!(verifier.verify(&token).await.is_err(), "{token}");
+        }
+    }
+
+    struct TestState {
+        tokens: TokenVerifier,
+        db: DatabaseConnection,
+    }
+
+    impl Authenticator for TestState {
+        fn tokens(&self) -> &TokenVerifier {
+            &self.tokens
+        }
+
+        fn db(&self) -> &DatabaseConnection {
+            &self.db
+        }
+    }
+
+    async fn whoami(request: Request) -> String {
+        request
+            .extensions()
+            .get::<CurrentUser>()
+            .map(|u| u.id.clone())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn layer_inserts_current_user() {
+        let state = Arc::new(TestState {
+            tokens: verifier(),
+            db: MockDatabase::new(DatabaseBackend::Postgres).into_connection(),
+        });
+        let app = Router::new()
+            .route("/whoami", get(whoami))
+            .layer(axum::middleware::from_fn_with_state(state, authenticate));
+        let request = |authorization: Option<String>| {
+            let mut builder = Request::builder().uri("/whoami");
+            if let Some(value) = authorization {
+                builder = builder.header(header::AUTHORIZATION, value);
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        for (authorization, expected) in [
+            (Some(format!("Bearer {}", token("k1", claims()))), "u1"),
+            (Some(format!("Bearer {}", token("k2", claims()))), ""),
+            (None, ""),
+        ] {
+            let response = app.clone().oneshot(request(authorization)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, expected);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::auth::{CurrentUser, OIDC_SCHEME};
+use crate::entities::{
+    hackathon, prelude::*, sea_orm_active_enums::HackathonRole, user_hackathon_roles,
+};
+use crate::error::Error;
+use crate::lifecycle::{effective_phase, phase::Phase};
+use crate::state::AppState;
+use axum::extract::{FromRequestParts, Path};
+use axum::http::StatusCode;
+use axum::http::request::Parts;
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{ActiveEnum, ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+use slac::openapi::{DescribePolicy, Security};
+use slac::{AnyOf, Policy, policy, scoped_policy};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Loads the hackathon named by the `{hackathon_id}` path segment.
+pub(crate) async fn hackathon_from_path(
+    parts: &mut Parts,
+    state: &Arc<AppState>,
+) -> Result<hackathon::Model, Error> {
+    let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+        .await
+        .map_err(|e| Error::BadRequest(e.body_text()))?;
+    let id = params
+        .get("hackathon_id")
+        .ok_or_else(|| Error::BadRequest("missing hackathon_id".into()))?;
+
+    Hackathon::find_by_id(id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("hackathon {id}")))
+}
+
+/// Every role `user_id` holds in `hackathon_id`.
+pub(crate) async fn roles_in(
+    state: &AppState,
+    user_id: &str,
+    hackathon_id: &str,
+) -> Result<Vec<HackathonRole>, Error> {
+    Ok(UserHackathonRoles::find()
+        .select_only()
+        .column(user_hackathon_roles::Column::Role)
+        .filter(user_hackathon_roles::Column::UserId.eq(user_id))
+        .filter(user_hackathon_roles::Column::HackathonId.eq(hackathon_id))
+        .into_tuple()
+        .all(&state.db)
+        .await?)
+}
+
+/// The caller, a hackathon and every role the caller holds in it.
+pub(crate) struct Membership {
+    pub user: CurrentUser,
+    pub hackathon: hackathon::Model,
+    pub roles: Vec<HackathonRole>,
+}
+
+impl Membership {
+    pub fn holds_any(&self, accepted: &[HackathonRole]) -> bool {
+        self.roles.iter().any(|r| accepted.contains(r))
+    }
+}
+
+/// Loader for [`scoped_policy!`](slac::scoped_policy) checks on the
+/// hackathon in `hackathon_id`.
+pub(crate) async fn membership(
+    parts: &mut Parts,
+    state: &Arc<AppState>,
+    hackathon_id: String,
+) -> Result<Membership, Error> {
+    let user = CurrentUser::from_request_parts(parts, state).await?;
+    let hackathon = Hackathon::find_by_id(&hackathon_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("hackathon {hackathon_id}")))?;
+    let roles = roles_in(state, &user.id, &hackathon.id).await?;
+
+    Ok(Membership {
+        user,
+        hackathon,
+        roles,
+    })
+}
+
+/// The OIDC scope a role is documented under, matching its database value.
+fn role_scope(role: &HackathonRole) -> &'static str {
+    match role {
+        HackathonRole::Admin => "admin",
+        HackathonRole::Hacker => "hacker",
+        HackathonRole::Judge => "judge",
+        HackathonRole::Organizer => "organizer",
+        HackathonRole::Sponsor => "sponsor",
+        HackathonRole::Volunteer => "volunteer",
+    }
+}
+
+/// Holding any one of `accepted`, in a hackathon that may not exist.
+fn role_security(accepted: &[HackathonRole]) -> Security {
+    accepted
+        .iter()
+        .map(|r| Security::scopes(OIDC_SCHEME, [role_scope(r)]))
+        .reduce(Security::or)
+        .unwrap_or_default()
+        .rejects(StatusCode::NOT_FOUND)
+}
+
+macro_rules! role_policy {
+    ($(#[$attr:meta])* $name:ident => [$($role:ident),+]) => {
+        scoped_policy! {
+            $(#[$attr])*
+            pub struct $name for Arc<AppState>, error = Error {
+                let m: Membership = membership["hackathon_id"];
+                require m.holds_any(&[$(HackathonRole::$role),+]), else Error::Forbidden;
+                output (CurrentUser, hackathon::Model) = (m.user, m.hackathon);
+            }
+        }
+
+        impl DescribePolicy for $name {
+            fn security() -> Security {
+                role_security(&[$(HackathonRole::$role),+])
+            }
+        }
+    };
+}
+
+role_policy! {
+    /// The caller is an admin of the hackathon in the `{hackathon_id}` path
+    /// segment. The proof carries the loaded hackathon so handlers need not
+    /// refetch it.
+    IsHackathonAdmin => [Admin]
+}
+
+role_policy! {
+    /// The caller organizes the hackathon in the `{hackathon_id}` path
+    /// segment. Admins are organizers too.
+    IsHackathonOrganizer => [Organizer, Admin]
+}
+
+role_policy! {
+    /// The caller volunteers at the hackathon in the `{hackathon_id}` path
+    /// segment.
+    IsHackathonVolunteer => [Volunteer]
+}
+
+role_policy! {
+    /// The caller judges at the hackathon in the `{hackathon_id}` path
+    /// segment.
+    IsHackathonJudge => [Judge]
+}
+
+role_policy! {
+    /// The caller represents a sponsor of the hackathon in the
+    /// `{hackathon_id}` path segment.
+    IsHackathonSponsor => [Sponsor]
+}
+
+role_policy! {
+    /// The caller is a hacker at the hackathon in the `{hackathon_id}` path
+    /// segment.
+    IsHackathonHacker => [Hacker]
+}
+
+/// The hackathon in the `{hackathon_id}` path segment is currently in phase
+/// `P`, e.g. `InPhase<phase::Judging>`. Pair it with a role policy via
+/// [`slac::All`]; on its own it says nothing about the caller.
+pub struct InPhase<P>(PhantomData<fn() -> P>);
+
+impl<P: Phase> Policy<Arc<AppState>> for InPhase<P> {
+    type Output = hackathon::Model;
+    type Error = Error;
+
+    async fn check(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self::Output, Self::Error> {
+        let hackathon = hackathon_from_path(parts, state).await?;
+        Self::admits(&hackathon, Utc::now().naive_utc())?;
+        Ok(hackathon)
+    }
+}
+
+impl<P: Phase> InPhase<P> {
+    /// Whether `hackathon` is in phase `P` at `now` (UTC).
+    fn admits(hackathon: &hackathon::Model, now: NaiveDateTime) -> Result<(), Error> {
+        let current = effective_phase(hackathon, now);
+        if current != P::PHASE {
+            return Err(Error::Conflict(format!(
+                "not available during the {} phase",
+                current.to_value()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<P> DescribePolicy for InPhase<P> {
+    fn security() -> Security {
+        Security::none()
+            .rejects(StatusCode::NOT_FOUND)
+            .rejects(StatusCode::CONFLICT)
+    }
+}
+
+policy! {
+    /// Who may change role assignments in a hackathon. Only admins may
+    /// grant or revoke the admin role itself.
+    pub enum RoleManager for Arc<AppState>, error = Error {
+        Admin = IsHackathonAdmin,
+        Organizer = IsHackathonOrganizer,
+    }
+}
+
+impl DescribePolicy for RoleManager {
+    fn security() -> Security {
+        AnyOf::<(IsHackathonAdmin, IsHackathonOrganizer)>::security()
+    }
+}
+
+impl RoleManager {
+    pub fn into_parts(self) -> (CurrentUser, hackathon::Model) {
+        match self {
+            Self::Admin(data) | Self::Organizer(data) => data,
+        }
+    }
+
+    /// Whether this manager may grant or revoke `role`.
+    pub fn may_manage(&self, role: &HackathonRole) -> bool {
+        matches!(self, Self::Admin(_)) || *role != HackathonRole::Admin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::Iterable;
+
+    fn manager(admin: bool) -> RoleManager {
+        let data = (CurrentUser { id: "u1".into() }, crate::testing::hackathon());
+        if admin {
+            RoleManager::Admin(data)
+        } else {
+            RoleManager::Organizer(data)
+        }
+    }
+
+    #[test]
+    fn admins_manage_every_role() {
+        let admin = manager(true);
+        assert!(HackathonRole::iter().all(|r| admin.may_manage(&r)));
+    }
+
+    #[test]
+    fn organizers_cannot_manage_admins() {
+        let organizer = manager(false);
+        assert!(!organizer.may_manage(&HackathonRole::Admin));
+        assert!(organizer.may_manage(&HackathonRole::Organizer));
+        assert!(organizer.may_manage(&HackathonRole::Judge));
+    }
+
+    #[test]
+    fn in_phase_follows_the_schedule_in_the_hackathon_timezone() {
+        use crate::entities::sea_orm_active_enums::HackathonPhase;
+        use crate::lifecycle::phase;
+        use chrono::NaiveDate;
+
+        let mut h = crate::testing::hackathon();
+        h.phase = HackathonPhase::Hacking;
+        // 09:00 in Pittsburgh, 14:00 UTC.
+        h.judging_at = NaiveDate::from_ymd_opt(2027, 2, 7)
+            .unwrap()
+            .and_hms_opt(9, 0, 0);
+        let utc = |hour| {
+            NaiveDate::from_ymd_opt(2027, 2, 7)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap()
+        };
+
+        assert!(matches!(
+            InPhase::<phase::Judging>::admits(&h, utc(13)),
+            Err(Error::Conflict(_))
+        ));
+        assert!(InPhase::<phase::Hacking>::admits(&h, utc(13)).is_ok());
+        assert!(InPhase::<phase::Judging>::admits(&h, utc(14)).is_ok());
+
+        // An override holds the phase regardless of the schedule.
+        h.phase_locked = true;
+        assert!(InPhase::<phase::Hacking>::admits(&h, utc(14)).is_ok());
+    }
+
+    #[test]
+    fn organizer_policy_documents_each_accepted_role() {
+        let security = IsHackathonOrganizer::security();
+        let scopes: Vec<_> = security
+            .requirements
+            .iter()
+            .flat_map(|r| r[OIDC_SCHEME].iter().copied())
+            .collect();
+        assert_eq!(scopes, ["organizer", "admin"]);
+        assert_eq!(security.rejections, [401, 403, 404].into());
+    }
+}
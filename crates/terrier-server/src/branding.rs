@@ -0,0 +1,291 @@
+//! Public landing configuration for a hackathon.
+//!
+//! Web and mobile clients address a hackathon by its slug and fetch
+//! [`Branding`] without authenticating, so everything here must be safe to
+//! show to anyone. Organizers edit it through the authenticated
+//! `/hackathons/{hackathon_id}/branding` route.
+
+use crate::auth::{Auth, policies::IsHackathonOrganizer};
+use crate::entities::{hackathon, prelude::*, sea_orm_active_enums::HackathonPhase};
+use crate::error::Error;
+use crate::lifecycle::effective_phase;
+use crate::state::AppState;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::IntoResponse;
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, Set, SqlErr,
+};
+use serde::{Deserialize, Serialize};
+use slac::openapi::public;
+use slac::secure;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+/// How long clients and shared caches may reuse a branding response.
+const BRANDING_MAX_AGE_SECS: u32 = 300;
+
+pub fn router() -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::new()
+        .routes(public(routes!(get_branding)))
+        .routes(secure!(IsHackathonOrganizer; update_branding))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SocialLink {
+    /// Display label, e.g. "Instagram" or "Discord".
+    pub label: String,
+    pub url: String,
+}
+
+/// Colours as `#rrggbb`. Clients fall back to their own defaults when unset.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Theme {
+    pub primary_color: Option<String>,
+    pub accent_color: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Branding {
+    pub slug: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub location: String,
+    /// IANA zone the event's schedule is shown in, e.g. `America/New_York`.
+    pub timezone: String,
+    pub start_date: NaiveDateTime,
+    pub end_date: NaiveDateTime,
+    /// Current lifecycle phase, so landing pages can show e.g. whether
+    /// applications are open.
+    pub phase: HackathonPhase,
+    pub logo_url: Option<String>,
+    pub theme: Theme,
+    pub social_links: Vec<SocialLink>,
+}
+
+impl TryFrom<hackathon::Model> for Branding {
+    type Error = Error;
+
+    fn try_from(h: hackathon::Model) -> Result<Self, Error> {
+        Ok(Self {
+            phase: effective_phase(&h, Utc::now().naive_utc()),
+            social_links: serde_json::from_value(h.social_links).map_err(|e| {
+                anyhow::anyhow!(
+                    "stored social links for hackathon {} are malformed: {e}",
+                    h.id
+                )
+            })?,
+            slug: h.slug,
+            name: h.name,
+            description: h.description,
+            location: h.location,
+            timezone: h.timezone,
+            start_date: h.start_date,
+            end_date: h.end_date,
+            logo_url: h.logo_url,
+            theme: Theme {
+                primary_color: h.primary_color,
+                accent_color: h.accent_color,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateBranding {
+    pub slug: String,
+    pub timezone: String,
+    pub description: Option<String>,
+    pub logo_url: Option<String>,
+    #[serde(default)]
+    pub theme: Theme,
+    #[serde(default)]
+    pub social_links: Vec<SocialLink>,
+}
+
+impl UpdateBranding {
+    fn validate(&self) -> Result<(), String> {
+        validate_slug(&self.slug)?;
+        self.timezone
+            .parse::<chrono_tz::Tz>()
+            .map_err(|_| format!("unknown timezone {:?}", self.timezone))?;
+        if let Some(logo) = &self.logo_url {
+            validate_url(logo)?;
+        }
+        for color in [&self.theme.primary_color, &self.theme.accent_color]
+            .into_iter()
+            .flatten()
+        {
+            validate_color(color)?;
+        }
+        for link in &self.social_links {
+            if link.label.trim().is_empty() {
+                return Err("social link labels must not be empty".into());
+            }
+            validate_url(&link.url)?;
+        }
+        Ok(())
+    }
+}
+
+/// Slugs appear in URLs, so they are limited to lowercase ASCII letters,
+/// digits and single inner hyphens.
+fn validate_slug(slug: &str) -> Result<(), String> {
+    let valid = (3..=64).contains(&slug.len())
+        && slug
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-')
+        && !slug.contains("--");
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid slug {slug:?}: use 3-64 lowercase letters, digits and hyphens"
+        ))
+    }
+}
+
+fn validate_color(color: &str) -> Result<(), String> {
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => Ok(()),
+        _ => Err(format!("invalid colour {color:?}: expected #rrggbb")),
+    }
+}
+
+fn validate_url(raw: &str) -> Result<(), String> {
+    match url::Url::parse(raw) {
+        Ok(url) if matches!(url.scheme(), "https" | "http") => Ok(()),
+        _ => Err(format!("invalid URL {raw:?}: expected an http(s) URL")),
+    }
+}
+
+/// Public branding for the hackathon with the given slug. Does not require
+/// authentication.
+#[utoipa::path(
+    get,
+    path = "/h/{slug}/branding",
+    tag = "branding",
+    params(("slug" = String, Path)),
+    responses((status = OK, body = Branding), (status = NOT_FOUND)),
+)]
+pub async fn get_branding(
+    State(state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let hackathon = Hackathon::find()
+        .filter(hackathon::Column::Slug.eq(&slug))
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("hackathon {slug}")))?;
+
+    Ok((
+        [(
+            header::CACHE_CONTROL,
+            format!("public, max-age={BRANDING_MAX_AGE_SECS}"),
+        )],
+        Json(Branding::try_from(hackathon)?),
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/hackathons/{hackathon_id}/branding",
+    tag = "branding",
+    params(("hackathon_id" = String, Path)),
+    request_body = UpdateBranding,
+    responses((status = OK, body = Branding), (status = CONFLICT)),
+)]
+pub async fn update_branding(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<UpdateBranding>,
+) -> Result<Json<Branding>, Error> {
+    body.validate().map_err(Error::BadRequest)?;
+
+    let slug = body.slug.clone();
+    let mut active = hackathon.into_active_model();
+    active.slug = Set(body.slug);
+    active.timezone = Set(body.timezone);
+    active.description = Set(body.description);
+    active.logo_url = Set(body.logo_url);
+    active.primary_color = Set(body.theme.primary_color);
+    active.accent_color = Set(body.theme.accent_color);
+    active.social_links =
+        Set(serde_json::to_value(&body.social_links).map_err(anyhow::Error::from)?);
+    // The unique index decides, so two organizers claiming the same slug at
+    // once cannot both pass a check made before the write.
+    let hackathon = active
+        .update(&state.db)
+        .await
+        .map_err(|e| match e.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => {
+                Error::Conflict(format!("slug {slug:?} is already in use"))
+            }
+            _ => e.into(),
+        })?;
+
+    Ok(Json(hackathon.try_into()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update() -> UpdateBranding {
+        UpdateBranding {
+            slug: "tartanhacks-2027".into(),
+            timezone: "America/New_York".into(),
+            description: Some("CMU's largest hackathon".into()),
+            logo_url: Some("https://tartanhacks.com/logo.svg".into()),
+            theme: Theme {
+                primary_color: Some("#1A2B3c".into()),
+                accent_color: None,
+            },
+            social_links: vec![SocialLink {
+                label: "Instagram".into(),
+                url: "https://instagram.com/tartanhacks".into(),
+            }],
+        }
+    }
+
+    #[test]
+    fn valid_update_passes() {
+        assert_eq!(update().validate(), Ok(()));
+    }
+
+    #[test]
+    fn slug_rules() {
+        for ok in ["abc", "tartan-hacks", "th2027"] {
+            assert!(validate_slug(ok).is_ok(), "{ok}");
+        }
+        for bad in ["ab", "Tartan", "-th", "th-", "t--h", "th_27", "th/27"] {
+            assert!(validate_slug(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_timezone() {
+        let body = UpdateBranding {
+            timezone: "Mars/Olympus_Mons".into(),
+            ..update()
+        };
+        assert!(body.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_bad_colour_and_urls() {
+        assert!(validate_color("#12345").is_err());
+        assert!(validate_color("123456").is_err());
+        assert!(validate_color("#12345g").is_err());
+        assert!(validate_url("javascript:alert(1)").is_err());
+        assert!(validate_url("not a url").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result, bail};
+use std::path::PathBuf;
+
+pub struct Config {
+    pub host: String,
+    pub port: u16,
+    pub database_url: String,
+    pub oidc: OidcConfig,
+    pub email: EmailConfig,
+}
+
+/// The Keycloak realm whose access tokens authenticate API requests.
+pub struct OidcConfig {
+    /// The realm URL, which tokens carry as `iss`.
+    pub issuer: String,
+    /// Terrier's client, which tokens must name in `aud`.
+    pub client_id: String,
+}
+
+/// Which [`Transport`](crate::email::transport::Transport) the outbox worker
+/// delivers through.
+pub enum EmailTransportConfig {
+    Smtp { url: String },
+    File { dir: PathBuf },
+    Stdout,
+}
+
+pub struct EmailConfig {
+    pub from: String,
+    pub transport: EmailTransportConfig,
+    pub rate_limit_per_second: u32,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self> {
+        let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".into());
+        let port = std::env::var("PORT")
+            .unwrap_or_else(|_| "3000".into())
+            .parse::<u16>()
+            .context("PORT must be a valid u16")?;
+        let database_url = std::env::var("DATABASE_URL").context("DATABASE_URL must be set")?;
+
+        Ok(Self {
+            host,
+            port,
+            database_url,
+            oidc: OidcConfig::from_env()?,
+            email: EmailConfig::from_env()?,
+        })
+    }
+}
+
+impl OidcConfig {
+    pub fn from_env() -> Result<Self> {
+        let url = std::env::var("KEYCLOAK_URL").context("KEYCLOAK_URL must be set")?;
+        let realm = std::env::var("KEYCLOAK_REALM").context("KEYCLOAK_REALM must be set")?;
+        let client_id = std::env::var("OIDC_CLIENT_ID").context("OIDC_CLIENT_ID must be set")?;
+
+        Ok(Self {
+            issuer: format!("{}/realms/{realm}", url.trim_end_matches('/')),
+            client_id,
+        })
+    }
+
+    /// Where the realm publishes its token signing keys.
+    pub fn jwks_url(&self) -> String {
+        format!("{}/protocol/openid-connect/certs", self.issuer)
+    }
+}
+
+impl EmailConfig {
+    pub fn from_env() -> Result<Self> {
+        let from = std::env::var("EMAIL_FROM")
+            .unwrap_or_else(|_| "Terrier <noreply@terrier.build>".into());
+
+        let transport = match std::env::var("EMAIL_TRANSPORT")
+            .unwrap_or_else(|_| "stdout".into())
+            .as_str()
+        {
+            "smtp" => EmailTransportConfig::Smtp {
+                url: std::env::var("SMTP_URL")
+                    .context("SMTP_URL must be set when EMAIL_TRANSPORT=smtp")?,
+            },
+            "file" => EmailTransportConfig::File {
+                dir: std::env::var("EMAIL_FILE_DIR")
+                    .unwrap_or_else(|_| "mail".into())
+                    .into(),
+            },
+            "stdout" => EmailTransportConfig::Stdout,
+            other => bail!("EMAIL_TRANSPORT must be one of smtp, file, stdout (got {other})"),
+        };
+
+        let rate_limit_per_second = std::env::var("EMAIL_RATE_LIMIT_PER_SECOND")
+            .unwrap_or_else(|_| "5".into())
+            .parse::<u32>()
+            .context("EMAIL_RATE_LIMIT_PER_SECOND must be a valid u32")?;
+        if rate_limit_per_second == 0 {
+            bail!("EMAIL_RATE_LIMIT_PER_SECOND must be greater than zero");
+        }
+
+        Ok(Self {
+            from,
+            transport,
+            rate_limit_per_second,
+        })
+    }
+}
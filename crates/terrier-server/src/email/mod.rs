@@ -0,0 +1,74 @@
+//! Transactional email.
+//!
+//! Handlers render a message with [`Mailer::enqueue`], which writes it to the
+//! `email_outbox` table (optionally inside the caller's transaction). The
+//! [`outbox::outbox_task`] worker then delivers due messages through the
+//! configured [`transport::Transport`], retrying failures with exponential
+//! backoff. Organizers may replace a built-in template for their hackathon
+//! through [`overrides`].
+
+pub mod outbox;
+pub mod overrides;
+pub mod template;
+pub mod transport;
+
+use crate::config::EmailConfig;
+use crate::entities::{
+    email_outbox, email_template, hackathon, prelude::EmailTemplate,
+    sea_orm_active_enums::EmailTemplateKind,
+};
+use anyhow::{Context, Result};
+use lettre::message::Mailbox;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use serde::Serialize;
+use template::Templates;
+use transport::Transport;
+
+pub struct Mailer {
+    from: Mailbox,
+    transport: Transport,
+    templates: Templates,
+}
+
+impl Mailer {
+    pub fn new(config: &EmailConfig) -> Result<Self> {
+        Ok(Self {
+            from: config
+                .from
+                .parse()
+                .context("EMAIL_FROM must be a valid mailbox")?,
+            transport: Transport::from_config(&config.transport)?,
+            templates: Templates::new(),
+        })
+    }
+
+    pub fn templates(&self) -> &Templates {
+        &self.templates
+    }
+
+    /// Renders `kind` for `hackathon` (using the hackathon's override if it
+    /// has one) and queues it for `recipient`. Returns the outbox row ID.
+    pub async fn enqueue<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        hackathon: &hackathon::Model,
+        kind: EmailTemplateKind,
+        recipient: String,
+        vars: impl Serialize,
+    ) -> Result<String> {
+        let custom = EmailTemplate::find()
+            .filter(email_template::Column::HackathonId.eq(&hackathon.id))
+            .filter(email_template::Column::Kind.eq(kind.clone()))
+            .one(db)
+            .await?;
+
+        let rendered = self
+            .templates
+            .render(&kind, hackathon, custom.as_ref(), vars)?;
+        outbox::insert(db, Some(hackathon.id.clone()), recipient, rendered).await
+    }
+
+    pub(crate) async fn send(&self, email: &email_outbox::Model) -> Result<()> {
+        self.transport.send(&self.from, email).await
+    }
+}
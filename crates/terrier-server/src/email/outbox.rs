@@ -0,0 +1,156 @@
+use crate::email::Mailer;
+use crate::email::template::RenderedEmail;
+use crate::entities::{email_outbox, prelude::EmailOutbox, sea_orm_active_enums::EmailStatus};
+use crate::state::AppState;
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::sea_query::LockBehavior;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Attempts after which a message is marked `failed` and no longer retried.
+pub const MAX_ATTEMPTS: i32 = 8;
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Writes a rendered message to the outbox as `pending`, due immediately.
+/// Takes any connection so callers can enqueue inside the same transaction
+/// as the state change that triggered the email.
+pub async fn insert<C: ConnectionTrait>(
+    db: &C,
+    hackathon_id: Option<String>,
+    recipient: String,
+    email: RenderedEmail,
+) -> Result<String> {
+    let now = Utc::now().naive_utc();
+    let id = uuid::Uuid::new_v4().to_string();
+
+    email_outbox::ActiveModel {
+        id: Set(id.clone()),
+        hackathon_id: Set(hackathon_id),
+        recipient: Set(recipient),
+        subject: Set(email.subject),
+        text_body: Set(email.text),
+        html_body: Set(email.html),
+        status: Set(EmailStatus::Pending),
+        attempts: Set(0),
+        last_error: Set(None),
+        next_attempt_at: Set(now),
+        created_at: Set(now),
+        sent_at: Set(None),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(id)
+}
+
+/// Delay before retrying a message that has failed `attempts` times:
+/// exponential from [`BASE_BACKOFF`], capped at [`MAX_BACKOFF`].
+pub fn backoff(attempts: i32) -> Duration {
+    let exp = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    BASE_BACKOFF.saturating_mul(1 << exp).min(MAX_BACKOFF)
+}
+
+/// Claims the oldest due message with `FOR UPDATE SKIP LOCKED` (so several
+/// replicas can drain the same outbox), sends it and records the outcome.
+/// Returns `false` when nothing was due.
+pub async fn deliver_next(db: &DatabaseConnection, mailer: &Mailer) -> Result<bool> {
+    let txn = db.begin().await?;
+    let now = Utc::now().naive_utc();
+
+    let Some(email) = EmailOutbox::find()
+        .filter(email_outbox::Column::Status.eq(EmailStatus::Pending))
+        .filter(email_outbox::Column::NextAttemptAt.lte(now))
+        .order_by_asc(email_outbox::Column::NextAttemptAt)
+        .lock_with_behavior(
+            sea_orm::sea_query::LockType::Update,
+            LockBehavior::SkipLocked,
+        )
+        .one(&txn)
+        .await?
+    else {
+        txn.commit().await?;
+        return Ok(false);
+    };
+
+    let result = mailer.send(&email).await;
+    let attempts = email.attempts + 1;
+    let id = email.id.clone();
+    let mut active = email.into_active_model();
+    active.attempts = Set(attempts);
+
+    match result {
+        Ok(()) => {
+            active.status = Set(EmailStatus::Sent);
+            active.sent_at = Set(Some(Utc::now().naive_utc()));
+            active.last_error = Set(None);
+            tracing::info!(%id, attempts, "email sent");
+        }
+        Err(e) if attempts >= MAX_ATTEMPTS => {
+            active.status = Set(EmailStatus::Failed);
+            active.last_error = Set(Some(format!("{e:#}")));
+            tracing::error!(%id, attempts, error = %e, "email permanently failed");
+        }
+        Err(e) => {
+            let retry_at = now + chrono::TimeDelta::from_std(backoff(attempts))?;
+            active.next_attempt_at = Set(retry_at);
+            active.last_error = Set(Some(format!("{e:#}")));
+            tracing::warn!(%id, attempts, %retry_at, error = %e, "email delivery failed, will retry");
+        }
+    }
+
+    active.update(&txn).await?;
+    txn.commit().await?;
+    Ok(true)
+}
+
+/// Drains the outbox forever, sending at most
+/// `EMAIL_RATE_LIMIT_PER_SECOND` messages per second from this replica and
+/// polling every few seconds when the outbox is empty.
+pub async fn outbox_task(state: Arc<AppState>) {
+    let per_message = Duration::from_secs(1) / state.config.email.rate_limit_per_second;
+    let mut interval = tokio::time::interval(per_message);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        match deliver_next(&state.db, &state.mailer).await {
+            Ok(true) => {}
+            Ok(false) => tokio::time::sleep(POLL_INTERVAL).await,
+            Err(e) => {
+                tracing::error!(error = %e, "failed to process email outbox");
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_from_base() {
+        assert_eq!(backoff(1), Duration::from_secs(30));
+        assert_eq!(backoff(2), Duration::from_secs(60));
+        assert_eq!(backoff(3), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(backoff(7), Duration::from_secs(1920));
+        assert_eq!(backoff(8), MAX_BACKOFF);
+        assert_eq!(backoff(100), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn backoff_handles_zero_attempts() {
+        assert_eq!(backoff(0), BASE_BACKOFF);
+    }
+}
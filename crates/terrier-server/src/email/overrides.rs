@@ -0,0 +1,176 @@
+//! Organizer-managed overrides of the built-in transactional templates.
+//!
+//! A hackathon has at most one override per [`EmailTemplateKind`]; while it
+//! exists, [`Mailer::enqueue`](super::Mailer::enqueue) renders it in place of
+//! the built-in template. Deleting it restores the built-in.
+
+use super::template::sample_vars;
+use crate::auth::{Auth, policies::IsHackathonOrganizer};
+use crate::entities::{email_template, prelude::*, sea_orm_active_enums::EmailTemplateKind};
+use crate::error::Error;
+use crate::state::AppState;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+use slac::secure;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+
+pub fn router() -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::new()
+        .routes(secure!(IsHackathonOrganizer; list_overrides))
+        .routes(secure!(
+            IsHackathonOrganizer;
+            put_override,
+            delete_override
+        ))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TemplateOverride {
+    pub kind: EmailTemplateKind,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+impl From<email_template::Model> for TemplateOverride {
+    fn from(m: email_template::Model) -> Self {
+        Self {
+            kind: m.kind,
+            subject: m.subject,
+            text_body: m.text_body,
+            html_body: m.html_body,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PutOverride {
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/hackathons/{hackathon_id}/email-templates",
+    tag = "email",
+    params(("hackathon_id" = String, Path)),
+    responses((status = OK, body = Vec<TemplateOverride>)),
+)]
+pub async fn list_overrides(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<TemplateOverride>>, Error> {
+    let overrides = EmailTemplate::find()
+        .filter(email_template::Column::HackathonId.eq(&hackathon.id))
+        .order_by_asc(email_template::Column::Kind)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(TemplateOverride::from)
+        .collect();
+    Ok(Json(overrides))
+}
+
+/// Creates or replaces the override for `kind`. The sources are rendered
+/// against placeholder values first, so a syntax error or a variable the
+/// server never provides is rejected here rather than when a real email is
+/// sent.
+#[utoipa::path(
+    put,
+    path = "/hackathons/{hackathon_id}/email-templates/{kind}",
+    tag = "email",
+    params(("hackathon_id" = String, Path), ("kind" = EmailTemplateKind, Path)),
+    request_body = PutOverride,
+    responses((status = OK, body = TemplateOverride), (status = BAD_REQUEST)),
+)]
+pub async fn put_override(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<(String, EmailTemplateKind)>,
+    Json(body): Json<PutOverride>,
+) -> Result<Json<TemplateOverride>, Error> {
+    let (_, kind) = path;
+    if body.subject.trim().is_empty() {
+        return Err(Error::BadRequest("subject must not be empty".into()));
+    }
+
+    let model = email_template::Model {
+        id: uuid::Uuid::new_v4().to_string(),
+        hackathon_id: hackathon.id.clone(),
+        kind: kind.clone(),
+        subject: body.subject,
+        text_body: body.text_body,
+        html_body: body.html_body,
+    };
+    state
+        .mailer
+        .templates()
+        .render(&kind, &hackathon, Some(&model), sample_vars(&kind))
+        .map_err(|e| Error::BadRequest(format!("{e:#}")))?;
+
+    let saved = EmailTemplate::insert(email_template::ActiveModel {
+        id: Set(model.id),
+        hackathon_id: Set(model.hackathon_id),
+        kind: Set(model.kind),
+        subject: Set(model.subject),
+        text_body: Set(model.text_body),
+        html_body: Set(model.html_body),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            email_template::Column::HackathonId,
+            email_template::Column::Kind,
+        ])
+        .update_columns([
+            email_template::Column::Subject,
+            email_template::Column::TextBody,
+            email_template::Column::HtmlBody,
+        ])
+        .to_owned(),
+    )
+    .exec_with_returning(&state.db)
+    .await?;
+
+    Ok(Json(saved.into()))
+}
+
+/// Removes the override for `kind`, so the built-in template is used again.
+#[utoipa::path(
+    delete,
+    path = "/hackathons/{hackathon_id}/email-templates/{kind}",
+    tag = "email",
+    params(("hackathon_id" = String, Path), ("kind" = EmailTemplateKind, Path)),
+    responses((status = NO_CONTENT), (status = NOT_FOUND)),
+)]
+pub async fn delete_override(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<(String, EmailTemplateKind)>,
+) -> Result<StatusCode, Error> {
+    let (_, kind) = path;
+    let result = EmailTemplate::delete_many()
+        .filter(email_template::Column::HackathonId.eq(&hackathon.id))
+        .filter(email_template::Column::Kind.eq(kind.clone()))
+        .exec(&state.db)
+        .await?;
+    if result.rows_affected == 0 {
+        return Err(Error::NotFound(format!("no {kind:?} override")));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
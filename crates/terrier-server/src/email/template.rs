@@ -0,0 +1,352 @@
+use crate::entities::{email_template, hackathon, sea_orm_active_enums::EmailTemplateKind};
+use anyhow::{Context, Result};
+use minijinja::{Environment, UndefinedBehavior, Value, context};
+use serde::Serialize;
+
+/// A fully rendered message, ready to be written to the outbox.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+/// Hackathon fields exposed to every template as `hackathon.*`.
+#[derive(Serialize)]
+struct HackathonContext<'a> {
+    name: &'a str,
+    location: &'a str,
+    start_date: String,
+    end_date: String,
+}
+
+impl<'a> From<&'a hackathon::Model> for HackathonContext<'a> {
+    fn from(h: &'a hackathon::Model) -> Self {
+        Self {
+            name: &h.name,
+            location: &h.location,
+            start_date: h.start_date.format("%B %-d, %Y").to_string(),
+            end_date: h.end_date.format("%B %-d, %Y").to_string(),
+        }
+    }
+}
+
+macro_rules! builtin {
+    ($env:ident, $dir:literal) => {
+        for (part, source) in [
+            (
+                "subject.txt",
+                include_str!(concat!("../../templates/email/", $dir, "/subject.txt")),
+            ),
+            (
+                "body.txt",
+                include_str!(concat!("../../templates/email/", $dir, "/body.txt")),
+            ),
+            (
+                "body.html",
+                include_str!(concat!("../../templates/email/", $dir, "/body.html")),
+            ),
+        ] {
+            $env.add_template_owned(format!("{}/{part}", $dir), source)
+                .expect(concat!("built-in ", $dir, " template must parse"));
+        }
+    };
+}
+
+/// The built-in templates shipped with the server, plus per-hackathon
+/// overrides rendered against the same environment so they can extend
+/// `layout.html`.
+///
+/// HTML bodies are auto-escaped by file extension; undefined variables are an
+/// error rather than an empty string so that a typo in an organizer's
+/// override surfaces at enqueue time instead of in a recipient's inbox.
+pub struct Templates {
+    env: Environment<'static>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Templates {
+    pub fn new() -> Self {
+        let mut env = Environment::new();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.add_template(
+            "layout.html",
+            include_str!("../../templates/email/layout.html"),
+        )
+        .expect("built-in layout template must parse");
+        env.add_template(
+            "plain.html",
+            include_str!("../../templates/email/plain.html"),
+        )
+        .expect("built-in plain template must parse");
+
+        builtin!(env, "application_decision");
+        builtin!(env, "team_invite");
+        builtin!(env, "rsvp_reminder");
+        builtin!(env, "judging_instructions");
+
+        Self { env }
+    }
+
+    /// Renders `kind` for `hackathon`, preferring the hackathon's override if
+    /// one is given. `vars` is merged into the context alongside `hackathon`.
+    pub fn render(
+        &self,
+        kind: &EmailTemplateKind,
+        hackathon: &hackathon::Model,
+        custom: Option<&email_template::Model>,
+        vars: impl Serialize,
+    ) -> Result<RenderedEmail> {
+        if let Some(t) = custom {
+            return self.render_custom(
+                hackathon,
+                &t.subject,
+                &t.text_body,
+                Some(&t.html_body),
+                vars,
+            );
+        }
+
+        let ctx = Self::context(hackathon, vars);
+        let dir = template_dir(kind);
+        Ok(RenderedEmail {
+            subject: self
+                .render_builtin(&format!("{dir}/subject.txt"), &ctx)?
+                .trim()
+                .to_string(),
+            text: self.render_builtin(&format!("{dir}/body.txt"), &ctx)?,
+            html: self.render_builtin(&format!("{dir}/body.html"), &ctx)?,
+        })
+    }
+
+    /// Renders ad-hoc template sources, e.g. an organizer's bulk message.
+    /// Without an HTML source, the rendered text is escaped and wrapped in the
+    /// branded layout.
+    pub fn render_custom(
+        &self,
+        hackathon: &hackathon::Model,
+        subject: &str,
+        text: &str,
+        html: Option<&str>,
+        vars: impl Serialize,
+    ) -> Result<RenderedEmail> {
+        let ctx = Self::context(hackathon, vars);
+        let subject = self.render_str("subject.txt", subject, &ctx)?;
+        let text = self.render_str("body.txt", text, &ctx)?;
+        let html = match html {
+            Some(html) => self.render_str("body.html", html, &ctx)?,
+            None => {
+                self.render_builtin("plain.html", &context! { body => &text, ..ctx.clone() })?
+            }
+        };
+
+        Ok(RenderedEmail {
+            subject: subject.trim().to_string(),
+            text,
+            html,
+        })
+    }
+
+    fn context(hackathon: &hackathon::Model, vars: impl Serialize) -> Value {
+        context! {
+            hackathon => HackathonContext::from(hackathon),
+            ..Value::from_serialize(vars)
+        }
+    }
+
+    fn render_builtin(&self, name: &str, ctx: &Value) -> Result<String> {
+        self.env
+            .get_template(name)
+            .and_then(|t| t.render(ctx))
+            .with_context(|| format!("failed to render {name}"))
+    }
+
+    fn render_str(&self, name: &str, source: &str, ctx: &Value) -> Result<String> {
+        self.env
+            .template_from_named_str(name, source)
+            .and_then(|t| t.render(ctx))
+            .with_context(|| format!("failed to render custom {name}"))
+    }
+}
+
+/// Placeholder values for every variable the server passes to `kind`, used to
+/// check an organizer's override before it is saved.
+pub fn sample_vars(kind: &EmailTemplateKind) -> Value {
+    match kind {
+        EmailTemplateKind::ApplicationDecision => context! {
+            first_name => "Ada",
+            accepted => true,
+            status => "accepted",
+        },
+        EmailTemplateKind::TeamInvite => context! {
+            first_name => "Ada",
+            team_name => "Team",
+            inviter_name => "Grace",
+            invite_url => "https://example.com/invite",
+        },
+        EmailTemplateKind::RsvpReminder => context! {
+            first_name => "Ada",
+            rsvp_url => "https://example.com/rsvp",
+            deadline => "January 1",
+        },
+        EmailTemplateKind::JudgingInstructions => context! {
+            first_name => "Ada",
+            judging_url => "https://example.com/judging",
+        },
+    }
+}
+
+fn template_dir(kind: &EmailTemplateKind) -> &'static str {
+    match kind {
+        EmailTemplateKind::ApplicationDecision => "application_decision",
+        EmailTemplateKind::TeamInvite => "team_invite",
+        EmailTemplateKind::RsvpReminder => "rsvp_reminder",
+        EmailTemplateKind::JudgingInstructions => "judging_instructions",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::hackathon;
+
+    #[test]
+    fn builtin_renders_text_and_html() {
+        let templates = Templates::new();
+        let email = templates
+            .render(
+                &EmailTemplateKind::ApplicationDecision,
+                &hackathon(),
+                None,
+                context! { first_name => "Ada", accepted => true },
+            )
+            .unwrap();
+
+        assert_eq!(email.subject, "TartanHacks: your application decision");
+        assert!(email.text.contains("Hi Ada,"));
+        assert!(email.text.contains("February 5, 2027"));
+        assert!(email.html.contains("<strong>TartanHacks</strong>"));
+        assert!(email.html.starts_with("<!DOCTYPE html>"));
+    }
+
+    #[test]
+    fn every_builtin_kind_renders() {
+        use sea_orm::Iterable;
+
+        let templates = Templates::new();
+        for kind in EmailTemplateKind::iter() {
+            templates
+                .render(&kind, &hackathon(), None, sample_vars(&kind))
+                .unwrap_or_else(|e| panic!("{kind:?} failed to render: {e:#}"));
+        }
+    }
+
+    #[test]
+    fn waitlisted_applicants_are_told_so() {
+        let templates = Templates::new();
+        let email = templates
+            .render(
+                &EmailTemplateKind::ApplicationDecision,
+                &hackathon(),
+                None,
+                context! { first_name => "Ada", accepted => false, status => "waitlisted" },
+            )
+            .unwrap();
+
+        assert!(email.text.contains("placed you on our waitlist"));
+        assert!(!email.text.contains("unable to offer"));
+        assert!(email.html.contains("placed you on our waitlist"));
+    }
+
+    #[test]
+    fn html_escapes_variables() {
+        let templates = Templates::new();
+        let email = templates
+            .render(
+                &EmailTemplateKind::ApplicationDecision,
+                &hackathon(),
+                None,
+                context! { first_name => "<script>", accepted => true },
+            )
+            .unwrap();
+
+        assert!(email.html.contains("&lt;script&gt;"));
+        assert!(email.text.contains("<script>"));
+    }
+
+    #[test]
+    fn custom_template_overrides_builtin() {
+        let templates = Templates::new();
+        let custom = email_template::Model {
+            id: "t1".into(),
+            hackathon_id: "h1".into(),
+            kind: EmailTemplateKind::ApplicationDecision,
+            subject: "Welcome to {{ hackathon.name }}".into(),
+            text_body: "See you there, {{ first_name }}!".into(),
+            html_body:
+                "{% extends \"layout.html\" %}{% block content %}Hi {{ first_name }}{% endblock %}"
+                    .into(),
+        };
+
+        let email = templates
+            .render(
+                &EmailTemplateKind::ApplicationDecision,
+                &hackathon(),
+                Some(&custom),
+                context! { first_name => "Ada" },
+            )
+            .unwrap();
+
+        assert_eq!(email.subject, "Welcome to TartanHacks");
+        assert_eq!(email.text, "See you there, Ada!");
+        assert!(email.html.contains("Hi Ada"));
+        assert!(
+            email
+                .html
+                .contains("Sent by Terrier on behalf of TartanHacks.")
+        );
+    }
+
+    #[test]
+    fn custom_without_html_wraps_escaped_text() {
+        let templates = Templates::new();
+        let email = templates
+            .render_custom(
+                &hackathon(),
+                "Hello {{ recipient.name }}",
+                "Line one & <two>\nLine three",
+                None,
+                context! { recipient => context! { name => "Ada" } },
+            )
+            .unwrap();
+
+        assert_eq!(email.subject, "Hello Ada");
+        assert!(
+            email
+                .html
+                .contains("Line one &amp; &lt;two&gt;<br>\nLine three")
+        );
+        assert!(
+            email
+                .html
+                .contains("Sent by Terrier on behalf of TartanHacks.")
+        );
+    }
+
+    #[test]
+    fn undefined_variable_is_an_error() {
+        let templates = Templates::new();
+        let result = templates.render(
+            &EmailTemplateKind::TeamInvite,
+            &hackathon(),
+            None,
+            context! { first_name => "Ada" },
+        );
+        assert!(result.is_err());
+    }
+}
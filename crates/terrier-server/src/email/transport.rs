@@ -0,0 +1,73 @@
+use crate::config::EmailTransportConfig;
+use crate::entities::email_outbox;
+use anyhow::{Context, Result};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::{AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+/// Delivery backend for outbox messages. SMTP is used in production; the
+/// file and stdout transports exist so local development never needs a
+/// mail server.
+pub enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    /// Writes each message as an `.eml` file into a directory.
+    File(AsyncFileTransport<Tokio1Executor>),
+    /// Logs the plain-text part of each message.
+    Stdout,
+}
+
+impl Transport {
+    pub fn from_config(config: &EmailTransportConfig) -> Result<Self> {
+        Ok(match config {
+            EmailTransportConfig::Smtp { url } => Self::Smtp(
+                AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+                    .context("invalid SMTP_URL")?
+                    .build(),
+            ),
+            EmailTransportConfig::File { dir } => {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("failed to create {}", dir.display()))?;
+                Self::File(AsyncFileTransport::new(dir))
+            }
+            EmailTransportConfig::Stdout => Self::Stdout,
+        })
+    }
+
+    pub async fn send(&self, from: &Mailbox, email: &email_outbox::Model) -> Result<()> {
+        match self {
+            Self::Smtp(smtp) => {
+                smtp.send(build_message(from, email)?).await?;
+            }
+            Self::File(file) => {
+                file.send(build_message(from, email)?).await?;
+            }
+            Self::Stdout => {
+                tracing::info!(
+                    id = %email.id,
+                    to = %email.recipient,
+                    subject = %email.subject,
+                    body = %email.text_body,
+                    "email delivered to stdout"
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn build_message(from: &Mailbox, email: &email_outbox::Model) -> Result<Message> {
+    let to: Mailbox = email
+        .recipient
+        .parse()
+        .with_context(|| format!("invalid recipient address {:?}", email.recipient))?;
+
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(&email.subject)
+        .message_id(Some(format!("<{}@terrier>", email.id)))
+        .multipart(MultiPart::alternative_plain_html(
+            email.text_body.clone(),
+            email.html_body.clone(),
+        ))
+        .context("failed to build email message")
+}
@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.19
 
+use super::sea_orm_active_enums::ApplicationStatus;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -12,10 +13,21 @@ pub struct Model {
     pub email: String,
     pub application_id: String,
     pub user_id: Option<String>,
+    pub hackathon_id: Option<String>,
+    pub status: ApplicationStatus,
+    pub rsvp_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::hackathon::Entity",
+        from = "Column::HackathonId",
+        to = "super::hackathon::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    Hackathon,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::UserId",
@@ -26,6 +38,12 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::hackathon::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Hackathon.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.19
 
+use super::sea_orm_active_enums::HackathonPhase;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -11,22 +12,76 @@ pub struct Model {
     pub start_date: DateTime,
     pub end_date: DateTime,
     pub location: String,
+    #[sea_orm(unique)]
+    pub slug: String,
+    pub timezone: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub description: Option<String>,
+    pub logo_url: Option<String>,
+    pub primary_color: Option<String>,
+    pub accent_color: Option<String>,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub social_links: Json,
+    pub phase: HackathonPhase,
+    pub phase_locked: bool,
+    pub check_in_at: Option<DateTime>,
+    pub hacking_at: Option<DateTime>,
+    pub judging_at: Option<DateTime>,
+    pub closed_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::applicant::Entity")]
+    Applicant,
+    #[sea_orm(has_many = "super::bulk_message::Entity")]
+    BulkMessage,
+    #[sea_orm(has_many = "super::email_outbox::Entity")]
+    EmailOutbox,
+    #[sea_orm(has_many = "super::email_template::Entity")]
+    EmailTemplate,
     #[sea_orm(has_many = "super::events::Entity")]
     Events,
     #[sea_orm(has_many = "super::judge_assignment::Entity")]
     JudgeAssignment,
+    #[sea_orm(has_many = "super::message_segment::Entity")]
+    MessageSegment,
     #[sea_orm(has_many = "super::prize::Entity")]
     Prize,
     #[sea_orm(has_many = "super::project::Entity")]
     Project,
+    #[sea_orm(has_many = "super::sponsor::Entity")]
+    Sponsor,
     #[sea_orm(has_many = "super::team::Entity")]
     Team,
     #[sea_orm(has_many = "super::track::Entity")]
     Track,
+    #[sea_orm(has_many = "super::user_hackathon_roles::Entity")]
+    UserHackathonRoles,
+}
+
+impl Related<super::applicant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Applicant.def()
+    }
+}
+
+impl Related<super::bulk_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BulkMessage.def()
+    }
+}
+
+impl Related<super::email_outbox::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EmailOutbox.def()
+    }
+}
+
+impl Related<super::email_template::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EmailTemplate.def()
+    }
 }
 
 impl Related<super::events::Entity> for Entity {
@@ -41,6 +96,12 @@ impl Related<super::judge_assignment::Entity> for Entity {
     }
 }
 
+impl Related<super::message_segment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageSegment.def()
+    }
+}
+
 impl Related<super::prize::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Prize.def()
@@ -53,6 +114,12 @@ impl Related<super::project::Entity> for Entity {
     }
 }
 
+impl Related<super::sponsor::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Sponsor.def()
+    }
+}
+
 impl Related<super::team::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Team.def()
@@ -65,4 +132,10 @@ impl Related<super::track::Entity> for Entity {
     }
 }
 
+impl Related<super::user_hackathon_roles::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserHackathonRoles.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -9,6 +9,7 @@ pub struct Model {
     pub id: String,
     pub judge_id: String,
     pub hackathon_id: String,
+    pub track_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -29,6 +30,14 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Judge,
+    #[sea_orm(
+        belongs_to = "super::track::Entity",
+        from = "Column::TrackId",
+        to = "super::track::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    Track,
 }
 
 impl Related<super::hackathon::Entity> for Entity {
@@ -43,4 +52,10 @@ impl Related<super::judge::Entity> for Entity {
     }
 }
 
+impl Related<super::track::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Track.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
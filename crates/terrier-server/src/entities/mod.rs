@@ -3,7 +3,11 @@
 pub mod prelude;
 
 pub mod applicant;
+pub mod bulk_message;
+pub mod bulk_message_recipient;
 pub mod checkins;
+pub mod email_outbox;
+pub mod email_template;
 pub mod evaluation;
 pub mod events;
 pub mod expo_evaluation;
@@ -11,11 +15,14 @@ pub mod hackathon;
 pub mod hacker;
 pub mod judge;
 pub mod judge_assignment;
+pub mod message_segment;
 pub mod prize;
 pub mod project;
+pub mod sea_orm_active_enums;
 pub mod sponsor;
 pub mod sponsor_org;
 pub mod submission;
 pub mod team;
 pub mod track;
 pub mod user;
+pub mod user_hackathon_roles;
@@ -1,7 +1,11 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.19
 
 pub use super::applicant::Entity as Applicant;
+pub use super::bulk_message::Entity as BulkMessage;
+pub use super::bulk_message_recipient::Entity as BulkMessageRecipient;
 pub use super::checkins::Entity as Checkins;
+pub use super::email_outbox::Entity as EmailOutbox;
+pub use super::email_template::Entity as EmailTemplate;
 pub use super::evaluation::Entity as Evaluation;
 pub use super::events::Entity as Events;
 pub use super::expo_evaluation::Entity as ExpoEvaluation;
@@ -9,6 +13,7 @@ pub use super::hackathon::Entity as Hackathon;
 pub use super::hacker::Entity as Hacker;
 pub use super::judge::Entity as Judge;
 pub use super::judge_assignment::Entity as JudgeAssignment;
+pub use super::message_segment::Entity as MessageSegment;
 pub use super::prize::Entity as Prize;
 pub use super::project::Entity as Project;
 pub use super::sponsor::Entity as Sponsor;
@@ -17,3 +22,4 @@ pub use super::submission::Entity as Submission;
 pub use super::team::Entity as Team;
 pub use super::track::Entity as Track;
 pub use super::user::Entity as User;
+pub use super::user_hackathon_roles::Entity as UserHackathonRoles;
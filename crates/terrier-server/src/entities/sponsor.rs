@@ -11,10 +11,19 @@ pub struct Model {
     pub description: String,
     pub user_id: Option<String>,
     pub sponsor_org_id: Option<String>,
+    pub hackathon_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::hackathon::Entity",
+        from = "Column::HackathonId",
+        to = "super::hackathon::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    Hackathon,
     #[sea_orm(
         belongs_to = "super::sponsor_org::Entity",
         from = "Column::SponsorOrgId",
@@ -33,6 +42,12 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::hackathon::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Hackathon.def()
+    }
+}
+
 impl Related<super::sponsor_org::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::SponsorOrg.def()
@@ -21,6 +21,8 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Hackathon,
+    #[sea_orm(has_many = "super::judge_assignment::Entity")]
+    JudgeAssignment,
     #[sea_orm(has_many = "super::submission::Entity")]
     Submission,
 }
@@ -31,6 +33,12 @@ impl Related<super::hackathon::Entity> for Entity {
     }
 }
 
+impl Related<super::judge_assignment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JudgeAssignment.def()
+    }
+}
+
 impl Related<super::submission::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Submission.def()
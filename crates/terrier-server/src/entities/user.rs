@@ -9,19 +9,23 @@ pub struct Model {
     pub id: String,
     pub username: String,
     pub password: String,
-    pub role: String,
+    pub email: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::applicant::Entity")]
     Applicant,
+    #[sea_orm(has_many = "super::bulk_message::Entity")]
+    BulkMessage,
     #[sea_orm(has_many = "super::hacker::Entity")]
     Hacker,
     #[sea_orm(has_many = "super::judge::Entity")]
     Judge,
     #[sea_orm(has_many = "super::sponsor::Entity")]
     Sponsor,
+    #[sea_orm(has_many = "super::user_hackathon_roles::Entity")]
+    UserHackathonRoles,
 }
 
 impl Related<super::applicant::Entity> for Entity {
@@ -30,6 +34,12 @@ impl Related<super::applicant::Entity> for Entity {
     }
 }
 
+impl Related<super::bulk_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BulkMessage.def()
+    }
+}
+
 impl Related<super::hacker::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Hacker.def()
@@ -48,4 +58,10 @@ impl Related<super::sponsor::Entity> for Entity {
     }
 }
 
+impl Related<super::user_hackathon_roles::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserHackathonRoles.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,74 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("authentication required")]
+    Unauthorized,
+    #[error("forbidden")]
+    Forbidden,
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("bad request: {0}")]
+    BadRequest(String),
+    #[error("conflict: {0}")]
+    Conflict(String),
+    #[error(transparent)]
+    Database(#[from] sea_orm::DbErr),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::Forbidden => StatusCode::FORBIDDEN,
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Error::Conflict(_) => StatusCode::CONFLICT,
+            Error::Database(_) | Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        // Server-side failures are logged in full but not echoed to clients.
+        if status.is_server_error() {
+            tracing::error!(error = %self, "request failed");
+            return (status, "internal server error").into_response();
+        }
+
+        (status, self.to_string()).into_response()
+    }
+}
+
+impl From<slac::PathParamError> for Error {
+    fn from(e: slac::PathParamError) -> Self {
+        Error::BadRequest(e.to_string())
+    }
+}
+
+/// Lets policies combined with `error = Error` report the most useful
+/// rejection: a concrete refusal over a missing login, and any server-side
+/// failure over both.
+impl slac::Priority for Error {
+    fn priority(&self) -> u8 {
+        match self {
+            Error::Unauthorized => 0,
+            Error::Database(_) | Error::Internal(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Only a missing login or a refusal may be negated by `slac::Not`; a
+/// missing resource or failed lookup must not grant access.
+impl slac::Denial for Error {
+    fn is_denial(&self) -> bool {
+        matches!(self, Error::Unauthorized | Error::Forbidden)
+    }
+}
+
+impl slac::CombineErrors for Error {
+    fn combine(errors: Vec<Self>) -> Self {
+        slac::most_specific(errors)
+    }
+}
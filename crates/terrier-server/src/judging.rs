@@ -0,0 +1,75 @@
+//! What judges see once judging opens.
+
+use crate::auth::Auth;
+use crate::auth::policies::{InPhase, IsHackathonJudge};
+use crate::entities::{judge, judge_assignment, prelude::*};
+use crate::error::Error;
+use crate::lifecycle::phase;
+use crate::state::AppState;
+use axum::Json;
+use axum::extract::State;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::Serialize;
+use slac::{All, secure};
+use std::sync::Arc;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+
+/// A judge of the hackathon, while it is in the judging phase.
+pub type JudgingJudge = All<IsHackathonJudge, InPhase<phase::Judging>>;
+
+pub fn router() -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::new().routes(secure!(JudgingJudge; list_assignments))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Assignment {
+    /// `None` when the judge is assigned to the hackathon as a whole.
+    pub track_id: Option<String>,
+    pub track_name: Option<String>,
+}
+
+/// The caller's judging assignments in this hackathon.
+#[utoipa::path(
+    get,
+    path = "/hackathons/{hackathon_id}/judging/assignments",
+    tag = "judging",
+    params(("hackathon_id" = String, Path)),
+    responses((status = OK, body = Vec<Assignment>)),
+)]
+pub async fn list_assignments(
+    Auth {
+        data: ((user, hackathon), _),
+        ..
+    }: Auth<JudgingJudge>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Assignment>>, Error> {
+    let assignments = JudgeAssignment::find()
+        .inner_join(Judge)
+        .filter(judge::Column::UserId.eq(&user.id))
+        .filter(judge_assignment::Column::HackathonId.eq(&hackathon.id))
+        .find_also_related(Track)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|(a, track)| Assignment {
+            track_id: a.track_id,
+            track_name: track.map(|t| t.name),
+        })
+        .collect();
+    Ok(Json(assignments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slac::openapi::DescribePolicy;
+
+    #[test]
+    fn documents_the_judge_role_and_the_phase_rejection() {
+        let security = JudgingJudge::security();
+        assert_eq!(security.requirements.len(), 1);
+        assert!(security.requirements[0][crate::auth::OIDC_SCHEME].contains("judge"));
+        assert_eq!(security.rejections, [401, 403, 404, 409].into());
+    }
+}
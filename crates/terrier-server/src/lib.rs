@@ -0,0 +1,106 @@
+pub mod applications;
+pub mod auth;
+pub mod branding;
+pub mod config;
+pub mod email;
+pub mod entities;
+pub mod error;
+pub mod judging;
+pub mod lifecycle;
+pub mod messaging;
+pub mod roles;
+pub mod state;
+#[cfg(test)]
+mod testing;
+
+use axum::Router;
+use slac::inventory::Inventory;
+use state::AppState;
+use std::sync::Arc;
+use tower_http::trace::TraceLayer;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_swagger_ui::SwaggerUi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Terrier API",
+        description = "Hackathon management platform",
+        license(name = "AGPL-3.0-or-later"),
+    ),
+    modifiers(&OidcScheme),
+)]
+struct ApiDoc;
+
+/// Registers the bearer scheme that policy-derived security requirements
+/// refer to; see [`slac::openapi`].
+struct OidcScheme;
+
+impl Modify for OidcScheme {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            auth::OIDC_SCHEME,
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
+#[utoipa::path(get, path = "/health", responses((status = OK, body = str)))]
+async fn health() -> &'static str {
+    "ok"
+}
+
+/// Path prefixes under which every route must be guarded by a policy or
+/// declared public; enforced against [`route_inventory`].
+pub const PROTECTED_PREFIXES: &[&str] = &["/hackathons"];
+
+fn api_router() -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .routes(slac::openapi::public(utoipa_axum::routes!(health)))
+        .merge(applications::router())
+        .merge(branding::router())
+        .merge(email::overrides::router())
+        .merge(judging::router())
+        .merge(lifecycle::router())
+        .merge(messaging::router())
+        .merge(roles::router())
+}
+
+/// Every documented endpoint and the policy guarding it.
+pub fn route_inventory() -> Inventory {
+    let (_, api) = api_router().split_for_parts();
+    Inventory::from_openapi(&api)
+}
+
+pub fn app(state: Arc<AppState>) -> Router {
+    let (router, api) = api_router().with_state(state.clone()).split_for_parts();
+
+    router
+        .merge(SwaggerUi::new("/swagger-ui").url("/openapi.json", api))
+        .layer(slac::ObserverLayer::new(auth::AuditLog))
+        .layer(axum::middleware::from_fn_with_state(
+            state,
+            auth::oidc::authenticate,
+        ))
+        .layer(TraceLayer::new_for_http())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protected_routes_are_guarded() {
+        if let Err(unguarded) = route_inventory().check(PROTECTED_PREFIXES) {
+            panic!("{unguarded}");
+        }
+    }
+}
@@ -0,0 +1,394 @@
+//! Hackathon lifecycle phases.
+//!
+//! A hackathon moves forward through [`HackathonPhase`]s, either
+//! automatically when a configured start time passes (see [`phase_task`]) or
+//! by an organizer override, which also pins the phase until it is released.
+//! Handlers that only make sense in one phase guard on
+//! [`InPhase`](crate::auth::policies::InPhase) with one of the markers in
+//! [`phase`].
+
+use crate::auth::{Auth, policies::IsHackathonOrganizer};
+use crate::entities::{hackathon, prelude::*, sea_orm_active_enums::HackathonPhase};
+use crate::error::Error;
+use crate::state::AppState;
+use axum::Json;
+use axum::extract::State;
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter, Set,
+};
+use serde::{Deserialize, Serialize};
+use slac::secure;
+use std::sync::Arc;
+use std::time::Duration;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+
+const PHASE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Type-level names for each phase, for use as `InPhase<phase::Judging>`.
+pub mod phase {
+    use crate::entities::sea_orm_active_enums::HackathonPhase;
+
+    pub trait Phase: Send + Sync + 'static {
+        const PHASE: HackathonPhase;
+    }
+
+    macro_rules! marker {
+        ($($name:ident),+) => {$(
+            pub struct $name;
+
+            impl Phase for $name {
+                const PHASE: HackathonPhase = HackathonPhase::$name;
+            }
+        )+};
+    }
+
+    marker!(Applications, CheckIn, Hacking, Judging, Closed);
+}
+
+pub fn router() -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::new()
+        .routes(secure!(IsHackathonOrganizer; get_lifecycle))
+        .routes(secure!(IsHackathonOrganizer; update_schedule))
+        .routes(secure!(IsHackathonOrganizer; override_phase, release_override))
+}
+
+/// When the hackathon enters each phase after applications, as wall-clock
+/// times in the hackathon's timezone. Unset entries are only reached by a
+/// manual override.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Schedule {
+    pub check_in_at: Option<NaiveDateTime>,
+    pub hacking_at: Option<NaiveDateTime>,
+    pub judging_at: Option<NaiveDateTime>,
+    pub closed_at: Option<NaiveDateTime>,
+}
+
+impl Schedule {
+    pub fn of(h: &hackathon::Model) -> Self {
+        Self {
+            check_in_at: h.check_in_at,
+            hacking_at: h.hacking_at,
+            judging_at: h.judging_at,
+            closed_at: h.closed_at,
+        }
+    }
+
+    fn transitions(&self) -> [(HackathonPhase, Option<NaiveDateTime>); 4] {
+        [
+            (HackathonPhase::CheckIn, self.check_in_at),
+            (HackathonPhase::Hacking, self.hacking_at),
+            (HackathonPhase::Judging, self.judging_at),
+            (HackathonPhase::Closed, self.closed_at),
+        ]
+    }
+
+    /// The latest phase whose start time is at or before `now`, in the
+    /// hackathon's local time.
+    pub fn phase_at(&self, now: NaiveDateTime) -> HackathonPhase {
+        self.transitions()
+            .into_iter()
+            .filter(|(_, at)| at.is_some_and(|at| at <= now))
+            .map(|(phase, _)| phase)
+            .max()
+            .unwrap_or(HackathonPhase::Applications)
+    }
+
+    /// Set times must not go backwards from one phase to the next.
+    fn validate(&self) -> Result<(), String> {
+        let set: Vec<_> = self
+            .transitions()
+            .into_iter()
+            .filter_map(|(phase, at)| at.map(|at| (phase, at)))
+            .collect();
+        for pair in set.windows(2) {
+            let [(earlier, a), (later, b)] = pair else {
+                unreachable!()
+            };
+            if b < a {
+                return Err(format!(
+                    "{later:?} cannot start before {earlier:?} ({b} < {a})"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The phase `h` is in at `now` (UTC). Scheduled transitions only ever move
+/// a hackathon forward, and a locked phase ignores the schedule entirely.
+pub fn effective_phase(h: &hackathon::Model, now: NaiveDateTime) -> HackathonPhase {
+    if h.phase_locked {
+        h.phase
+    } else {
+        h.phase.max(Schedule::of(h).phase_at(local_time(h, now)))
+    }
+}
+
+/// `now` (UTC) on the wall clock of `h`'s timezone, which its schedule is
+/// written in. `update_branding` only stores known zones; anything else is
+/// read as UTC.
+fn local_time(h: &hackathon::Model, now: NaiveDateTime) -> NaiveDateTime {
+    let tz = h.timezone.parse::<Tz>().unwrap_or(Tz::UTC);
+    tz.from_utc_datetime(&now).naive_local()
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Lifecycle {
+    pub phase: HackathonPhase,
+    /// Whether an organizer override is holding the phase in place.
+    pub locked: bool,
+    pub schedule: Schedule,
+}
+
+impl From<&hackathon::Model> for Lifecycle {
+    fn from(h: &hackathon::Model) -> Self {
+        Self {
+            phase: effective_phase(h, Utc::now().naive_utc()),
+            locked: h.phase_locked,
+            schedule: Schedule::of(h),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OverridePhase {
+    pub phase: HackathonPhase,
+}
+
+#[utoipa::path(
+    get,
+    path = "/hackathons/{hackathon_id}/lifecycle",
+    tag = "lifecycle",
+    params(("hackathon_id" = String, Path)),
+    responses((status = OK, body = Lifecycle)),
+)]
+pub async fn get_lifecycle(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+) -> Json<Lifecycle> {
+    Json(Lifecycle::from(&hackathon))
+}
+
+#[utoipa::path(
+    put,
+    path = "/hackathons/{hackathon_id}/lifecycle/schedule",
+    tag = "lifecycle",
+    params(("hackathon_id" = String, Path)),
+    request_body = Schedule,
+    responses((status = OK, body = Lifecycle)),
+)]
+pub async fn update_schedule(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Json(schedule): Json<Schedule>,
+) -> Result<Json<Lifecycle>, Error> {
+    schedule.validate().map_err(Error::BadRequest)?;
+
+    let mut active = hackathon.into_active_model();
+    active.check_in_at = Set(schedule.check_in_at);
+    active.hacking_at = Set(schedule.hacking_at);
+    active.judging_at = Set(schedule.judging_at);
+    active.closed_at = Set(schedule.closed_at);
+    let hackathon = active.update(&state.db).await?;
+
+    Ok(Json(Lifecycle::from(&hackathon)))
+}
+
+/// Moves the hackathon to `phase`, forwards or backwards, and holds it there
+/// until the override is released.
+#[utoipa::path(
+    put,
+    path = "/hackathons/{hackathon_id}/lifecycle/override",
+    tag = "lifecycle",
+    params(("hackathon_id" = String, Path)),
+    request_body = OverridePhase,
+    responses((status = OK, body = Lifecycle)),
+)]
+pub async fn override_phase(
+    Auth {
+        data: (user, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<OverridePhase>,
+) -> Result<Json<Lifecycle>, Error> {
+    let mut active = hackathon.into_active_model();
+    active.phase = Set(body.phase);
+    active.phase_locked = Set(true);
+    let hackathon = active.update(&state.db).await?;
+
+    tracing::info!(hackathon = %hackathon.id, user = %user.id, phase = ?body.phase, "phase overridden");
+    Ok(Json(Lifecycle::from(&hackathon)))
+}
+
+/// Hands control back to the schedule. If a scheduled transition has
+/// already passed, the hackathon moves forward to it.
+#[utoipa::path(
+    delete,
+    path = "/hackathons/{hackathon_id}/lifecycle/override",
+    tag = "lifecycle",
+    params(("hackathon_id" = String, Path)),
+    responses((status = OK, body = Lifecycle)),
+)]
+pub async fn release_override(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Lifecycle>, Error> {
+    let mut hackathon = hackathon;
+    hackathon.phase_locked = false;
+    let phase = effective_phase(&hackathon, Utc::now().naive_utc());
+
+    let mut active = hackathon.into_active_model();
+    active.phase = Set(phase);
+    active.phase_locked = Set(false);
+    let hackathon = active.update(&state.db).await?;
+
+    Ok(Json(Lifecycle::from(&hackathon)))
+}
+
+/// Persists any scheduled transitions that have come due. Returns how many
+/// hackathons moved.
+pub async fn advance_phases<C: ConnectionTrait>(db: &C, now: NaiveDateTime) -> Result<u64, DbErr> {
+    let candidates = Hackathon::find()
+        .filter(hackathon::Column::PhaseLocked.eq(false))
+        .filter(hackathon::Column::Phase.ne(HackathonPhase::Closed))
+        .all(db)
+        .await?;
+
+    let mut advanced = 0;
+    for h in candidates {
+        let phase = effective_phase(&h, now);
+        if phase == h.phase {
+            continue;
+        }
+
+        tracing::info!(hackathon = %h.id, from = ?h.phase, to = ?phase, "advancing phase");
+        // Conditional on the phase we read, so a concurrent override wins.
+        let result = Hackathon::update_many()
+            .col_expr(hackathon::Column::Phase, phase.into())
+            .filter(hackathon::Column::Id.eq(&h.id))
+            .filter(hackathon::Column::Phase.eq(h.phase))
+            .filter(hackathon::Column::PhaseLocked.eq(false))
+            .exec(db)
+            .await?;
+        advanced += result.rows_affected;
+    }
+    Ok(advanced)
+}
+
+/// Applies scheduled phase transitions every 30 seconds.
+pub async fn phase_task(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(PHASE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = advance_phases(&state.db, Utc::now().naive_utc()).await {
+            tracing::error!(error = %e, "failed to advance hackathon phases");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2027, 2, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn schedule() -> Schedule {
+        Schedule {
+            check_in_at: Some(at(5, 12)),
+            hacking_at: Some(at(5, 18)),
+            judging_at: Some(at(7, 9)),
+            closed_at: Some(at(7, 17)),
+        }
+    }
+
+    #[test]
+    fn phase_follows_schedule() {
+        let s = schedule();
+        assert_eq!(s.phase_at(at(1, 0)), HackathonPhase::Applications);
+        assert_eq!(s.phase_at(at(5, 12)), HackathonPhase::CheckIn);
+        assert_eq!(s.phase_at(at(6, 0)), HackathonPhase::Hacking);
+        assert_eq!(s.phase_at(at(7, 10)), HackathonPhase::Judging);
+        assert_eq!(s.phase_at(at(8, 0)), HackathonPhase::Closed);
+    }
+
+    #[test]
+    fn unset_transitions_are_skipped() {
+        let s = Schedule {
+            check_in_at: None,
+            judging_at: None,
+            ..schedule()
+        };
+        assert_eq!(s.phase_at(at(5, 15)), HackathonPhase::Applications);
+        assert_eq!(s.phase_at(at(7, 10)), HackathonPhase::Hacking);
+    }
+
+    #[test]
+    fn effective_phase_never_goes_backwards_and_respects_lock() {
+        let mut h = crate::testing::hackathon();
+        h.hacking_at = Some(at(5, 18));
+        h.judging_at = Some(at(7, 9));
+
+        assert_eq!(effective_phase(&h, at(6, 0)), HackathonPhase::Hacking);
+
+        // An organizer jumped ahead to judging early and released the lock.
+        h.phase = HackathonPhase::Judging;
+        assert_eq!(effective_phase(&h, at(6, 0)), HackathonPhase::Judging);
+
+        // While locked, passing scheduled times changes nothing.
+        h.phase = HackathonPhase::CheckIn;
+        h.phase_locked = true;
+        assert_eq!(effective_phase(&h, at(7, 10)), HackathonPhase::CheckIn);
+    }
+
+    #[test]
+    fn schedule_is_read_in_the_hackathon_timezone() {
+        let mut h = crate::testing::hackathon();
+        // 18:00 in Pittsburgh is 23:00 UTC.
+        h.hacking_at = Some(at(5, 18));
+
+        assert_eq!(effective_phase(&h, at(5, 18)), HackathonPhase::Applications);
+        assert_eq!(effective_phase(&h, at(5, 23)), HackathonPhase::Hacking);
+
+        h.timezone = "UTC".into();
+        assert_eq!(effective_phase(&h, at(5, 18)), HackathonPhase::Hacking);
+    }
+
+    #[test]
+    fn validate_rejects_out_of_order_times() {
+        assert!(schedule().validate().is_ok());
+        assert!(Schedule::default().validate().is_ok());
+
+        let s = Schedule {
+            judging_at: Some(at(4, 0)),
+            ..schedule()
+        };
+        assert!(s.validate().is_err());
+
+        // Gaps are fine as long as the set times are ordered.
+        let s = Schedule {
+            check_in_at: None,
+            hacking_at: None,
+            ..schedule()
+        };
+        assert!(s.validate().is_ok());
+    }
+}
@@ -1,25 +1,17 @@
+use std::sync::Arc;
+use terrier_server::config::Config;
+use terrier_server::state::AppState;
 use tokio::net::TcpListener;
-use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use utoipa::OpenApi;
-use utoipa_axum::router::OpenApiRouter;
-use utoipa_swagger_ui::SwaggerUi;
-
-#[derive(OpenApi)]
-#[openapi(info(
-    title = "Terrier API",
-    description = "Hackathon management platform",
-    license(name = "AGPL-3.0-or-later"),
-))]
-struct ApiDoc;
-
-#[utoipa::path(get, path = "/health", responses((status = OK, body = str)))]
-async fn health() -> &'static str {
-    "ok"
-}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // `terrier routes` prints the route inventory for auditors and exits.
+    if std::env::args().nth(1).as_deref() == Some("routes") {
+        println!("{:#}", terrier_server::route_inventory().to_json());
+        return Ok(());
+    }
+
     let _ = dotenvy::dotenv();
 
     tracing_subscriber::registry()
@@ -29,17 +21,17 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer().json())
         .init();
 
-    let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".into());
-    let port = std::env::var("PORT").unwrap_or_else(|_| "3000".into());
-    let addr = format!("{host}:{port}");
+    let config = Config::from_env()?;
+    let addr = format!("{}:{}", config.host, config.port);
+    let state = Arc::new(AppState::new(config).await?);
 
-    let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
-        .routes(utoipa_axum::routes!(health))
-        .split_for_parts();
+    tokio::spawn(terrier_server::email::outbox::outbox_task(state.clone()));
+    tokio::spawn(terrier_server::messaging::messages::bulk_message_task(
+        state.clone(),
+    ));
+    tokio::spawn(terrier_server::lifecycle::phase_task(state.clone()));
 
-    let mut app = router
-        .merge(SwaggerUi::new("/swagger-ui").url("/openapi.json", api))
-        .layer(TraceLayer::new_for_http());
+    let mut app = terrier_server::app(state);
 
     if std::path::Path::new("assets").exists() {
         app = app.fallback_service(
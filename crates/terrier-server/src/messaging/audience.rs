@@ -0,0 +1,245 @@
+use crate::entities::{
+    applicant, checkins, hacker, judge, judge_assignment,
+    prelude::*,
+    sea_orm_active_enums::{ApplicationStatus, RecipientKind},
+    sponsor, team, user,
+};
+use sea_orm::sea_query::Query;
+use sea_orm::{ActiveEnum, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+/// Who a message goes to: the union of every filter in `include`, with
+/// people matched by more than one filter receiving a single copy.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Audience {
+    pub include: Vec<AudienceFilter>,
+}
+
+/// A set of people within the hackathon. Unset fields do not filter.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AudienceFilter {
+    Applicants {
+        status: Option<ApplicationStatus>,
+        /// Whether the applicant has confirmed their spot.
+        rsvped: Option<bool>,
+    },
+    /// Hackers on a team in this hackathon.
+    Hackers {
+        checked_in: Option<bool>,
+    },
+    /// Judges assigned to this hackathon, optionally only those assigned to
+    /// `track_id`.
+    Judges {
+        track_id: Option<String>,
+    },
+    Sponsors {
+        sponsor_org_id: Option<String>,
+    },
+}
+
+/// One resolved person. `email` is `None` when the underlying record has no
+/// address (judges and sponsors whose user has never logged in).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Recipient {
+    pub kind: RecipientKind,
+    pub entity_id: String,
+    pub email: Option<String>,
+    pub name: String,
+}
+
+impl Audience {
+    /// Resolves the audience against the current state of `hackathon_id`.
+    pub async fn resolve<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        hackathon_id: &str,
+    ) -> Result<Vec<Recipient>, DbErr> {
+        let mut resolved = Vec::new();
+        for filter in &self.include {
+            resolved.extend(filter.resolve(db, hackathon_id).await?);
+        }
+        Ok(dedup(resolved))
+    }
+}
+
+impl AudienceFilter {
+    async fn resolve<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        hackathon_id: &str,
+    ) -> Result<Vec<Recipient>, DbErr> {
+        match self {
+            Self::Applicants { status, rsvped } => {
+                let mut query =
+                    Applicant::find().filter(applicant::Column::HackathonId.eq(hackathon_id));
+                if let Some(status) = status {
+                    query = query.filter(applicant::Column::Status.eq(status.clone()));
+                }
+                query = match rsvped {
+                    Some(true) => query.filter(applicant::Column::RsvpAt.is_not_null()),
+                    Some(false) => query.filter(applicant::Column::RsvpAt.is_null()),
+                    None => query,
+                };
+
+                Ok(query
+                    .all(db)
+                    .await?
+                    .into_iter()
+                    .map(|a| Recipient {
+                        kind: RecipientKind::Applicant,
+                        entity_id: a.id,
+                        email: Some(a.email),
+                        name: a.first_name,
+                    })
+                    .collect())
+            }
+            Self::Hackers { checked_in } => {
+                let mut query = Hacker::find()
+                    .inner_join(Team)
+                    .filter(team::Column::HackathonId.eq(hackathon_id));
+                let checked_in_ids = Query::select()
+                    .column(checkins::Column::HackerId)
+                    .from(Checkins)
+                    .to_owned();
+                query = match checked_in {
+                    Some(true) => query.filter(hacker::Column::Id.in_subquery(checked_in_ids)),
+                    Some(false) => query.filter(hacker::Column::Id.not_in_subquery(checked_in_ids)),
+                    None => query,
+                };
+
+                Ok(query
+                    .all(db)
+                    .await?
+                    .into_iter()
+                    .map(|h| Recipient {
+                        kind: RecipientKind::Hacker,
+                        entity_id: h.id,
+                        email: Some(h.email),
+                        name: h.first_name,
+                    })
+                    .collect())
+            }
+            Self::Judges { track_id } => {
+                let mut assigned = Query::select()
+                    .column(judge_assignment::Column::JudgeId)
+                    .from(JudgeAssignment)
+                    .and_where(judge_assignment::Column::HackathonId.eq(hackathon_id))
+                    .to_owned();
+                if let Some(track_id) = track_id {
+                    assigned.and_where(judge_assignment::Column::TrackId.eq(track_id));
+                }
+                let query = Judge::find().filter(judge::Column::Id.in_subquery(assigned));
+
+                Ok(query
+                    .find_also_related(User)
+                    .all(db)
+                    .await?
+                    .into_iter()
+                    .map(|(j, u)| Recipient {
+                        kind: RecipientKind::Judge,
+                        entity_id: j.id,
+                        email: u.as_ref().and_then(|u| u.email.clone()),
+                        name: u.map(|u| u.username).unwrap_or_default(),
+                    })
+                    .collect())
+            }
+            Self::Sponsors { sponsor_org_id } => {
+                let mut query =
+                    Sponsor::find().filter(sponsor::Column::HackathonId.eq(hackathon_id));
+                if let Some(org) = sponsor_org_id {
+                    query = query.filter(sponsor::Column::SponsorOrgId.eq(org));
+                }
+
+                Ok(query
+                    .find_also_related(User)
+                    .all(db)
+                    .await?
+                    .into_iter()
+                    .map(|(s, u)| Recipient {
+                        kind: RecipientKind::Sponsor,
+                        entity_id: s.id,
+                        email: u.and_then(|u: user::Model| u.email),
+                        name: s.name,
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Keeps the first occurrence of each email address (case-insensitively)
+/// and of each record without one.
+fn dedup(recipients: Vec<Recipient>) -> Vec<Recipient> {
+    let mut seen_emails = HashSet::new();
+    let mut seen_records = HashSet::new();
+    recipients
+        .into_iter()
+        .filter(|r| match &r.email {
+            Some(email) => seen_emails.insert(email.to_lowercase()),
+            None => seen_records.insert((r.kind.to_value(), r.entity_id.clone())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipient(kind: RecipientKind, id: &str, email: Option<&str>) -> Recipient {
+        Recipient {
+            kind,
+            entity_id: id.into(),
+            email: email.map(Into::into),
+            name: id.into(),
+        }
+    }
+
+    #[test]
+    fn dedup_merges_same_email_across_kinds() {
+        let out = dedup(vec![
+            recipient(RecipientKind::Applicant, "a1", Some("ada@example.edu")),
+            recipient(RecipientKind::Hacker, "h1", Some("ADA@example.edu")),
+            recipient(RecipientKind::Hacker, "h2", Some("grace@example.edu")),
+        ]);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].entity_id, "a1");
+        assert_eq!(out[1].entity_id, "h2");
+    }
+
+    #[test]
+    fn dedup_keeps_distinct_records_without_email() {
+        let out = dedup(vec![
+            recipient(RecipientKind::Judge, "j1", None),
+            recipient(RecipientKind::Judge, "j1", None),
+            recipient(RecipientKind::Sponsor, "j1", None),
+        ]);
+        assert_eq!(out.len(), 2);
+    }
+
+    #[test]
+    fn audience_json_shape() {
+        let audience: Audience = serde_json::from_str(
+            r#"{"include": [
+                {"kind": "applicants", "status": "accepted", "rsvped": false},
+                {"kind": "judges", "track_id": "t1"}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            audience.include,
+            vec![
+                AudienceFilter::Applicants {
+                    status: Some(ApplicationStatus::Accepted),
+                    rsvped: Some(false),
+                },
+                AudienceFilter::Judges {
+                    track_id: Some("t1".into()),
+                },
+            ]
+        );
+    }
+}
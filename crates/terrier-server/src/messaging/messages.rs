@@ -0,0 +1,471 @@
+use crate::auth::{Auth, policies::IsHackathonOrganizer};
+use crate::email::outbox;
+use crate::entities::{
+    bulk_message, bulk_message_recipient, email_outbox,
+    prelude::*,
+    sea_orm_active_enums::{BulkMessageStatus, EmailStatus, RecipientKind},
+};
+use crate::error::Error;
+use crate::messaging::audience::{Audience, Recipient};
+use crate::messaging::segments::{Segment, find_segment};
+use crate::state::AppState;
+use anyhow::Context;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use chrono::{NaiveDateTime, Utc};
+use minijinja::context;
+use sea_orm::sea_query::{Expr, LockBehavior, LockType};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(15);
+/// Dispatch attempts after which a message is marked `failed`, e.g. when its
+/// templates do not render, and no longer retried.
+pub const MAX_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Message {
+    pub id: String,
+    pub segment_id: Option<String>,
+    pub created_by: String,
+    pub audience: Audience,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+    pub status: BulkMessageStatus,
+    pub scheduled_at: NaiveDateTime,
+    pub attempts: i32,
+    /// Why the last dispatch attempt failed, for messages still being
+    /// retried or marked `failed`.
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub sent_at: Option<NaiveDateTime>,
+}
+
+impl TryFrom<bulk_message::Model> for Message {
+    type Error = Error;
+
+    fn try_from(m: bulk_message::Model) -> Result<Self, Error> {
+        Ok(Self {
+            audience: serde_json::from_value(m.audience)
+                .map_err(|e| anyhow::anyhow!("stored message {} is malformed: {e}", m.id))?,
+            id: m.id,
+            segment_id: m.segment_id,
+            created_by: m.created_by,
+            subject: m.subject,
+            text_body: m.text_body,
+            html_body: m.html_body,
+            status: m.status,
+            scheduled_at: m.scheduled_at,
+            attempts: m.attempts,
+            last_error: m.last_error,
+            created_at: m.created_at,
+            sent_at: m.sent_at,
+        })
+    }
+}
+
+/// Exactly one of `segment_id` and `audience` must be given. Subject and
+/// bodies are templates with `hackathon.*` and `recipient.{name,email,kind}`
+/// in scope. Without `scheduled_at` the message goes out on the next
+/// dispatcher tick.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateMessage {
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+    pub segment_id: Option<String>,
+    pub audience: Option<Audience>,
+    pub scheduled_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Sent,
+    Failed,
+    /// The recipient had no email address, so nothing was queued.
+    NoAddress,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageRecipient {
+    pub kind: RecipientKind,
+    pub entity_id: String,
+    pub email: Option<String>,
+    pub delivery: DeliveryStatus,
+    pub last_error: Option<String>,
+}
+
+fn recipient_vars(r: &Recipient) -> minijinja::Value {
+    context! {
+        recipient => context! { name => &r.name, email => &r.email, kind => &r.kind },
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/hackathons/{hackathon_id}/messages",
+    tag = "messaging",
+    params(("hackathon_id" = String, Path)),
+    responses((status = OK, body = Vec<Message>)),
+)]
+pub async fn list_messages(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Message>>, Error> {
+    let messages = BulkMessage::find()
+        .filter(bulk_message::Column::HackathonId.eq(&hackathon.id))
+        .order_by_desc(bulk_message::Column::ScheduledAt)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(Message::try_from)
+        .collect::<Result<_, _>>()?;
+    Ok(Json(messages))
+}
+
+#[utoipa::path(
+    post,
+    path = "/hackathons/{hackathon_id}/messages",
+    tag = "messaging",
+    params(("hackathon_id" = String, Path)),
+    request_body = CreateMessage,
+    responses((status = CREATED, body = Message)),
+)]
+pub async fn create_message(
+    Auth {
+        data: (user, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<CreateMessage>,
+) -> Result<(StatusCode, Json<Message>), Error> {
+    let audience = match (&body.segment_id, body.audience) {
+        (Some(segment_id), None) => {
+            let segment: Segment = find_segment(&state, &hackathon.id, segment_id)
+                .await?
+                .try_into()?;
+            segment.audience
+        }
+        (None, Some(audience)) => audience,
+        _ => {
+            return Err(Error::BadRequest(
+                "exactly one of segment_id and audience is required".into(),
+            ));
+        }
+    };
+
+    // Render once against a placeholder recipient so template mistakes are
+    // reported now rather than when the dispatcher runs.
+    let sample = Recipient {
+        kind: RecipientKind::Hacker,
+        entity_id: String::new(),
+        email: Some("recipient@example.com".into()),
+        name: "Recipient".into(),
+    };
+    state
+        .mailer
+        .templates()
+        .render_custom(
+            &hackathon,
+            &body.subject,
+            &body.text_body,
+            body.html_body.as_deref(),
+            recipient_vars(&sample),
+        )
+        .map_err(|e| Error::BadRequest(format!("{e:#}")))?;
+
+    let now = Utc::now().naive_utc();
+    let scheduled_at = body.scheduled_at.unwrap_or(now);
+    let message = bulk_message::ActiveModel {
+        id: Set(uuid::Uuid::new_v4().to_string()),
+        hackathon_id: Set(hackathon.id),
+        segment_id: Set(body.segment_id),
+        created_by: Set(user.id),
+        audience: Set(serde_json::to_value(&audience).map_err(anyhow::Error::from)?),
+        subject: Set(body.subject),
+        text_body: Set(body.text_body),
+        html_body: Set(body.html_body),
+        status: Set(BulkMessageStatus::Scheduled),
+        scheduled_at: Set(scheduled_at),
+        attempts: Set(0),
+        last_error: Set(None),
+        next_attempt_at: Set(scheduled_at),
+        created_at: Set(now),
+        sent_at: Set(None),
+    }
+    .insert(&state.db)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(message.try_into()?)))
+}
+
+/// Per-recipient delivery status of a dispatched message.
+#[utoipa::path(
+    get,
+    path = "/hackathons/{hackathon_id}/messages/{message_id}/recipients",
+    tag = "messaging",
+    params(("hackathon_id" = String, Path), ("message_id" = String, Path)),
+    responses((status = OK, body = Vec<MessageRecipient>)),
+)]
+pub async fn list_recipients(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<(String, String)>,
+) -> Result<Json<Vec<MessageRecipient>>, Error> {
+    let (_, message_id) = path;
+    let message = find_message(&state, &hackathon.id, &message_id).await?;
+
+    let recipients = BulkMessageRecipient::find()
+        .filter(bulk_message_recipient::Column::BulkMessageId.eq(&message.id))
+        .find_also_related(EmailOutbox)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|(r, email)| {
+            let (delivery, last_error) = match email {
+                None => (DeliveryStatus::NoAddress, None),
+                Some(email_outbox::Model {
+                    status, last_error, ..
+                }) => (
+                    match status {
+                        EmailStatus::Pending => DeliveryStatus::Pending,
+                        EmailStatus::Sent => DeliveryStatus::Sent,
+                        EmailStatus::Failed => DeliveryStatus::Failed,
+                    },
+                    last_error,
+                ),
+            };
+            MessageRecipient {
+                kind: r.kind,
+                entity_id: r.entity_id,
+                email: r.email,
+                delivery,
+                last_error,
+            }
+        })
+        .collect();
+
+    Ok(Json(recipients))
+}
+
+/// Cancels a message that has not been dispatched yet.
+#[utoipa::path(
+    post,
+    path = "/hackathons/{hackathon_id}/messages/{message_id}/cancel",
+    tag = "messaging",
+    params(("hackathon_id" = String, Path), ("message_id" = String, Path)),
+    responses((status = OK, body = Message), (status = CONFLICT)),
+)]
+pub async fn cancel_message(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<(String, String)>,
+) -> Result<Json<Message>, Error> {
+    let (_, message_id) = path;
+    let message = find_message(&state, &hackathon.id, &message_id).await?;
+
+    // Conditional on still being scheduled, so a cancel that races the
+    // dispatcher cannot overwrite a message that has already gone out.
+    let result = BulkMessage::update_many()
+        .col_expr(
+            bulk_message::Column::Status,
+            Expr::value(BulkMessageStatus::Cancelled),
+        )
+        .filter(bulk_message::Column::Id.eq(&message.id))
+        .filter(bulk_message::Column::Status.eq(BulkMessageStatus::Scheduled))
+        .exec(&state.db)
+        .await?;
+    if result.rows_affected == 0 {
+        return Err(Error::Conflict(format!(
+            "message {message_id} is no longer scheduled"
+        )));
+    }
+
+    let message = find_message(&state, &hackathon.id, &message_id).await?;
+    Ok(Json(message.try_into()?))
+}
+
+async fn find_message(
+    state: &AppState,
+    hackathon_id: &str,
+    message_id: &str,
+) -> Result<bulk_message::Model, Error> {
+    BulkMessage::find_by_id(message_id)
+        .filter(bulk_message::Column::HackathonId.eq(hackathon_id))
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("message {message_id}")))
+}
+
+/// Claims the oldest due message and dispatches it. An attempt that fails
+/// is rolled back and retried with the outbox's backoff, so it does not hold
+/// up later messages, until [`MAX_ATTEMPTS`] mark it `failed`. Returns
+/// `false` when nothing was due.
+pub async fn dispatch_next(state: &AppState) -> anyhow::Result<bool> {
+    let txn = state.db.begin().await?;
+    let now = Utc::now().naive_utc();
+
+    let Some(message) = BulkMessage::find()
+        .filter(bulk_message::Column::Status.eq(BulkMessageStatus::Scheduled))
+        .filter(bulk_message::Column::NextAttemptAt.lte(now))
+        .order_by_asc(bulk_message::Column::NextAttemptAt)
+        .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+        .one(&txn)
+        .await?
+    else {
+        txn.commit().await?;
+        return Ok(false);
+    };
+
+    // A savepoint, so a failed attempt leaves no recipients or emails behind
+    // but is still recorded on the message.
+    let attempt = txn.begin().await?;
+    let result = queue_recipients(state, &attempt, &message).await;
+    let attempts = message.attempts + 1;
+    let id = message.id.clone();
+    let mut active = message.into_active_model();
+    active.attempts = Set(attempts);
+
+    match result {
+        Ok(recipients) => {
+            attempt.commit().await?;
+            active.status = Set(BulkMessageStatus::Sent);
+            active.sent_at = Set(Some(Utc::now().naive_utc()));
+            active.last_error = Set(None);
+            tracing::info!(%id, recipients, "dispatched bulk message");
+        }
+        Err(e) => {
+            attempt.rollback().await?;
+            active.last_error = Set(Some(format!("{e:#}")));
+            match retry_at(attempts, now)? {
+                Some(retry_at) => {
+                    active.next_attempt_at = Set(retry_at);
+                    tracing::warn!(%id, attempts, %retry_at, error = %e, "bulk message dispatch failed, will retry");
+                }
+                None => {
+                    active.status = Set(BulkMessageStatus::Failed);
+                    tracing::error!(%id, attempts, error = %e, "bulk message permanently failed");
+                }
+            }
+        }
+    }
+
+    active.update(&txn).await?;
+    txn.commit().await?;
+    Ok(true)
+}
+
+/// When to retry a message that has failed `attempts` times, or `None` once
+/// it has used up [`MAX_ATTEMPTS`].
+fn retry_at(attempts: i32, now: NaiveDateTime) -> anyhow::Result<Option<NaiveDateTime>> {
+    if attempts >= MAX_ATTEMPTS {
+        return Ok(None);
+    }
+    Ok(Some(
+        now + chrono::TimeDelta::from_std(outbox::backoff(attempts))?,
+    ))
+}
+
+/// Resolves the message's audience and queues one outbox email per
+/// recipient. Returns the number of recipients.
+async fn queue_recipients<C: ConnectionTrait>(
+    state: &AppState,
+    db: &C,
+    message: &bulk_message::Model,
+) -> anyhow::Result<usize> {
+    let hackathon = Hackathon::find_by_id(&message.hackathon_id)
+        .one(db)
+        .await?
+        .context("bulk message references a missing hackathon")?;
+    let audience: Audience = serde_json::from_value(message.audience.clone())?;
+    let recipients = audience.resolve(db, &hackathon.id).await?;
+    let templates = state.mailer.templates();
+
+    for r in &recipients {
+        let email_outbox_id = match &r.email {
+            Some(address) => {
+                let rendered = templates.render_custom(
+                    &hackathon,
+                    &message.subject,
+                    &message.text_body,
+                    message.html_body.as_deref(),
+                    recipient_vars(r),
+                )?;
+                Some(
+                    outbox::insert(db, Some(hackathon.id.clone()), address.clone(), rendered)
+                        .await?,
+                )
+            }
+            None => None,
+        };
+
+        bulk_message_recipient::ActiveModel {
+            id: Set(uuid::Uuid::new_v4().to_string()),
+            bulk_message_id: Set(message.id.clone()),
+            kind: Set(r.kind.clone()),
+            entity_id: Set(r.entity_id.clone()),
+            email: Set(r.email.clone()),
+            email_outbox_id: Set(email_outbox_id),
+        }
+        .insert(db)
+        .await?;
+    }
+
+    Ok(recipients.len())
+}
+
+/// Dispatches due bulk messages every 15 seconds.
+pub async fn bulk_message_task(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(DISPATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        loop {
+            match dispatch_next(&state).await {
+                Ok(true) => continue,
+                Ok(false) => break,
+                Err(e) => {
+                    tracing::error!(error = %e, "failed to dispatch bulk message");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_with_backoff_until_max_attempts() {
+        let now = Utc::now().naive_utc();
+        assert_eq!(
+            retry_at(1, now).unwrap(),
+            Some(now + chrono::TimeDelta::seconds(30))
+        );
+        assert_eq!(
+            retry_at(MAX_ATTEMPTS - 1, now).unwrap(),
+            Some(now + chrono::TimeDelta::from_std(outbox::backoff(MAX_ATTEMPTS - 1)).unwrap())
+        );
+        assert_eq!(retry_at(MAX_ATTEMPTS, now).unwrap(), None);
+    }
+}
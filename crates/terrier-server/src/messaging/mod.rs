@@ -0,0 +1,81 @@
+//! Organizer bulk messaging.
+//!
+//! Organizers describe an [`audience::Audience`] (optionally saved as a
+//! segment), preview how many people it reaches, and schedule a message to
+//! it. [`messages::bulk_message_task`] resolves the audience when the
+//! message falls due and hands each recipient to the email outbox, whose
+//! rows then carry per-recipient delivery status.
+
+pub mod audience;
+pub mod messages;
+pub mod segments;
+
+use crate::auth::{Auth, policies::IsHackathonOrganizer};
+use crate::error::Error;
+use crate::state::AppState;
+use audience::{Audience, Recipient};
+use axum::Json;
+use axum::extract::State;
+use serde::Serialize;
+use slac::secure;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+
+pub fn router() -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::new()
+        .routes(secure!(IsHackathonOrganizer; preview_audience))
+        .routes(secure!(
+            IsHackathonOrganizer;
+            segments::list_segments,
+            segments::create_segment
+        ))
+        .routes(secure!(IsHackathonOrganizer; segments::delete_segment))
+        .routes(secure!(IsHackathonOrganizer; segments::preview_segment))
+        .routes(secure!(
+            IsHackathonOrganizer;
+            messages::list_messages,
+            messages::create_message
+        ))
+        .routes(secure!(IsHackathonOrganizer; messages::list_recipients))
+        .routes(secure!(IsHackathonOrganizer; messages::cancel_message))
+}
+
+/// How many people an audience currently reaches.
+#[derive(Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct AudiencePreview {
+    /// Unique people matched.
+    pub total: usize,
+    /// Of those, how many have no email address and would be skipped.
+    pub without_email: usize,
+}
+
+impl From<&[Recipient]> for AudiencePreview {
+    fn from(recipients: &[Recipient]) -> Self {
+        Self {
+            total: recipients.len(),
+            without_email: recipients.iter().filter(|r| r.email.is_none()).count(),
+        }
+    }
+}
+
+/// Counts the recipients an ad-hoc audience would reach right now.
+#[utoipa::path(
+    post,
+    path = "/hackathons/{hackathon_id}/audiences/preview",
+    tag = "messaging",
+    params(("hackathon_id" = String, Path)),
+    request_body = Audience,
+    responses((status = OK, body = AudiencePreview)),
+)]
+async fn preview_audience(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Json(audience): Json<Audience>,
+) -> Result<Json<AudiencePreview>, Error> {
+    let recipients = audience.resolve(&state.db, &hackathon.id).await?;
+    Ok(Json(AudiencePreview::from(recipients.as_slice())))
+}
@@ -0,0 +1,161 @@
+use crate::auth::{Auth, policies::IsHackathonOrganizer};
+use crate::entities::{message_segment, prelude::*};
+use crate::error::Error;
+use crate::messaging::AudiencePreview;
+use crate::messaging::audience::Audience;
+use crate::state::AppState;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// A saved, named audience that can be reused across messages.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Segment {
+    pub id: String,
+    pub name: String,
+    pub audience: Audience,
+    pub created_at: NaiveDateTime,
+}
+
+impl TryFrom<message_segment::Model> for Segment {
+    type Error = Error;
+
+    fn try_from(m: message_segment::Model) -> Result<Self, Error> {
+        Ok(Self {
+            audience: serde_json::from_value(m.audience)
+                .map_err(|e| anyhow::anyhow!("stored segment {} is malformed: {e}", m.id))?,
+            id: m.id,
+            name: m.name,
+            created_at: m.created_at,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateSegment {
+    pub name: String,
+    pub audience: Audience,
+}
+
+/// Loads a segment, requiring that it belongs to `hackathon_id`.
+pub(crate) async fn find_segment(
+    state: &AppState,
+    hackathon_id: &str,
+    segment_id: &str,
+) -> Result<message_segment::Model, Error> {
+    MessageSegment::find_by_id(segment_id)
+        .filter(message_segment::Column::HackathonId.eq(hackathon_id))
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("segment {segment_id}")))
+}
+
+#[utoipa::path(
+    get,
+    path = "/hackathons/{hackathon_id}/segments",
+    tag = "messaging",
+    params(("hackathon_id" = String, Path)),
+    responses((status = OK, body = Vec<Segment>)),
+)]
+pub async fn list_segments(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Segment>>, Error> {
+    let segments = MessageSegment::find()
+        .filter(message_segment::Column::HackathonId.eq(&hackathon.id))
+        .order_by_asc(message_segment::Column::Name)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(Segment::try_from)
+        .collect::<Result<_, _>>()?;
+    Ok(Json(segments))
+}
+
+#[utoipa::path(
+    post,
+    path = "/hackathons/{hackathon_id}/segments",
+    tag = "messaging",
+    params(("hackathon_id" = String, Path)),
+    request_body = CreateSegment,
+    responses((status = CREATED, body = Segment)),
+)]
+pub async fn create_segment(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<CreateSegment>,
+) -> Result<(StatusCode, Json<Segment>), Error> {
+    if body.name.trim().is_empty() {
+        return Err(Error::BadRequest("segment name must not be empty".into()));
+    }
+
+    let segment = message_segment::ActiveModel {
+        id: Set(uuid::Uuid::new_v4().to_string()),
+        hackathon_id: Set(hackathon.id),
+        name: Set(body.name),
+        audience: Set(serde_json::to_value(&body.audience).map_err(anyhow::Error::from)?),
+        created_at: Set(Utc::now().naive_utc()),
+    }
+    .insert(&state.db)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(segment.try_into()?)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/hackathons/{hackathon_id}/segments/{segment_id}",
+    tag = "messaging",
+    params(("hackathon_id" = String, Path), ("segment_id" = String, Path)),
+    responses((status = NO_CONTENT)),
+)]
+pub async fn delete_segment(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<(String, String)>,
+) -> Result<StatusCode, Error> {
+    let (_, segment_id) = path;
+    let segment = find_segment(&state, &hackathon.id, &segment_id).await?;
+    MessageSegment::delete_by_id(segment.id)
+        .exec(&state.db)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Counts the recipients a saved segment would reach right now.
+#[utoipa::path(
+    get,
+    path = "/hackathons/{hackathon_id}/segments/{segment_id}/preview",
+    tag = "messaging",
+    params(("hackathon_id" = String, Path), ("segment_id" = String, Path)),
+    responses((status = OK, body = AudiencePreview)),
+)]
+pub async fn preview_segment(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<(String, String)>,
+) -> Result<Json<AudiencePreview>, Error> {
+    let (_, segment_id) = path;
+    let segment: Segment = find_segment(&state, &hackathon.id, &segment_id)
+        .await?
+        .try_into()?;
+    let recipients = segment.audience.resolve(&state.db, &hackathon.id).await?;
+    Ok(Json(AudiencePreview::from(recipients.as_slice())))
+}
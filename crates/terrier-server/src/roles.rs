@@ -0,0 +1,189 @@
+//! Per-hackathon role assignments.
+//!
+//! A user's roles live in `user_hackathon_roles`, one row per role held in a
+//! given hackathon, and are what the policies in [`crate::auth::policies`]
+//! check against.
+
+use crate::auth::Auth;
+use crate::auth::policies::{IsHackathonOrganizer, RoleManager};
+use crate::entities::{prelude::*, sea_orm_active_enums::HackathonRole, user_hackathon_roles};
+use crate::error::Error;
+use crate::state::AppState;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, ModelTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, SqlErr, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use slac::secure;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+
+pub fn router() -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::new()
+        .routes(secure!(IsHackathonOrganizer; list_roles))
+        .routes(secure!(RoleManager; grant_role))
+        .routes(secure!(RoleManager; revoke_role))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RoleGrant {
+    pub user_id: String,
+    pub role: HackathonRole,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<user_hackathon_roles::Model> for RoleGrant {
+    fn from(m: user_hackathon_roles::Model) -> Self {
+        Self {
+            user_id: m.user_id,
+            role: m.role,
+            created_at: m.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GrantRole {
+    pub user_id: String,
+    pub role: HackathonRole,
+}
+
+#[utoipa::path(
+    get,
+    path = "/hackathons/{hackathon_id}/roles",
+    tag = "roles",
+    params(("hackathon_id" = String, Path)),
+    responses((status = OK, body = Vec<RoleGrant>)),
+)]
+pub async fn list_roles(
+    Auth {
+        data: (_, hackathon),
+        ..
+    }: Auth<IsHackathonOrganizer>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<RoleGrant>>, Error> {
+    let grants = UserHackathonRoles::find()
+        .filter(user_hackathon_roles::Column::HackathonId.eq(&hackathon.id))
+        .order_by_asc(user_hackathon_roles::Column::CreatedAt)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(RoleGrant::from)
+        .collect();
+    Ok(Json(grants))
+}
+
+/// Grants a role in this hackathon. Organizers may grant any role except
+/// admin, which only admins may grant.
+#[utoipa::path(
+    post,
+    path = "/hackathons/{hackathon_id}/roles",
+    tag = "roles",
+    params(("hackathon_id" = String, Path)),
+    request_body = GrantRole,
+    responses((status = CREATED, body = RoleGrant), (status = CONFLICT)),
+)]
+pub async fn grant_role(
+    Auth { data: manager, .. }: Auth<RoleManager>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<GrantRole>,
+) -> Result<(StatusCode, Json<RoleGrant>), Error> {
+    if !manager.may_manage(&body.role) {
+        return Err(Error::Forbidden);
+    }
+    let (_, hackathon) = manager.into_parts();
+
+    User::find_by_id(&body.user_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("user {}", body.user_id)))?;
+
+    // The unique index decides, so two concurrent grants of the same role
+    // cannot both pass a check made before the insert.
+    let user_id = body.user_id.clone();
+    let grant = user_hackathon_roles::ActiveModel {
+        id: Set(uuid::Uuid::new_v4().to_string()),
+        user_id: Set(body.user_id),
+        hackathon_id: Set(hackathon.id),
+        role: Set(body.role),
+        created_at: Set(Utc::now().naive_utc()),
+    }
+    .insert(&state.db)
+    .await
+    .map_err(|e| match e.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => {
+            Error::Conflict(format!("user {user_id} already holds that role"))
+        }
+        _ => e.into(),
+    })?;
+
+    Ok((StatusCode::CREATED, Json(grant.into())))
+}
+
+/// Revokes a role in this hackathon. The last admin cannot be removed, so a
+/// hackathon is never left without someone able to manage admins.
+#[utoipa::path(
+    delete,
+    path = "/hackathons/{hackathon_id}/roles/{user_id}/{role}",
+    tag = "roles",
+    params(
+        ("hackathon_id" = String, Path),
+        ("user_id" = String, Path),
+        ("role" = HackathonRole, Path),
+    ),
+    responses((status = NO_CONTENT), (status = CONFLICT)),
+)]
+pub async fn revoke_role(
+    Auth { data: manager, .. }: Auth<RoleManager>,
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<(String, String, HackathonRole)>,
+) -> Result<StatusCode, Error> {
+    let (_, user_id, role) = path;
+    if !manager.may_manage(&role) {
+        return Err(Error::Forbidden);
+    }
+    let (_, hackathon) = manager.into_parts();
+
+    let txn = state.db.begin().await?;
+    if role == HackathonRole::Admin {
+        // Locking every admin grant serializes concurrent revocations, so
+        // each counts the admins the one before it left behind.
+        let admins = UserHackathonRoles::find()
+            .filter(user_hackathon_roles::Column::HackathonId.eq(&hackathon.id))
+            .filter(user_hackathon_roles::Column::Role.eq(HackathonRole::Admin))
+            .lock_exclusive()
+            .all(&txn)
+            .await?;
+        if admins.len() <= 1 {
+            return Err(Error::Conflict(
+                "cannot revoke the last admin of a hackathon".into(),
+            ));
+        }
+    }
+
+    let grant = find_grant(&txn, &hackathon.id, &user_id, &role)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("user {user_id} does not hold that role")))?;
+    grant.delete(&txn).await?;
+    txn.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn find_grant<C: ConnectionTrait>(
+    db: &C,
+    hackathon_id: &str,
+    user_id: &str,
+    role: &HackathonRole,
+) -> Result<Option<user_hackathon_roles::Model>, Error> {
+    Ok(UserHackathonRoles::find()
+        .filter(user_hackathon_roles::Column::HackathonId.eq(hackathon_id))
+        .filter(user_hackathon_roles::Column::UserId.eq(user_id))
+        .filter(user_hackathon_roles::Column::Role.eq(role.clone()))
+        .one(db)
+        .await?)
+}
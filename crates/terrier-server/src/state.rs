@@ -0,0 +1,29 @@
+use crate::auth::oidc::TokenVerifier;
+use crate::config::Config;
+use crate::email::Mailer;
+use anyhow::{Context, Result};
+use sea_orm::{Database, DatabaseConnection};
+
+pub struct AppState {
+    pub config: Config,
+    pub db: DatabaseConnection,
+    pub mailer: Mailer,
+    pub tokens: TokenVerifier,
+}
+
+impl AppState {
+    pub async fn new(config: Config) -> Result<Self> {
+        let db = Database::connect(&config.database_url)
+            .await
+            .context("failed to connect to database")?;
+        let mailer = Mailer::new(&config.email)?;
+        let tokens = TokenVerifier::new(&config.oidc);
+
+        Ok(Self {
+            config,
+            db,
+            mailer,
+            tokens,
+        })
+    }
+}
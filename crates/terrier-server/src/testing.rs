@@ -0,0 +1,34 @@
+//! Fixtures shared by unit tests.
+
+use crate::entities::{hackathon, sea_orm_active_enums::HackathonPhase};
+use chrono::NaiveDate;
+
+/// A fully populated hackathon running February 5-7, 2027.
+pub(crate) fn hackathon() -> hackathon::Model {
+    hackathon::Model {
+        id: "h1".into(),
+        name: "TartanHacks".into(),
+        start_date: NaiveDate::from_ymd_opt(2027, 2, 5)
+            .unwrap()
+            .and_hms_opt(17, 0, 0)
+            .unwrap(),
+        end_date: NaiveDate::from_ymd_opt(2027, 2, 7)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap(),
+        location: "Pittsburgh, PA".into(),
+        slug: "tartanhacks".into(),
+        timezone: "America/New_York".into(),
+        description: None,
+        logo_url: None,
+        primary_color: None,
+        accent_color: None,
+        social_links: serde_json::json!([]),
+        phase: HackathonPhase::Applications,
+        phase_locked: false,
+        check_in_at: None,
+        hacking_at: None,
+        judging_at: None,
+        closed_at: None,
+    }
+}
@@ -50,6 +50,9 @@
     HOST = "127.0.0.1";
     PORT = "3000";
 
+    # Write outgoing email to ./mail as .eml files instead of sending it
+    EMAIL_TRANSPORT = "file";
+
     # saml-proxy configuration
     SAML_PROXY_BASE_URL = "http://localhost:8443";
     SAML_PROXY_ENTITY_ID = "https://saml-proxy.example.com";